@@ -0,0 +1,33 @@
+use std::hint::black_box;
+use std::time::Instant;
+use pictropy::{calculate_entropy, calculate_symbol_entropy};
+
+const SAMPLE_BYTES: usize = 16 * 1024 * 1024;
+const ROUNDS: u32 = 5;
+
+/// Times the entropy function over the data, returning the mean seconds per round.
+fn time(entropy: impl Fn(&[u8]) -> f64, data: &[u8]) -> f64 {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        black_box(entropy(black_box(data)));
+    }
+    start.elapsed().as_secs_f64() / ROUNDS as f64
+}
+
+fn main() {
+    let mut state: u32 = 0x1234_5678;
+    let data: Vec<u8> = (0..SAMPLE_BYTES)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 24) as u8
+        })
+        .collect();
+
+    let hashmap_seconds = time(calculate_symbol_entropy, &data);
+    let array_seconds = time(calculate_entropy, &data);
+
+    println!("Entropy of {} MiB of bytes, mean of {} rounds:", SAMPLE_BYTES / (1024 * 1024), ROUNDS);
+    println!("HashMap histogram: {:.2} ms", hashmap_seconds * 1000.0);
+    println!("Array histogram:   {:.2} ms", array_seconds * 1000.0);
+    println!("Speedup: {:.1}x", hashmap_seconds / array_seconds);
+}
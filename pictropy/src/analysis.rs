@@ -0,0 +1,211 @@
+use image::{DynamicImage, GenericImageView};
+use serde::Serialize;
+use crate::{
+    calculate_entropy, calculate_smoothed_entropy, count_transparent_pixels, ppm_compress, split_assumed_channels,
+    split_opaque_rgb_channels, split_rgb_channels, subsample, qoi_compress, webp_compress, ChannelOrder, Compressor, PpmWarmup,
+    RgbChannels, Smoothing,
+};
+
+/// Settings for the full analysis pipeline run by `analyze`.
+#[derive(Clone, Debug, Serialize)]
+pub struct AnalysisOptions {
+    pub channel_order: ChannelOrder,
+    /// Interpret the buffer as this many interleaved channels instead of its color type
+    pub assume_channels: Option<usize>,
+    /// Leave fully transparent pixels out of the histograms
+    pub ignore_transparent: bool,
+    pub smoothing: Smoothing,
+    /// Pseudocount for `Smoothing::Laplace`
+    pub smoothing_k: f64,
+    /// Analyze every Nth pixel; compressed sizes are scaled back up
+    pub sample_rate: usize,
+    pub compressor: Compressor,
+    pub ppm_warmup: PpmWarmup,
+    /// Only run the compressor, without measuring entropy
+    pub skip_entropy: bool,
+    /// Only measure entropy, without running any compressor
+    pub entropy_only: bool,
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        AnalysisOptions {
+            channel_order: ChannelOrder::Rgb,
+            assume_channels: None,
+            ignore_transparent: false,
+            smoothing: Smoothing::None,
+            smoothing_k: 1.0,
+            sample_rate: 1,
+            compressor: Compressor::Ppm,
+            ppm_warmup: PpmWarmup::Backoff,
+            skip_entropy: false,
+            entropy_only: false,
+        }
+    }
+}
+
+/// Output sizes of the selected real compressor.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompressedSizes {
+    /// One PPM estimate per color channel, in red, green, blue order
+    PerChannel([usize; 3]),
+    /// A single WebP or QOI size for the whole image
+    Whole(usize),
+}
+
+impl CompressedSizes {
+    pub fn total(&self) -> usize {
+        self.as_slice().iter().sum()
+    }
+
+    pub fn as_slice(&self) -> &[usize] {
+        match self {
+            CompressedSizes::PerChannel(sizes) => sizes,
+            CompressedSizes::Whole(size) => std::slice::from_ref(size),
+        }
+    }
+
+    pub fn from_slice(sizes: &[usize]) -> Option<Self> {
+        match *sizes {
+            [red, green, blue] => Some(CompressedSizes::PerChannel([red, green, blue])),
+            [whole] => Some(CompressedSizes::Whole(whole)),
+            _ => None,
+        }
+    }
+}
+
+/// Every metric produced by `analyze`.
+#[derive(Clone, Debug, Serialize)]
+pub struct AnalysisResult {
+    pub width: u32,
+    pub height: u32,
+    /// Pixels left out with `ignore_transparent`
+    pub transparent_pixels: usize,
+    /// Pixels the theoretical minimum is based on
+    pub analyzed_pixels: usize,
+    /// Red, green and blue entropy in bits per pixel, absent with `skip_entropy`
+    pub channel_entropy: Option<[f64; 3]>,
+    pub total_entropy: Option<f64>,
+    /// Lossless size limit in bytes implied by the entropy, before any comparison with a file size
+    pub theoretical_minimum_size: Option<f64>,
+    /// Absent with `entropy_only`
+    pub compressed_sizes: Option<CompressedSizes>,
+    pub compressed_size: Option<usize>,
+}
+
+impl AnalysisResult {
+    /// Assembles the result from measured entropy and compressed sizes.
+    pub fn new(
+        img: &DynamicImage,
+        options: &AnalysisOptions,
+        channel_entropy: Option<[f64; 3]>,
+        compressed_sizes: Option<CompressedSizes>,
+    ) -> Self {
+        let (width, height) = img.dimensions();
+        // Transparent pixels carry no visible color, so they don't count towards the limit
+        let transparent_pixels = if options.ignore_transparent { count_transparent_pixels(img) } else { 0 };
+        // Assumed channels regroup the buffer, so it holds a different number of pixels
+        let analyzed_pixels = match options.assume_channels {
+            Some(channels) => img.as_bytes().len() / channels,
+            None => (width * height) as usize - transparent_pixels,
+        };
+        let total_entropy = channel_entropy.map(|entropy| entropy.iter().sum::<f64>());
+
+        AnalysisResult {
+            width,
+            height,
+            transparent_pixels,
+            analyzed_pixels,
+            channel_entropy,
+            total_entropy,
+            theoretical_minimum_size: total_entropy.map(|entropy| entropy * analyzed_pixels as f64 / 8.0),
+            compressed_size: compressed_sizes.as_ref().map(CompressedSizes::total),
+            compressed_sizes,
+        }
+    }
+}
+
+/// Rejects images without pixels, which have no distribution to measure.
+pub fn check_dimensions(img: &DynamicImage) -> Result<(), String> {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return Err(format!("Error: The image is {}x{} and has no pixels to analyze.", width, height));
+    }
+    Ok(())
+}
+
+/// Whether the image is a single row or column, which has no 2D neighborhoods for the spatial modes.
+pub fn is_strip(img: &DynamicImage) -> bool {
+    let (width, height) = img.dimensions();
+    width == 1 || height == 1
+}
+
+/// Runs the whole pipeline: channel split, entropy, theoretical limit and compression.
+pub fn analyze(img: &DynamicImage, options: &AnalysisOptions) -> Result<AnalysisResult, String> {
+    let (channel_entropy, compressed_sizes) = measure(img, options)?;
+    Ok(AnalysisResult::new(img, options, channel_entropy, compressed_sizes))
+}
+
+/// Separates the color channels as the options ask, keeping a regular 1-in-R sample of the pixel values.
+pub fn split_channels(img: &DynamicImage, options: &AnalysisOptions) -> Result<RgbChannels, String> {
+    let (red_channel, green_channel, blue_channel) = match options.assume_channels {
+        Some(channels) => split_assumed_channels(img, channels, options.channel_order)?,
+        None if options.ignore_transparent => split_opaque_rgb_channels(img, options.channel_order),
+        None => split_rgb_channels(img, options.channel_order),
+    };
+
+    let sample_rate = options.sample_rate;
+    Ok(if sample_rate > 1 {
+        (
+            subsample(&red_channel, sample_rate),
+            subsample(&green_channel, sample_rate),
+            subsample(&blue_channel, sample_rate),
+        )
+    } else {
+        (red_channel, green_channel, blue_channel)
+    })
+}
+
+/// The expensive part of `analyze`: channel entropy and the selected compressor's output size.
+pub fn measure(
+    img: &DynamicImage,
+    options: &AnalysisOptions,
+) -> Result<(Option<[f64; 3]>, Option<CompressedSizes>), String> {
+    check_dimensions(img)?;
+
+    // Separate data into color channels, unless a whole-image size is all that's wanted
+    let sample_rate = options.sample_rate;
+    let channels = if options.skip_entropy && options.compressor != Compressor::Ppm {
+        None
+    } else {
+        Some(split_channels(img, options)?)
+    };
+
+    // Calculate entropies
+    let entropy = |channel: &[u8]| match options.smoothing {
+        Smoothing::None => calculate_entropy(channel),
+        Smoothing::Laplace => calculate_smoothed_entropy(channel, options.smoothing_k),
+    };
+    let channel_entropy = match &channels {
+        Some((red_channel, green_channel, blue_channel)) if !options.skip_entropy => {
+            Some([entropy(red_channel), entropy(green_channel), entropy(blue_channel)])
+        },
+        _ => None,
+    };
+
+    let compressed_sizes = match (options.compressor, &channels) {
+        _ if options.entropy_only => None,
+        // Compress entropy results using PPM
+        // Compressed sizes of a sample are scaled back up to the full image
+        (Compressor::Ppm, Some((red_channel, green_channel, blue_channel))) => Some(CompressedSizes::PerChannel([
+            ppm_compress(red_channel, options.ppm_warmup) * sample_rate,
+            ppm_compress(green_channel, options.ppm_warmup) * sample_rate,
+            ppm_compress(blue_channel, options.ppm_warmup) * sample_rate,
+        ])),
+        (Compressor::Qoi, _) => Some(CompressedSizes::Whole(qoi_compress(img))),
+        _ => Some(CompressedSizes::Whole(webp_compress(img)?)),
+    };
+
+    Ok((channel_entropy, compressed_sizes))
+}
@@ -0,0 +1,130 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use ::png::{BlendOp, ColorType, Decoder, DisposeOp, Transformations};
+use image::{DynamicImage, GrayAlphaImage, GrayImage, RgbImage, RgbaImage};
+use crate::{calculate_entropy, split_rgb_channels, ChannelOrder, RgbChannels};
+
+/// Entropy of one composited animation frame.
+pub struct ApngFrame {
+    /// Region the frame's own data covers, as width, height, x and y offset
+    pub region: (u32, u32, u32, u32),
+    /// Summed channel entropy of the full composited canvas, in bits per pixel
+    pub entropy: f64,
+    /// Summed channel entropy of the byte-wise difference from the previous canvas; None for the first frame
+    pub difference_entropy: Option<f64>,
+}
+
+/// Decodes every frame of an animated PNG, composites it onto the canvas as the APNG blend and
+/// dispose operations say, and measures the canvas and its difference from the previous one.
+/// A default image that isn't part of the animation is skipped.
+pub fn frame_entropy(path: &Path, order: ChannelOrder) -> Result<Vec<ApngFrame>, String> {
+    let invalid = |reason: &dyn std::fmt::Display| format!("Error: Unable to decode the APNG '{}': {}", path.display(), reason);
+    let file = File::open(path).map_err(|err| invalid(&err))?;
+    let mut decoder = Decoder::new(BufReader::new(file));
+    decoder.set_transformations(Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|err| invalid(&err))?;
+
+    let info = reader.info();
+    let Some(animation) = info.animation_control else {
+        return Err(format!("Error: '{}' is not an animated PNG (it has no acTL chunk).", path.display()));
+    };
+    let (width, height) = (info.width, info.height);
+    let hidden_default_image = info.frame_control.is_none();
+    let frame_count = animation.num_frames as usize + hidden_default_image as usize;
+
+    let mut buffer = vec![0; reader.output_buffer_size().ok_or_else(|| invalid(&"the frames are too large"))?];
+    let mut canvas = RgbaImage::new(width, height);
+    let mut previous_channels: Option<RgbChannels> = None;
+    let mut frames = Vec::new();
+    for index in 0..frame_count {
+        let output = reader.next_frame(&mut buffer).map_err(|err| invalid(&err))?;
+        if index == 0 && hidden_default_image {
+            continue;
+        }
+        let control = reader.info().frame_control.ok_or_else(|| invalid(&"a frame has no fcTL chunk"))?;
+        let pixels = subframe_to_rgba(&buffer[..output.buffer_size()], output.width, output.height, output.color_type)
+            .ok_or_else(|| invalid(&"a frame is smaller than its fcTL says"))?;
+
+        // "Previous" restores the canvas as it was before this frame; on the first frame it clears
+        let restore = match control.dispose_op {
+            DisposeOp::Previous if !frames.is_empty() => Some(canvas.clone()),
+            _ => None,
+        };
+        for (x, y, pixel) in pixels.enumerate_pixels() {
+            let (canvas_x, canvas_y) = (x + control.x_offset, y + control.y_offset);
+            if canvas_x >= width || canvas_y >= height {
+                continue;
+            }
+            let target = canvas.get_pixel_mut(canvas_x, canvas_y);
+            *target = match control.blend_op {
+                BlendOp::Source => *pixel,
+                BlendOp::Over => blend_over(*pixel, *target),
+            };
+        }
+
+        let channels = split_rgb_channels(&DynamicImage::ImageRgba8(canvas.clone()), order);
+        let difference_entropy = previous_channels.as_ref().map(|previous| {
+            channel_entropy_sum(&(
+                difference(&previous.0, &channels.0),
+                difference(&previous.1, &channels.1),
+                difference(&previous.2, &channels.2),
+            ))
+        });
+        frames.push(ApngFrame {
+            region: (control.width, control.height, control.x_offset, control.y_offset),
+            entropy: channel_entropy_sum(&channels),
+            difference_entropy,
+        });
+        previous_channels = Some(channels);
+
+        match (control.dispose_op, restore) {
+            (DisposeOp::None, _) => {},
+            (DisposeOp::Previous, Some(restore)) => canvas = restore,
+            _ => {
+                for y in control.y_offset..(control.y_offset + control.height).min(height) {
+                    for x in control.x_offset..(control.x_offset + control.width).min(width) {
+                        canvas.put_pixel(x, y, image::Rgba([0, 0, 0, 0]));
+                    }
+                }
+            },
+        }
+    }
+    Ok(frames)
+}
+
+/// Converts a decoded 8-bit subframe to RGBA.
+fn subframe_to_rgba(data: &[u8], width: u32, height: u32, color_type: ColorType) -> Option<RgbaImage> {
+    let data = data.to_vec();
+    let image = match color_type {
+        ColorType::Grayscale => DynamicImage::ImageLuma8(GrayImage::from_raw(width, height, data)?),
+        ColorType::GrayscaleAlpha => DynamicImage::ImageLumaA8(GrayAlphaImage::from_raw(width, height, data)?),
+        ColorType::Rgb => DynamicImage::ImageRgb8(RgbImage::from_raw(width, height, data)?),
+        ColorType::Rgba => DynamicImage::ImageRgba8(RgbaImage::from_raw(width, height, data)?),
+        ColorType::Indexed => return None, // Expanded by normalize_to_color8
+    };
+    Some(image.to_rgba8())
+}
+
+/// Composites a straight-alpha source pixel over the destination (APNG_BLEND_OP_OVER).
+fn blend_over(source: image::Rgba<u8>, destination: image::Rgba<u8>) -> image::Rgba<u8> {
+    let source_alpha = source[3] as f64 / 255.0;
+    let destination_alpha = destination[3] as f64 / 255.0 * (1.0 - source_alpha);
+    let alpha = source_alpha + destination_alpha;
+    if alpha == 0.0 {
+        return image::Rgba([0, 0, 0, 0]);
+    }
+    let mix = |channel: usize| {
+        ((source[channel] as f64 * source_alpha + destination[channel] as f64 * destination_alpha) / alpha).round() as u8
+    };
+    image::Rgba([mix(0), mix(1), mix(2), (alpha * 255.0).round() as u8])
+}
+
+/// Byte-wise difference, wrapping modulo 256 like a PNG Up filter across frames.
+fn difference(previous: &[u8], current: &[u8]) -> Vec<u8> {
+    previous.iter().zip(current).map(|(&before, &after)| after.wrapping_sub(before)).collect()
+}
+
+fn channel_entropy_sum((red, green, blue): &RgbChannels) -> f64 {
+    calculate_entropy(red) + calculate_entropy(green) + calculate_entropy(blue)
+}
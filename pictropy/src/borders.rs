@@ -0,0 +1,62 @@
+use image::{DynamicImage, GenericImageView, Rgba};
+
+/// Widths of the uniform margins on each edge of an image, in pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Borders {
+    pub top: u32,
+    pub bottom: u32,
+    pub left: u32,
+    pub right: u32,
+}
+
+impl Borders {
+    /// Whether no edge has a uniform margin.
+    pub fn is_empty(&self) -> bool {
+        *self == Borders::default()
+    }
+
+    /// Number of pixels inside the margins.
+    pub fn border_pixels(&self, width: u32, height: u32) -> u64 {
+        let content = (width - self.left - self.right) as u64 * (height - self.top - self.bottom) as u64;
+        width as u64 * height as u64 - content
+    }
+}
+
+/// Finds the uniform margins by scanning inward from each edge until a row or column holds a
+/// color other than the edge's own. Top and bottom are found first; left and right only span the
+/// rows between them. An image made only of uniform bands has no content to frame, so it reports
+/// no borders.
+pub fn detect_borders(img: &DynamicImage) -> Borders {
+    let pixels = img.to_rgba8();
+    let (width, height) = pixels.dimensions();
+    let row_is = |y: u32, color: Rgba<u8>| (0..width).all(|x| *pixels.get_pixel(x, y) == color);
+    let top_color = *pixels.get_pixel(0, 0);
+    let top = (0..height).take_while(|&y| row_is(y, top_color)).count() as u32;
+    let bottom_color = *pixels.get_pixel(0, height - 1);
+    let bottom = (top..height).rev().take_while(|&y| row_is(y, bottom_color)).count() as u32;
+    if top + bottom == height {
+        return Borders::default();
+    }
+
+    let rows = top..height - bottom;
+    let column_is = |x: u32, color: Rgba<u8>| rows.clone().all(|y| *pixels.get_pixel(x, y) == color);
+    let left_color = *pixels.get_pixel(0, top);
+    let left = (0..width).take_while(|&x| column_is(x, left_color)).count() as u32;
+    let right_color = *pixels.get_pixel(width - 1, top);
+    let right = (left..width).rev().take_while(|&x| column_is(x, right_color)).count() as u32;
+    if left + right == width {
+        return Borders::default();
+    }
+    Borders { top, bottom, left, right }
+}
+
+/// Crops the margins off the image.
+pub fn trim_borders(img: &DynamicImage, borders: &Borders) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    img.crop_imm(
+        borders.left,
+        borders.top,
+        width - borders.left - borders.right,
+        height - borders.top - borders.bottom,
+    )
+}
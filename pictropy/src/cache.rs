@@ -0,0 +1,65 @@
+use std::fs;
+use std::path::Path;
+
+/// Results of the expensive analysis steps, as stored in the cache.
+pub struct CachedAnalysis {
+    /// Red, green and blue entropy, absent when entropy was skipped
+    pub channel_entropy: Option<[f64; 3]>,
+    pub compressed_sizes: Vec<usize>,
+}
+
+/// BLAKE3 hash of the file content, which identifies the file regardless of its name.
+pub fn content_hash(data: &[u8]) -> blake3::Hash {
+    blake3::hash(data)
+}
+
+/// Derives a cache key from the content hash and a description of the analysis parameters.
+pub fn cache_key(content_hash: &blake3::Hash, parameters: &str) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(content_hash.as_bytes());
+    hasher.update(parameters.as_bytes());
+    hasher.finalize().to_hex().to_string()
+}
+
+/// Looks up a cached analysis. Missing or unreadable entries are treated as misses.
+pub fn load(cache_dir: &Path, key: &str) -> Option<CachedAnalysis> {
+    let contents = fs::read_to_string(cache_dir.join(key)).ok()?;
+    let mut channel_entropy = None;
+    let mut compressed_sizes = None;
+
+    for line in contents.lines() {
+        let (field, values) = line.split_once(' ').unwrap_or((line, ""));
+        match field {
+            "entropy" => {
+                let entropy: Vec<f64> = values.split_whitespace().map(str::parse).collect::<Result<_, _>>().ok()?;
+                channel_entropy = Some(entropy.try_into().ok()?);
+            },
+            "compressed" => {
+                compressed_sizes = Some(values.split_whitespace().map(str::parse).collect::<Result<_, _>>().ok()?);
+            },
+            _ => return None,
+        }
+    }
+
+    Some(CachedAnalysis { channel_entropy, compressed_sizes: compressed_sizes? })
+}
+
+/// Stores an analysis under the key, creating the cache directory if needed.
+pub fn store(cache_dir: &Path, key: &str, analysis: &CachedAnalysis) -> Result<(), String> {
+    let to_message =
+        |err: std::io::Error| format!("Error: Unable to write to the cache '{}': {}", cache_dir.display(), err);
+
+    let mut contents = String::new();
+    if let Some([red_entropy, green_entropy, blue_entropy]) = analysis.channel_entropy {
+        contents += &format!("entropy {} {} {}\n", red_entropy, green_entropy, blue_entropy);
+    }
+    let sizes: Vec<String> = analysis.compressed_sizes.iter().map(usize::to_string).collect();
+    contents += &format!("compressed {}\n", sizes.join(" "));
+
+    fs::create_dir_all(cache_dir).map_err(to_message)?;
+    // Write then rename, so a concurrent run never reads a half-written entry
+    let entry = cache_dir.join(key);
+    let partial = entry.with_extension("partial");
+    fs::write(&partial, contents).map_err(to_message)?;
+    fs::rename(&partial, &entry).map_err(to_message)
+}
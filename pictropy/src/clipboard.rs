@@ -0,0 +1,14 @@
+use arboard::Clipboard;
+use image::{DynamicImage, RgbaImage};
+
+/// Reads the image currently on the system clipboard as RGBA.
+pub fn read_clipboard_image() -> Result<DynamicImage, String> {
+    let mut clipboard = Clipboard::new().map_err(|err| format!("Error: Unable to open the clipboard: {}", err))?;
+    let data = clipboard.get_image().map_err(|err| match err {
+        arboard::Error::ContentNotAvailable => String::from("Error: The clipboard does not hold an image."),
+        err => format!("Error: Unable to read the clipboard image: {}", err),
+    })?;
+    RgbaImage::from_raw(data.width as u32, data.height as u32, data.bytes.into_owned())
+        .map(DynamicImage::ImageRgba8)
+        .ok_or_else(|| String::from("Error: The clipboard image has fewer bytes than its dimensions require."))
+}
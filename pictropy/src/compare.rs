@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use serde::Deserialize;
+
+/// The figures of one file that a later run is compared against.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ReportEntry {
+    pub original_size: u64,
+    /// Absent when entropy was skipped
+    pub total_entropy: Option<f64>,
+}
+
+/// Entry of a report's "files" array; failed files carry only an error.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FileEntry {
+    Analyzed {
+        file: String,
+        #[serde(flatten)]
+        entry: ReportEntry,
+    },
+    Failed {
+        #[allow(dead_code)]
+        error: String,
+    },
+}
+
+/// A `--format json` document: a directory's batch, or a single file's object.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ReportDocument {
+    Batch { files: Vec<FileEntry> },
+    Single(FileEntry),
+}
+
+/// Differences between two reports, with files matched by path.
+#[derive(Debug, Default)]
+pub struct ReportDiff {
+    /// Files whose size or entropy differ: the path, the previous and the current entry
+    pub changed: Vec<(String, ReportEntry, ReportEntry)>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// Loads the analyzed files of a `--format json` report, keyed by path. Failed files are left out.
+pub fn load_report(path: &Path) -> Result<BTreeMap<String, ReportEntry>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("Error: Unable to read the report '{}': {}", path.display(), err))?;
+    let document: ReportDocument = serde_json::from_str(&contents)
+        .map_err(|err| format!("Error: '{}' is not a pictropy JSON report: {}", path.display(), err))?;
+
+    let entries = match document {
+        ReportDocument::Batch { files } => files,
+        ReportDocument::Single(entry) => vec![entry],
+    };
+    Ok(entries
+        .into_iter()
+        .filter_map(|entry| match entry {
+            FileEntry::Analyzed { file, entry } => Some((file, entry)),
+            FileEntry::Failed { .. } => None,
+        })
+        .collect())
+}
+
+/// Matches files by path and collects those that changed, appeared or disappeared, in path order.
+pub fn diff_reports(previous: &BTreeMap<String, ReportEntry>, current: &BTreeMap<String, ReportEntry>) -> ReportDiff {
+    let mut diff = ReportDiff::default();
+    for (file, current_entry) in current {
+        match previous.get(file) {
+            Some(previous_entry) if previous_entry != current_entry => {
+                diff.changed.push((file.clone(), previous_entry.clone(), current_entry.clone()));
+            },
+            Some(_) => {},
+            None => diff.added.push(file.clone()),
+        }
+    }
+    diff.removed = previous.keys().filter(|file| !current.contains_key(*file)).cloned().collect();
+    diff
+}
@@ -0,0 +1,32 @@
+use clap::ValueEnum;
+use image::DynamicImage;
+use imageproc::filter::{gaussian_blur_f32, median_filter};
+
+/// Noise-suppressing filters applied before a second entropy measurement.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DenoiseFilter {
+    /// 3x3 median filter; removes impulse noise while keeping edges
+    Median,
+    /// Gaussian blur with a sigma of one pixel
+    Gaussian,
+}
+
+impl DenoiseFilter {
+    /// Display name of the filter.
+    pub fn name(self) -> &'static str {
+        match self {
+            DenoiseFilter::Median => "Median",
+            DenoiseFilter::Gaussian => "Gaussian",
+        }
+    }
+}
+
+/// Applies the filter to the RGB data of the image. Alpha is dropped.
+pub fn denoise(image: &DynamicImage, filter: DenoiseFilter) -> DynamicImage {
+    let rgb_image = image.to_rgb8();
+    let filtered = match filter {
+        DenoiseFilter::Median => median_filter(&rgb_image, 1, 1),
+        DenoiseFilter::Gaussian => gaussian_blur_f32(&rgb_image, 1.0),
+    };
+    DynamicImage::ImageRgb8(filtered)
+}
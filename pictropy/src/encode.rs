@@ -0,0 +1,184 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use clap::ValueEnum;
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::{DynamicImage, GenericImageView, GrayAlphaImage, GrayImage, ImageOutputFormat, Luma, LumaA, RgbaImage};
+
+/// Formats the analyzed image can be written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum EncodeFormat {
+    /// PNG at maximum compression
+    Png,
+    /// Baseline JPEG at the chosen quality
+    Jpeg,
+    /// Lossless WebP
+    Webp,
+}
+
+impl EncodeFormat {
+    /// Picks the format matching a file extension, if any.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "png" => Some(EncodeFormat::Png),
+            "jpg" | "jpeg" => Some(EncodeFormat::Jpeg),
+            "webp" => Some(EncodeFormat::Webp),
+            _ => None,
+        }
+    }
+
+    /// Display name of the format.
+    pub fn name(self) -> &'static str {
+        match self {
+            EncodeFormat::Png => "PNG",
+            EncodeFormat::Jpeg => "JPEG",
+            EncodeFormat::Webp => "WebP",
+        }
+    }
+}
+
+/// The image's samples in PNG byte order. `as_bytes` gives 16-bit samples in native byte order,
+/// but PNG stores them big-endian.
+fn png_samples(image: &DynamicImage) -> Cow<'_, [u8]> {
+    let wide: &[u16] = match image {
+        DynamicImage::ImageLuma16(buffer) => buffer,
+        DynamicImage::ImageLumaA16(buffer) => buffer,
+        DynamicImage::ImageRgb16(buffer) => buffer,
+        DynamicImage::ImageRgba16(buffer) => buffer,
+        _ => return Cow::Borrowed(image.as_bytes()),
+    };
+    Cow::Owned(wide.iter().flat_map(|sample| sample.to_be_bytes()).collect())
+}
+
+/// Encodes the image as an optimized PNG: best DEFLATE level with the Paeth filter.
+pub fn encode_png_best(image: &DynamicImage) -> Result<Vec<u8>, String> {
+    let (width, height) = image.dimensions();
+    let mut data = Vec::new();
+    PngEncoder::new_with_quality(&mut data, CompressionType::Best, FilterType::Paeth)
+        .encode(&png_samples(image), width, height, image.color())
+        .map_err(|err| format!("Error: PNG encoding failed: {}", err))?;
+    Ok(data)
+}
+
+/// Encodes the image as PNG with each scanline filter at the best DEFLATE level and keeps the
+/// smallest result, a brute-force take on what PNG optimizers do.
+pub fn encode_png_smallest(image: &DynamicImage) -> Result<Vec<u8>, String> {
+    let (width, height) = image.dimensions();
+    let samples = png_samples(image);
+    let mut smallest: Option<Vec<u8>> = None;
+    for filter in [FilterType::NoFilter, FilterType::Sub, FilterType::Up, FilterType::Avg, FilterType::Paeth] {
+        let mut data = Vec::new();
+        PngEncoder::new_with_quality(&mut data, CompressionType::Best, filter)
+            .encode(&samples, width, height, image.color())
+            .map_err(|err| format!("Error: PNG encoding failed: {}", err))?;
+        if smallest.as_ref().is_none_or(|best| data.len() < best.len()) {
+            smallest = Some(data);
+        }
+    }
+    Ok(smallest.unwrap_or_default())
+}
+
+/// The PNG color types, from the fewest bytes per pixel to the most.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PngColorType {
+    Indexed,
+    Grayscale,
+    GrayscaleAlpha,
+    Truecolor,
+    TruecolorAlpha,
+}
+
+impl PngColorType {
+    /// Display name of the color type.
+    pub fn name(self) -> &'static str {
+        match self {
+            PngColorType::Indexed => "Indexed",
+            PngColorType::Grayscale => "Grayscale",
+            PngColorType::GrayscaleAlpha => "Grayscale + Alpha",
+            PngColorType::Truecolor => "Truecolor",
+            PngColorType::TruecolorAlpha => "Truecolor + Alpha",
+        }
+    }
+}
+
+/// Encodes the image as an 8-bit PNG in every color type that holds its pixels exactly, each at the
+/// best DEFLATE level with the Paeth filter. Grayscale types need every pixel to be gray, types
+/// without alpha need it fully opaque, and indexed needs at most 256 distinct colors. The `image`
+/// encoder can't write palettes, so indexed goes through the `png` crate with any transparency in
+/// a tRNS chunk. 16-bit images are compared at 8 bits per sample.
+pub fn encode_png_color_types(image: &DynamicImage) -> Result<Vec<(PngColorType, Vec<u8>)>, String> {
+    let rgba = image.to_rgba8();
+    let opaque = rgba.pixels().all(|pixel| pixel[3] == u8::MAX);
+    let gray = rgba.pixels().all(|pixel| pixel[0] == pixel[1] && pixel[1] == pixel[2]);
+    let (width, height) = rgba.dimensions();
+
+    let mut variants = Vec::new();
+    if let Some(data) = encode_png_indexed(&rgba)? {
+        variants.push((PngColorType::Indexed, data));
+    }
+    if gray && opaque {
+        let luma = GrayImage::from_fn(width, height, |x, y| Luma([rgba.get_pixel(x, y)[0]]));
+        variants.push((PngColorType::Grayscale, encode_png_best(&DynamicImage::ImageLuma8(luma))?));
+    }
+    if gray {
+        let luma_alpha = GrayAlphaImage::from_fn(width, height, |x, y| {
+            let pixel = rgba.get_pixel(x, y);
+            LumaA([pixel[0], pixel[3]])
+        });
+        variants.push((PngColorType::GrayscaleAlpha, encode_png_best(&DynamicImage::ImageLumaA8(luma_alpha))?));
+    }
+    if opaque {
+        variants.push((PngColorType::Truecolor, encode_png_best(&DynamicImage::ImageRgb8(image.to_rgb8()))?));
+    }
+    variants.push((PngColorType::TruecolorAlpha, encode_png_best(&DynamicImage::ImageRgba8(rgba))?));
+    Ok(variants)
+}
+
+/// Encodes the pixels as an 8-bit indexed PNG, or returns None when they hold more than 256 colors.
+fn encode_png_indexed(rgba: &RgbaImage) -> Result<Option<Vec<u8>>, String> {
+    let mut palette = Vec::new();
+    let mut palette_indices = HashMap::new();
+    let mut indices = Vec::with_capacity(rgba.width() as usize * rgba.height() as usize);
+    for pixel in rgba.pixels() {
+        let index = match palette_indices.get(pixel) {
+            Some(&index) => index,
+            None if palette.len() == 256 => return Ok(None),
+            None => {
+                palette.push(*pixel);
+                palette_indices.insert(*pixel, (palette.len() - 1) as u8);
+                (palette.len() - 1) as u8
+            },
+        };
+        indices.push(index);
+    }
+
+    let invalid = |err: ::png::EncodingError| format!("Error: PNG encoding failed: {}", err);
+    let mut data = Vec::new();
+    let mut encoder = ::png::Encoder::new(&mut data, rgba.width(), rgba.height());
+    encoder.set_color(::png::ColorType::Indexed);
+    encoder.set_depth(::png::BitDepth::Eight);
+    encoder.set_palette(palette.iter().flat_map(|color| [color[0], color[1], color[2]]).collect::<Vec<u8>>());
+    if palette.iter().any(|color| color[3] < u8::MAX) {
+        encoder.set_trns(palette.iter().map(|color| color[3]).collect::<Vec<u8>>());
+    }
+    encoder.set_compression(::png::Compression::High);
+    encoder.set_filter(::png::Filter::Paeth);
+    let mut writer = encoder.write_header().map_err(invalid)?;
+    writer.write_image_data(&indices).map_err(invalid)?;
+    writer.finish().map_err(invalid)?;
+    Ok(Some(data))
+}
+
+/// Encodes the image in the given format; `quality` (1-100) only affects JPEG.
+pub fn encode_image(image: &DynamicImage, format: EncodeFormat, quality: u8) -> Result<Vec<u8>, String> {
+    match format {
+        EncodeFormat::Png => encode_png_best(image),
+        EncodeFormat::Jpeg => {
+            let mut data = Vec::new();
+            DynamicImage::ImageRgb8(image.to_rgb8())
+                .write_to(&mut data, ImageOutputFormat::Jpeg(quality))
+                .map_err(|err| format!("Error: JPEG encoding failed: {}", err))?;
+            Ok(data)
+        },
+        EncodeFormat::Webp => crate::webp_encode_lossless(image),
+    }
+}
@@ -0,0 +1,93 @@
+use std::fs;
+use std::path::Path;
+use image::{DynamicImage, RgbImage};
+
+/// Side length of every synthetic fixture, in pixels.
+pub const FIXTURE_SIZE: u32 = 64;
+
+/// A synthetic test image and the channel entropy it was built to have.
+pub struct Fixture {
+    /// File name the image is written under
+    pub name: &'static str,
+    pub image: DynamicImage,
+    /// Exact red, green and blue entropy in bits per pixel
+    pub channel_entropy: [f64; 3],
+}
+
+/// Builds the synthetic fixtures. All are 64x64 RGB and fully deterministic:
+///
+/// - `solid.png`: one color everywhere, so every channel has 0 bits.
+/// - `gradient.png`: red steps with x and green with y through 64 evenly used values (6 bits
+///   each); blue is constant (0 bits).
+/// - `checkerboard.png`: 8x8 black and white squares, two equally common values per channel
+///   (1 bit each).
+/// - `noise.png`: each channel is a shuffle of all 256 values, each used exactly 16 times (8 bits
+///   each), with no spatial structure for a compressor to find.
+/// - `two_color.png`: the left three quarters red, the rest blue. Red and blue split 3:1 between
+///   255 and 0, which is H(1/4) = 0.8113 bits; green is always 0 (0 bits).
+pub fn synthetic_fixtures() -> Vec<Fixture> {
+    let size = FIXTURE_SIZE;
+    let two_color_entropy = -(0.25f64 * 0.25f64.log2() + 0.75 * 0.75f64.log2());
+    let noise = [shuffled_bytes(1), shuffled_bytes(2), shuffled_bytes(3)];
+    vec![
+        Fixture {
+            name: "solid.png",
+            image: rgb_image(|_, _| [200, 120, 40]),
+            channel_entropy: [0.0, 0.0, 0.0],
+        },
+        Fixture {
+            name: "gradient.png",
+            image: rgb_image(|x, y| [(x * 4) as u8, (y * 4) as u8, 128]),
+            channel_entropy: [6.0, 6.0, 0.0],
+        },
+        Fixture {
+            name: "checkerboard.png",
+            image: rgb_image(|x, y| if (x / 8 + y / 8) % 2 == 0 { [0, 0, 0] } else { [255, 255, 255] }),
+            channel_entropy: [1.0, 1.0, 1.0],
+        },
+        Fixture {
+            name: "noise.png",
+            image: rgb_image(|x, y| {
+                let index = (y * size + x) as usize;
+                [noise[0][index], noise[1][index], noise[2][index]]
+            }),
+            channel_entropy: [8.0, 8.0, 8.0],
+        },
+        Fixture {
+            name: "two_color.png",
+            image: rgb_image(|x, _| if x < size * 3 / 4 { [255, 0, 0] } else { [0, 0, 255] }),
+            channel_entropy: [two_color_entropy, 0.0, two_color_entropy],
+        },
+    ]
+}
+
+/// Writes every synthetic fixture into the directory as PNG, creating it if needed.
+pub fn write_fixtures(dir: &Path) -> Result<Vec<Fixture>, String> {
+    fs::create_dir_all(dir).map_err(|err| format!("Error: Unable to create '{}': {}", dir.display(), err))?;
+    let fixtures = synthetic_fixtures();
+    for fixture in &fixtures {
+        let path = dir.join(fixture.name);
+        fixture
+            .image
+            .save(&path)
+            .map_err(|err| format!("Error: Unable to write '{}': {}", path.display(), err))?;
+    }
+    Ok(fixtures)
+}
+
+fn rgb_image(pixel: impl Fn(u32, u32) -> [u8; 3]) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(FIXTURE_SIZE, FIXTURE_SIZE, |x, y| image::Rgb(pixel(x, y))))
+}
+
+/// Every byte value equally often across one fixture's pixels, in an order shuffled by a seeded
+/// linear congruential generator.
+fn shuffled_bytes(seed: u32) -> Vec<u8> {
+    let pixel_count = (FIXTURE_SIZE * FIXTURE_SIZE) as usize;
+    let mut bytes: Vec<u8> = (0..pixel_count).map(|index| index as u8).collect();
+    let mut state = seed;
+    for index in (1..pixel_count).rev() {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        bytes.swap(index, (state >> 8) as usize % (index + 1));
+    }
+    bytes
+}
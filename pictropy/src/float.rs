@@ -0,0 +1,66 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use image::codecs::hdr::HdrDecoder;
+use crate::{calculate_symbol_entropy, file_extension};
+
+/// Extensions of the floating-point formats read by `read_float_image`.
+pub const FLOAT_EXTENSIONS: [&str; 2] = ["hdr", "exr"];
+
+/// A floating-point RGB image, one sample vector per channel.
+pub struct FloatImage {
+    pub width: u32,
+    pub height: u32,
+    /// Red, green and blue samples in row-major order
+    pub channels: [Vec<f32>; 3],
+}
+
+/// Reads a Radiance HDR image as linear float RGB.
+///
+/// OpenEXR shares the extension list so it is routed here, but the `image` version this crate
+/// builds against has no EXR decoder, so it fails with a note to convert the file.
+pub fn read_float_image(path: &Path) -> Result<FloatImage, String> {
+    if file_extension(path) == "exr" {
+        return Err(format!(
+            "Error: Unable to decode '{}': OpenEXR is not supported yet. Convert it to Radiance HDR (.hdr) first.",
+            path.display()
+        ));
+    }
+    let invalid = |reason: &dyn std::fmt::Display| format!("Error: Unable to decode the HDR image '{}': {}", path.display(), reason);
+    let file = File::open(path).map_err(|err| invalid(&err))?;
+    let decoder = HdrDecoder::new(BufReader::new(file)).map_err(|err| invalid(&err))?;
+    let metadata = decoder.metadata();
+    let pixels = decoder.read_image_hdr().map_err(|err| invalid(&err))?;
+
+    let mut channels = [Vec::with_capacity(pixels.len()), Vec::with_capacity(pixels.len()), Vec::with_capacity(pixels.len())];
+    for pixel in pixels {
+        for (channel, &sample) in channels.iter_mut().zip(pixel.0.iter()) {
+            channel.push(sample);
+        }
+    }
+    Ok(FloatImage { width: metadata.width, height: metadata.height, channels })
+}
+
+/// Shannon entropy in bits per sample after quantizing the samples into `bins` equal-width bins
+/// spanning their finite range. NaN and infinite samples share one extra bin. Continuous values
+/// have no discrete histogram, so the result depends on the bin count and is at most log2(bins + 1).
+pub fn binned_entropy(samples: &[f32], bins: u32) -> f64 {
+    let (min, max) = samples
+        .iter()
+        .filter(|sample| sample.is_finite())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &sample| (min.min(sample), max.max(sample)));
+    let width = (max as f64 - min as f64) / bins as f64;
+    let binned: Vec<u32> = samples
+        .iter()
+        .map(|&sample| {
+            if !sample.is_finite() {
+                bins
+            } else if width > 0.0 {
+                (((sample as f64 - min as f64) / width) as u32).min(bins - 1)
+            } else {
+                0
+            }
+        })
+        .collect();
+    calculate_symbol_entropy(&binned)
+}
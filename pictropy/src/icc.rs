@@ -0,0 +1,136 @@
+use std::io::Read;
+use flate2::read::ZlibDecoder;
+
+/// Largest profile inflated from a PNG iCCP chunk, the most JPEG's 255 APP2 segments can carry.
+/// The chunk is compressed, so a small file could otherwise inflate without bound.
+const MAX_PROFILE_BYTES: u64 = 255 * 65519;
+
+/// An embedded ICC color profile and the header fields worth reporting.
+pub struct IccProfile {
+    pub name: Option<String>,
+    pub color_space: String,
+    pub size: usize,
+}
+
+impl IccProfile {
+    /// Whether the profile describes sRGB, judging by its description.
+    pub fn is_srgb(&self) -> bool {
+        self.name.as_deref().is_some_and(|name| name.to_lowercase().contains("srgb"))
+    }
+}
+
+/// Finds the ICC profile embedded in PNG or JPEG file data, if any.
+pub fn read_icc_profile(data: &[u8]) -> Option<IccProfile> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let (chunk_name, profile) = read_png_iccp(data)?;
+        let mut parsed = parse_profile(&profile)?;
+        if parsed.name.is_none() && !chunk_name.is_empty() {
+            parsed.name = Some(chunk_name);
+        }
+        Some(parsed)
+    } else if data.starts_with(&[0xFF, 0xD8]) {
+        parse_profile(&read_jpeg_app2(data)?)
+    } else {
+        None
+    }
+}
+
+/// Returns the iCCP chunk's profile name and inflated profile bytes.
+fn read_png_iccp(data: &[u8]) -> Option<(String, Vec<u8>)> {
+    let mut position = 8;
+    while position + 8 <= data.len() {
+        let length = u32::from_be_bytes(data[position..position + 4].try_into().ok()?) as usize;
+        let chunk_type = &data[position + 4..position + 8];
+        let chunk = data.get(position + 8..position + 8 + length)?;
+
+        match chunk_type {
+            b"iCCP" => {
+                let name_end = chunk.iter().position(|&byte| byte == 0)?;
+                let name = String::from_utf8_lossy(&chunk[..name_end]).into_owned();
+                let mut profile = Vec::new();
+                ZlibDecoder::new(chunk.get(name_end + 2..)?)
+                    .take(MAX_PROFILE_BYTES + 1)
+                    .read_to_end(&mut profile)
+                    .ok()?;
+                if profile.len() as u64 > MAX_PROFILE_BYTES {
+                    return None;
+                }
+                return Some((name, profile));
+            },
+            b"IDAT" | b"IEND" => return None, // iCCP must precede the image data
+            _ => position += 12 + length,
+        }
+    }
+    None
+}
+
+/// Reassembles an ICC profile split across JPEG APP2 `ICC_PROFILE` segments.
+fn read_jpeg_app2(data: &[u8]) -> Option<Vec<u8>> {
+    const SIGNATURE: &[u8] = b"ICC_PROFILE\0";
+    let mut chunks: Vec<(u8, &[u8])> = Vec::new();
+    let mut position = 2;
+
+    while position + 4 <= data.len() && data[position] == 0xFF {
+        let marker = data[position + 1];
+        if marker == 0xDA || marker == 0xD9 {
+            break; // Start of scan or end of image: no more metadata
+        }
+        let length = u16::from_be_bytes([data[position + 2], data[position + 3]]) as usize;
+        let segment = data.get(position + 4..position + 2 + length)?;
+        if marker == 0xE2 && segment.starts_with(SIGNATURE) && segment.len() > SIGNATURE.len() + 2 {
+            chunks.push((segment[SIGNATURE.len()], &segment[SIGNATURE.len() + 2..]));
+        }
+        position += 2 + length;
+    }
+
+    if chunks.is_empty() {
+        return None;
+    }
+    chunks.sort_by_key(|&(sequence, _)| sequence);
+    Some(chunks.into_iter().flat_map(|(_, chunk)| chunk.iter().copied()).collect())
+}
+
+/// Reads the data color space and `desc` tag from a raw ICC profile.
+fn parse_profile(profile: &[u8]) -> Option<IccProfile> {
+    let color_space = String::from_utf8_lossy(profile.get(16..20)?).trim().to_string();
+    // The count comes from the file; no more tags fit than the profile has room for
+    let tag_count = (read_u32(profile, 128)? as usize).min(profile.len().saturating_sub(132) / 12);
+
+    let name = (0..tag_count).find_map(|index| {
+        let entry = 132 + index * 12;
+        if profile.get(entry..entry + 4)? != b"desc" {
+            return None;
+        }
+        let offset = read_u32(profile, entry + 4)? as usize;
+        read_description(profile, offset)
+    });
+
+    Some(IccProfile { name, color_space, size: profile.len() })
+}
+
+/// Decodes a `desc` (ICC v2) or `mluc` (ICC v4) text tag.
+fn read_description(profile: &[u8], offset: usize) -> Option<String> {
+    match profile.get(offset..offset + 4)? {
+        b"desc" => {
+            let length = read_u32(profile, offset + 8)? as usize;
+            let text = profile.get(offset + 12..offset + 12 + length)?;
+            Some(String::from_utf8_lossy(text).trim_end_matches('\0').to_string())
+        },
+        b"mluc" => {
+            // First record: language, country, length, offset relative to the tag
+            let length = read_u32(profile, offset + 20)? as usize;
+            let start = offset + read_u32(profile, offset + 24)? as usize;
+            let units: Vec<u16> = profile
+                .get(start..start + length)?
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            Some(String::from_utf16_lossy(&units).trim_end_matches('\0').to_string())
+        },
+        _ => None,
+    }
+}
+
+fn read_u32(data: &[u8], position: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(position..position + 4)?.try_into().ok()?))
+}
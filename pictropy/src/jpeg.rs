@@ -0,0 +1,406 @@
+/// Quantized DCT coefficients of one JPEG color component, as stored in the file.
+pub struct ComponentCoefficients {
+    pub id: u8,
+    pub coefficients: Vec<i32>,
+}
+
+/// A frame component as declared in the SOF segment.
+struct FrameComponent {
+    id: u8,
+    horizontal_sampling: usize,
+    vertical_sampling: usize,
+    blocks_wide: usize,
+    blocks_high: usize,
+    coefficients: Vec<i32>,
+}
+
+/// Frame header geometry needed to walk the MCUs of a scan.
+struct Frame {
+    components: Vec<FrameComponent>,
+    mcus_wide: usize,
+    mcus_high: usize,
+}
+
+/// Canonical Huffman table in the decoding form of ITU T.81 Annex F.2.2.3.
+struct HuffmanTable {
+    max_code: [i32; 17],
+    min_code: [i32; 17],
+    value_offset: [i32; 17],
+    values: Vec<u8>,
+}
+
+impl HuffmanTable {
+    fn new(counts: &[u8], values: Vec<u8>) -> Self {
+        let mut table = HuffmanTable { max_code: [-1; 17], min_code: [0; 17], value_offset: [0; 17], values };
+        let mut code = 0;
+        let mut index = 0;
+
+        for length in 1..=16 {
+            let count = counts[length - 1] as i32;
+            if count > 0 {
+                table.value_offset[length] = index;
+                table.min_code[length] = code;
+                code += count;
+                index += count;
+                table.max_code[length] = code - 1;
+            }
+            code <<= 1;
+        }
+
+        table
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u8, String> {
+        let mut code = 0;
+        for length in 1..=16 {
+            code = (code << 1) | reader.bit();
+            if code <= self.max_code[length] {
+                let index = self.value_offset[length] + code - self.min_code[length];
+                return self
+                    .values
+                    .get(index as usize)
+                    .copied()
+                    .ok_or_else(|| String::from("Corrupt Huffman table."));
+            }
+        }
+        Err(String::from("Invalid Huffman code in entropy-coded data."))
+    }
+}
+
+/// Largest DC difference and AC coefficient sizes of 12-bit sample precision (T.81 F.1.2).
+const MAX_DC_SIZE: u8 = 15;
+const MAX_AC_SIZE: u8 = 14;
+
+/// Reads bits from entropy-coded data, removing byte stuffing and stopping at markers.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: i32,
+    bits_left: u32,
+    hit_marker: bool,
+    /// Set once a bit was read past the data or the marker ending it
+    padded: bool,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        BitReader { data, position: 0, buffer: 0, bits_left: 0, hit_marker: false, padded: false }
+    }
+
+    fn next_byte(&mut self) -> i32 {
+        if self.hit_marker || self.position >= self.data.len() {
+            self.padded = true;
+            return 0; // Pad truncated or finished scans with zero bits
+        }
+        let byte = self.data[self.position];
+        if byte == 0xFF {
+            if self.data.get(self.position + 1) == Some(&0x00) {
+                self.position += 2; // Stuffed 0xFF data byte
+                return 0xFF;
+            }
+            self.hit_marker = true;
+            self.padded = true;
+            return 0;
+        }
+        self.position += 1;
+        byte as i32
+    }
+
+    fn bit(&mut self) -> i32 {
+        if self.bits_left == 0 {
+            self.buffer = self.next_byte();
+            self.bits_left = 8;
+        }
+        self.bits_left -= 1;
+        (self.buffer >> self.bits_left) & 1
+    }
+
+    /// Reads an `size`-bit magnitude and sign-extends it (RECEIVE and EXTEND in T.81).
+    /// Sizes come from the file's Huffman tables, so ones no 12-bit JPEG can have are rejected.
+    fn receive_extend(&mut self, size: u8, max_size: u8) -> Result<i32, String> {
+        if size > max_size {
+            return Err(format!("Error: JPEG coefficient size {} exceeds the maximum of {} bits.", size, max_size));
+        }
+        let mut value = 0;
+        for _ in 0..size {
+            value = (value << 1) | self.bit();
+        }
+        if size > 0 && value < 1 << (size - 1) {
+            Ok(value - (1 << size) + 1)
+        } else {
+            Ok(value)
+        }
+    }
+
+    /// Discards buffered bits and skips the RSTn marker at the current position.
+    fn restart(&mut self) {
+        self.bits_left = 0;
+        self.hit_marker = false;
+        if self.data.get(self.position) == Some(&0xFF)
+            && matches!(self.data.get(self.position + 1), Some(0xD0..=0xD7))
+        {
+            self.position += 2;
+        }
+    }
+}
+
+/// Reads the quantized DCT coefficients of every component of a sequential Huffman JPEG.
+pub fn read_dct_coefficients(data: &[u8]) -> Result<Vec<ComponentCoefficients>, String> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return Err(String::from("Error: The file does not start with a JPEG SOI marker."));
+    }
+
+    let mut frame: Option<Frame> = None;
+    let mut dc_tables: [Option<HuffmanTable>; 4] = Default::default();
+    let mut ac_tables: [Option<HuffmanTable>; 4] = Default::default();
+    let mut restart_interval = 0;
+    let mut position = 2;
+
+    loop {
+        // Skip to the next marker, tolerating fill bytes
+        while position < data.len() && data[position] != 0xFF {
+            position += 1;
+        }
+        while position < data.len() && data[position] == 0xFF {
+            position += 1;
+        }
+        if position >= data.len() {
+            break; // Missing EOI; keep whatever was decoded
+        }
+        let marker = data[position];
+        position += 1;
+
+        match marker {
+            0xD9 => break, // EOI
+            0x01 | 0xD0..=0xD7 => continue, // Markers without a length field
+            _ => {},
+        }
+
+        let length = read_u16(data, position)? as usize;
+        let segment = data
+            .get(position + 2..position + length)
+            .ok_or_else(|| String::from("Error: JPEG segment extends past the end of the file."))?;
+        position += length;
+
+        match marker {
+            0xC0 | 0xC1 => frame = Some(parse_frame(segment)?),
+            0xC2 | 0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => {
+                return Err(String::from(
+                    "Error: Only baseline and extended sequential Huffman JPEGs are supported for DCT analysis.",
+                ));
+            },
+            0xC4 => parse_huffman_tables(segment, &mut dc_tables, &mut ac_tables)?,
+            0xDD => restart_interval = read_u16(segment, 0)? as usize,
+            0xDA => {
+                let frame = frame
+                    .as_mut()
+                    .ok_or_else(|| String::from("Error: JPEG scan appears before the frame header."))?;
+                let scan = parse_scan_header(segment, frame)?;
+                position += decode_scan(&data[position..], frame, &scan, &dc_tables, &ac_tables, restart_interval)?;
+            },
+            _ => {}, // Metadata and quantization tables are not needed
+        }
+    }
+
+    let frame = frame.ok_or_else(|| String::from("Error: No JPEG frame header found."))?;
+    Ok(frame
+        .components
+        .into_iter()
+        .map(|component| ComponentCoefficients { id: component.id, coefficients: component.coefficients })
+        .collect())
+}
+
+fn read_u16(data: &[u8], position: usize) -> Result<u16, String> {
+    data.get(position..position + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| String::from("Error: Unexpected end of JPEG data."))
+}
+
+fn parse_frame(segment: &[u8]) -> Result<Frame, String> {
+    let height = read_u16(segment, 1)? as usize;
+    let width = read_u16(segment, 3)? as usize;
+    let component_count = *segment.get(5).ok_or("Error: Truncated JPEG frame header.")? as usize;
+    if width == 0 || height == 0 {
+        return Err(String::from("Error: JPEGs with a deferred (DNL) height are not supported."));
+    }
+
+    let mut components = Vec::with_capacity(component_count);
+    for index in 0..component_count {
+        let bytes = segment
+            .get(6 + index * 3..9 + index * 3)
+            .ok_or("Error: Truncated JPEG frame header.")?;
+        let horizontal_sampling = (bytes[1] >> 4) as usize;
+        let vertical_sampling = (bytes[1] & 0x0F) as usize;
+        if !(1..=4).contains(&horizontal_sampling) || !(1..=4).contains(&vertical_sampling) {
+            return Err(String::from("Error: Invalid JPEG sampling factors."));
+        }
+        components.push(FrameComponent {
+            id: bytes[0],
+            horizontal_sampling,
+            vertical_sampling,
+            blocks_wide: 0,
+            blocks_high: 0,
+            coefficients: Vec::new(),
+        });
+    }
+
+    let max_horizontal = components.iter().map(|c| c.horizontal_sampling).max().unwrap_or(1);
+    let max_vertical = components.iter().map(|c| c.vertical_sampling).max().unwrap_or(1);
+    for component in &mut components {
+        let component_width = (width * component.horizontal_sampling).div_ceil(max_horizontal);
+        let component_height = (height * component.vertical_sampling).div_ceil(max_vertical);
+        component.blocks_wide = component_width.div_ceil(8);
+        component.blocks_high = component_height.div_ceil(8);
+    }
+
+    Ok(Frame {
+        components,
+        mcus_wide: width.div_ceil(8 * max_horizontal),
+        mcus_high: height.div_ceil(8 * max_vertical),
+    })
+}
+
+fn parse_huffman_tables(
+    mut segment: &[u8],
+    dc_tables: &mut [Option<HuffmanTable>; 4],
+    ac_tables: &mut [Option<HuffmanTable>; 4],
+) -> Result<(), String> {
+    while !segment.is_empty() {
+        let class_and_id = segment[0];
+        let counts = segment.get(1..17).ok_or("Error: Truncated JPEG Huffman table.")?;
+        let value_count: usize = counts.iter().map(|&count| count as usize).sum();
+        let values = segment
+            .get(17..17 + value_count)
+            .ok_or("Error: Truncated JPEG Huffman table.")?
+            .to_vec();
+        let table = HuffmanTable::new(counts, values);
+
+        let id = (class_and_id & 0x0F) as usize;
+        if id > 3 {
+            return Err(String::from("Error: Invalid JPEG Huffman table id."));
+        }
+        if class_and_id >> 4 == 0 {
+            dc_tables[id] = Some(table);
+        } else {
+            ac_tables[id] = Some(table);
+        }
+        segment = &segment[17 + value_count..];
+    }
+    Ok(())
+}
+
+/// Scan component selectors: (frame component index, DC table id, AC table id).
+fn parse_scan_header(segment: &[u8], frame: &Frame) -> Result<Vec<(usize, usize, usize)>, String> {
+    let component_count = *segment.first().ok_or("Error: Truncated JPEG scan header.")? as usize;
+    let mut scan = Vec::with_capacity(component_count);
+
+    for index in 0..component_count {
+        let bytes = segment
+            .get(1 + index * 2..3 + index * 2)
+            .ok_or("Error: Truncated JPEG scan header.")?;
+        let component_index = frame
+            .components
+            .iter()
+            .position(|component| component.id == bytes[0])
+            .ok_or("Error: JPEG scan references an unknown component.")?;
+        scan.push((component_index, (bytes[1] >> 4) as usize & 3, (bytes[1] & 0x0F) as usize & 3));
+    }
+    Ok(scan)
+}
+
+/// Decodes one scan's entropy-coded data and returns the number of bytes consumed.
+fn decode_scan(
+    data: &[u8],
+    frame: &mut Frame,
+    scan: &[(usize, usize, usize)],
+    dc_tables: &[Option<HuffmanTable>; 4],
+    ac_tables: &[Option<HuffmanTable>; 4],
+    restart_interval: usize,
+) -> Result<usize, String> {
+    let mut reader = BitReader::new(data);
+    let mut predictions = vec![0; scan.len()];
+    let interleaved = scan.len() > 1;
+
+    let mcu_count = if interleaved {
+        frame.mcus_wide * frame.mcus_high
+    } else {
+        let component = &frame.components[scan[0].0];
+        component.blocks_wide * component.blocks_high
+    };
+
+    for mcu in 0..mcu_count {
+        // Reading past the data means the scan is truncated; going on would decode the declared
+        // size from padding alone, which can be billions of blocks
+        if reader.padded {
+            return Err(String::from("Error: The JPEG scan ends before all of its blocks."));
+        }
+        if restart_interval > 0 && mcu > 0 && mcu % restart_interval == 0 {
+            reader.restart();
+            predictions.iter_mut().for_each(|prediction| *prediction = 0);
+        }
+
+        for (slot, &(component_index, dc_id, ac_id)) in scan.iter().enumerate() {
+            let dc_table = dc_tables[dc_id].as_ref().ok_or("Error: JPEG scan uses an undefined DC table.")?;
+            let ac_table = ac_tables[ac_id].as_ref().ok_or("Error: JPEG scan uses an undefined AC table.")?;
+            let component = &mut frame.components[component_index];
+            let blocks = if interleaved {
+                component.horizontal_sampling * component.vertical_sampling
+            } else {
+                1
+            };
+
+            for _ in 0..blocks {
+                decode_block(&mut reader, dc_table, ac_table, &mut predictions[slot], &mut component.coefficients)?;
+            }
+        }
+    }
+
+    // Advance to the marker that ends the scan
+    let mut position = reader.position;
+    while position + 1 < data.len()
+        && (data[position] != 0xFF || matches!(data[position + 1], 0x00 | 0xD0..=0xD7))
+    {
+        position += 1;
+    }
+    Ok(position)
+}
+
+fn decode_block(
+    reader: &mut BitReader,
+    dc_table: &HuffmanTable,
+    ac_table: &HuffmanTable,
+    prediction: &mut i32,
+    coefficients: &mut Vec<i32>,
+) -> Result<(), String> {
+    let mut block = [0; 64];
+
+    let dc_size = dc_table.decode(reader)?;
+    *prediction = prediction
+        .checked_add(reader.receive_extend(dc_size, MAX_DC_SIZE)?)
+        .ok_or("Error: JPEG DC coefficient overflows.")?;
+    block[0] = *prediction;
+
+    let mut index = 1;
+    while index < 64 {
+        let run_size = ac_table.decode(reader)?;
+        let run = (run_size >> 4) as usize;
+        let size = run_size & 0x0F;
+
+        if size == 0 {
+            if run == 15 {
+                index += 16; // ZRL: sixteen zeros
+                continue;
+            }
+            break; // EOB: remaining coefficients are zero
+        }
+        index += run;
+        if index >= 64 {
+            return Err(String::from("Error: JPEG block has more than 64 coefficients."));
+        }
+        block[index] = reader.receive_extend(size, MAX_AC_SIZE)?;
+        index += 1;
+    }
+
+    coefficients.extend_from_slice(&block);
+    Ok(())
+}
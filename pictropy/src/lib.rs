@@ -0,0 +1,1032 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::io::{BufRead, BufReader, Cursor, Seek};
+use std::path::Path;
+use clap::ValueEnum;
+use image::{ColorType, DynamicImage, GenericImageView, ImageError, ImageFormat, RgbImage, RgbaImage};
+use image::error::{DecodingError, ImageFormatHint};
+use log::warn;
+use serde::Serialize;
+use image::imageops::FilterType;
+use imageproc::gradients::sobel_gradients;
+use webp::Encoder;
+
+pub mod analysis;
+pub mod apng;
+pub mod borders;
+pub mod cache;
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
+pub mod compare;
+pub mod denoise;
+pub mod encode;
+pub mod fixtures;
+pub mod float;
+pub mod icc;
+pub mod jpeg;
+pub mod lossy;
+pub mod metadata;
+pub mod png;
+pub mod ppm_model;
+pub mod quantize;
+#[cfg(feature = "raw")]
+pub mod raw;
+pub mod rate;
+pub mod recommend;
+pub mod serve;
+pub mod signed;
+pub mod reference;
+pub mod stats_db;
+
+pub use analysis::{analyze, AnalysisOptions, AnalysisResult};
+
+/// Highest context order used by the PPM model.
+pub const PPM_ORDER: usize = 3;
+
+/// Highest context order a `PpmModel` supports: contexts are packed into a `u64` key, one byte each.
+pub const PPM_MAX_ORDER: usize = 8;
+
+/// Most contexts the PPM model tracks per channel. Beyond this no new contexts are added and symbols
+/// escape to the lower orders already tracked, which bounds memory on full-resolution photos.
+pub const PPM_MAX_CONTEXTS: usize = 1 << 19;
+
+/// Handling of the PPM warmup symbols that lack a full-order context.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PpmWarmup {
+    /// Escape to lower-order contexts, down to a uniform order -1 model
+    Backoff,
+    /// Leave the warmup symbols out of the size tally
+    Exclude,
+}
+
+/// Adjustment of the histogram counts before they become probabilities.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Smoothing {
+    /// Empirical frequencies (maximum-likelihood estimate)
+    None,
+    /// Add-k smoothing: every byte value gets k extra occurrences
+    Laplace,
+}
+
+/// Logarithm base in which entropy is reported. Entropy is computed in bits and converted for display.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EntropyUnit {
+    /// Base 2
+    Bits,
+    /// Base e
+    Nats,
+    /// Base 10
+    Hartleys,
+}
+
+impl EntropyUnit {
+    /// Plural name of the unit, for labels.
+    pub fn name(self) -> &'static str {
+        match self {
+            EntropyUnit::Bits => "bits",
+            EntropyUnit::Nats => "nats",
+            EntropyUnit::Hartleys => "hartleys",
+        }
+    }
+
+    /// Converts an entropy in bits to this unit.
+    pub fn from_bits(self, bits: f64) -> f64 {
+        match self {
+            EntropyUnit::Bits => bits,
+            EntropyUnit::Nats => bits * std::f64::consts::LN_2,
+            EntropyUnit::Hartleys => bits * std::f64::consts::LOG10_2,
+        }
+    }
+}
+
+/// Red, green and blue sample planes of an image.
+pub type RgbChannels = (Vec<u8>, Vec<u8>, Vec<u8>);
+
+/// Real compressors whose output size is reported next to the entropy limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Compressor {
+    /// Per-channel Prediction by Partial Matching estimate
+    Ppm,
+    /// Lossless WebP encoding of the whole image
+    Webp,
+    /// QOI encoding of the whole image
+    Qoi,
+}
+
+impl Compressor {
+    /// Display name of the compressor.
+    pub fn name(self) -> &'static str {
+        match self {
+            Compressor::Ppm => "PPM",
+            Compressor::Webp => "WebP",
+            Compressor::Qoi => "QOI",
+        }
+    }
+}
+
+/// Byte order of the color samples within each pixel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChannelOrder {
+    /// Red, green, blue
+    Rgb,
+    /// Blue, green, red
+    Bgr,
+}
+
+/// Color spaces the pixels can be converted to before measuring, besides the stored RGB.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Colorspace {
+    /// Hue, saturation and value
+    Hsv,
+}
+
+impl Colorspace {
+    /// Display name of the color space.
+    pub fn name(self) -> &'static str {
+        match self {
+            Colorspace::Hsv => "HSV",
+        }
+    }
+
+    /// Names of the three planes, in the order `colorspace_planes` returns them.
+    pub fn plane_names(self) -> [&'static str; 3] {
+        match self {
+            Colorspace::Hsv => ["Hue", "Saturation", "Value"],
+        }
+    }
+}
+
+/// Extensions of the image files that are decoded to pixels.
+pub const IMAGE_EXTENSIONS: [&str; 6] = ["jpg", "jpeg", "png", "qoi", "tif", "tiff"];
+
+/// Reads an image from the specified file path, returning a Result to handle errors gracefully.
+pub fn read_image(image_path: &Path) -> Result<DynamicImage, String> {
+    decode_image_file(image_path, &read_image_file(image_path)?)
+}
+
+/// Decodes the already-read content of the file at `image_path`, in the format its extension names.
+pub fn decode_image_file(image_path: &Path, data: &[u8]) -> Result<DynamicImage, String> {
+    let image = if file_extension(image_path) == "qoi" {
+        decode_qoi(data)
+    } else {
+        ImageFormat::from_path(image_path).and_then(|format| image::load_from_memory_with_format(data, format))
+    };
+    image.map_err(|err| decode_error_message(image_path, data, &err, true))
+}
+
+/// Decodes the image by sniffing its content instead of trusting its extension.
+pub fn read_image_by_content(image_path: &Path) -> Result<DynamicImage, String> {
+    decode_image_file_by_content(image_path, &read_image_file(image_path)?)
+}
+
+/// Decodes the already-read content of the file at `image_path`, sniffing its format.
+pub fn decode_image_file_by_content(image_path: &Path, data: &[u8]) -> Result<DynamicImage, String> {
+    decode_image(data).map_err(|err| decode_error_message(image_path, data, &err, false))
+}
+
+/// Decodes image data held in memory, sniffing its format from the content.
+pub fn decode_image(data: &[u8]) -> Result<DynamicImage, ImageError> {
+    if data.starts_with(QOI_MAGIC) { decode_qoi(data) } else { image::load_from_memory(data) }
+}
+
+fn read_image_file(image_path: &Path) -> Result<Vec<u8>, String> {
+    std::fs::read(image_path).map_err(|err| format!("Error: Unable to read '{}': {}", image_path.display(), err))
+}
+
+/// Signature at the start of every QOI file.
+const QOI_MAGIC: &[u8] = b"qoif";
+
+/// Describes a failed decode, telling a file that ends early, such as a partial download, apart
+/// from one that is corrupt or not an image at all. The decoder's own message is kept, as a
+/// truncated PNG, for one, only reports a format error. With `by_extension`, data of another
+/// format than the extension names is called out too. TIFFs of signed samples are pointed to --signed.
+fn decode_error_message(image_path: &Path, data: &[u8], err: &ImageError, by_extension: bool) -> String {
+    let path = image_path.display();
+    let Some((name, end_marker)) = sniff_format(data) else {
+        return format!("Error: '{}' is not an image; its content matches no supported format ({}).", path, err);
+    };
+    let extension = file_extension(image_path);
+    let expected = if extension == "qoi" { Some("QOI") } else { ImageFormat::from_extension(&extension).map(format_name) };
+    match expected {
+        _ if name == "TIFF" && signed::is_signed_tiff(data) => format!(
+            "Error: '{}' holds signed integer samples, which are only read with --signed.",
+            path
+        ),
+        Some(expected) if by_extension && expected != name => format!(
+            "Error: '{}' holds {} data, not {}; --force decodes files by their content.",
+            path, name, expected
+        ),
+        _ if end_marker.is_some_and(|marker| !data.ends_with(marker)) => format!(
+            "Error: '{}' is a truncated {} file; it ends before its image data does, as a partial download would ({}).",
+            path, name, err
+        ),
+        _ => format!("Error: '{}' is a corrupt {} file: {}", path, name, err),
+    }
+}
+
+/// Name of the format the data's signature identifies, with the bytes every complete file of that
+/// format ends with where it has them.
+fn sniff_format(data: &[u8]) -> Option<(&'static str, Option<&'static [u8]>)> {
+    if data.starts_with(QOI_MAGIC) {
+        return Some(("QOI", Some(&[0, 0, 0, 0, 0, 0, 0, 1])));
+    }
+    let format = image::guess_format(data).ok()?;
+    let end_marker: Option<&'static [u8]> = match format {
+        ImageFormat::Png => Some(b"IEND\xAE\x42\x60\x82"),
+        ImageFormat::Jpeg => Some(&[0xFF, 0xD9]),
+        _ => None,
+    };
+    Some((format_name(format), end_marker))
+}
+
+/// Display name of a format the `image` crate reads.
+fn format_name(format: ImageFormat) -> &'static str {
+    match format {
+        ImageFormat::Png => "PNG",
+        ImageFormat::Jpeg => "JPEG",
+        ImageFormat::Gif => "GIF",
+        ImageFormat::WebP => "WebP",
+        ImageFormat::Bmp => "BMP",
+        ImageFormat::Tiff => "TIFF",
+        _ => "image",
+    }
+}
+
+/// Decodes a QOI file, which the `image` crate can't read, as RGB or RGBA like its header says.
+fn decode_qoi(data: &[u8]) -> Result<DynamicImage, ImageError> {
+    let qoi_error = |err: Box<dyn std::error::Error + Send + Sync>| {
+        ImageError::Decoding(DecodingError::new(ImageFormatHint::Name(String::from("QOI")), err))
+    };
+    let (header, pixels) = qoi::decode_to_vec(data).map_err(|err| qoi_error(err.into()))?;
+    let image = match header.channels {
+        qoi::Channels::Rgb => RgbImage::from_raw(header.width, header.height, pixels).map(DynamicImage::ImageRgb8),
+        qoi::Channels::Rgba => RgbaImage::from_raw(header.width, header.height, pixels).map(DynamicImage::ImageRgba8),
+    };
+    image.ok_or_else(|| qoi_error("the pixel data doesn't match the header".into()))
+}
+
+/// Width and height from the image's header, without decoding the pixels. The format is sniffed
+/// from the content, as `read_image_by_content` does, so a misnamed file is measured all the same.
+pub fn image_dimensions(image_path: &Path) -> Option<(u32, u32)> {
+    header_dimensions(BufReader::new(std::fs::File::open(image_path).ok()?))
+}
+
+/// `image_dimensions` of image data held in memory.
+pub fn data_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    header_dimensions(Cursor::new(data))
+}
+
+fn header_dimensions(mut reader: impl BufRead + Seek) -> Option<(u32, u32)> {
+    let start = reader.fill_buf().ok()?;
+    let (qoi, tiff) = (start.starts_with(QOI_MAGIC), is_tiff(start));
+    if qoi {
+        let mut header = [0; qoi::consts::QOI_HEADER_SIZE];
+        reader.read_exact(&mut header).ok()?;
+        qoi::decode_header(header).ok().map(|header| (header.width, header.height))
+    } else if tiff {
+        // The tiff crate also reads the headers of signed TIFFs, which the image crate refuses
+        tiff::decoder::Decoder::new(reader).ok()?.dimensions().ok()
+    } else {
+        image::io::Reader::new(reader).with_guessed_format().ok()?.into_dimensions().ok()
+    }
+}
+
+/// Refuses an image of more than `max_pixels` pixels, and one whose header can't be read, since
+/// its size would then only be known after decoding it.
+pub fn check_max_pixels(name: &str, dimensions: Option<(u32, u32)>, max_pixels: u64) -> Result<(), String> {
+    let Some((width, height)) = dimensions else {
+        return Err(format!(
+            "Error: Unable to read the dimensions of '{}' from its header, so the --max-pixels limit can't be checked.",
+            name
+        ));
+    };
+    let pixel_count = width as u64 * height as u64;
+    if pixel_count > max_pixels {
+        return Err(format!(
+            "Error: The image is {}x{} ({} pixels), which exceeds the --max-pixels limit of {}.",
+            width, height, pixel_count, max_pixels
+        ));
+    }
+    Ok(())
+}
+
+/// Whether the data starts with a little- or big-endian TIFF signature.
+fn is_tiff(data: &[u8]) -> bool {
+    data.starts_with(b"II*\0") || data.starts_with(b"MM\0*")
+}
+
+/// Lowercased extension of the path's final component (`png` for `foo.tar.gz.PNG`), or an empty
+/// string when there is none or it isn't valid UTF-8.
+pub fn file_extension(path: &Path) -> String {
+    path.extension().and_then(|extension| extension.to_str()).unwrap_or("").to_lowercase()
+}
+
+/// Counts the occurrences of each byte value, indexed by the value and so already in symbol order.
+pub fn byte_histogram(data: &[u8]) -> [u64; 256] {
+    // A fixed array avoids hashing every byte
+    let mut histogram = [0u64; 256];
+    for &value in data {
+        histogram[value as usize] += 1;
+    }
+    histogram
+}
+
+/// Calculates entropy of a given data array.
+pub fn calculate_entropy(image_data: &[u8]) -> f64 {
+    let histogram = byte_histogram(image_data);
+    entropy_from_counts(histogram.iter().copied().filter(|&count| count > 0), image_data.len())
+}
+
+/// Variance of the 256 histogram-bin probabilities. An even spread over many values gives a
+/// variance near zero and a few dominant values a high one, so it separates noise from posterized
+/// content of similar entropy. The maximum, all samples in one bin, is 255 / 256^2.
+pub fn histogram_variance(image_data: &[u8]) -> f64 {
+    let total = image_data.len().max(1) as f64;
+    let mean = 1.0 / 256.0;
+    byte_histogram(image_data)
+        .iter()
+        .map(|&count| {
+            let deviation = count as f64 / total - mean;
+            deviation * deviation
+        })
+        .sum::<f64>()
+        / 256.0
+}
+
+/// One byte value's share of a channel.
+pub struct ValueShare {
+    pub value: u8,
+    pub count: u64,
+    /// Fraction of the samples holding the value
+    pub frequency: f64,
+    /// The value's term of the entropy sum, `p * log2(1 / p)`, in bits per sample
+    pub entropy_contribution: f64,
+}
+
+/// The `k` most frequent byte values, most frequent first, ties broken by value. The contributions
+/// of all values sum to `calculate_entropy`.
+pub fn top_values(image_data: &[u8], k: usize) -> Vec<ValueShare> {
+    let total = image_data.len() as f64;
+    let mut histogram: Vec<(u8, u64)> = byte_histogram(image_data)
+        .iter()
+        .enumerate()
+        .filter(|&(_, &count)| count > 0)
+        .map(|(value, &count)| (value as u8, count))
+        .collect();
+    histogram.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    histogram
+        .into_iter()
+        .take(k)
+        .map(|(value, count)| {
+            let frequency = count as f64 / total;
+            ValueShare { value, count, frequency, entropy_contribution: frequency * (1.0 / frequency).log2() }
+        })
+        .collect()
+}
+
+/// Calculates entropy with add-k smoothing: each of the 256 byte values is credited `pseudocount`
+/// extra occurrences, so values a small sample happened to miss still get some probability.
+pub fn calculate_smoothed_entropy(image_data: &[u8], pseudocount: f64) -> f64 {
+    let histogram = byte_histogram(image_data);
+    let total = image_data.len() as f64 + 256.0 * pseudocount;
+    histogram
+        .iter()
+        .map(|&count| count as f64 + pseudocount)
+        .filter(|&count| count > 0.0)
+        .map(|count| {
+            let probability = count / total;
+            -probability * probability.log2()
+        })
+        .sum()
+}
+
+/// Counts the occurrences of symbols too wide for an array histogram, sorted by symbol value.
+/// Sorting makes anything printed or summed from the counts reproducible across runs, where
+/// `HashMap` iteration order is not.
+pub fn symbol_histogram<T: Ord + Hash + Copy>(symbols: &[T]) -> Vec<(T, u64)> {
+    let mut counts = HashMap::new();
+    for &value in symbols {
+        *counts.entry(value).or_insert(0u64) += 1;
+    }
+
+    let mut histogram: Vec<(T, u64)> = counts.into_iter().collect();
+    histogram.sort_unstable_by_key(|&(value, _)| value);
+    histogram
+}
+
+/// Calculates entropy of symbols too wide for an array histogram, such as 16-bit samples or DCT coefficients.
+pub fn calculate_symbol_entropy<T: Ord + Hash + Copy>(symbols: &[T]) -> f64 {
+    let histogram = symbol_histogram(symbols);
+    entropy_from_counts(histogram.into_iter().map(|(_, count)| count), symbols.len())
+}
+
+/// Calculates the joint entropy H(X, Y) in bits per pixel of two aligned byte sequences, from their pair histogram.
+pub fn joint_entropy(first: &[u8], second: &[u8]) -> f64 {
+    let mut histogram = vec![0u64; 256 * 256];
+    for (&x, &y) in first.iter().zip(second) {
+        histogram[x as usize * 256 + y as usize] += 1;
+    }
+    entropy_from_counts(histogram.into_iter().filter(|&count| count > 0), first.len().min(second.len()))
+}
+
+/// Calculates the mutual information I(X; Y) = H(X) + H(Y) - H(X, Y) of two aligned byte sequences:
+/// how many bits per pixel knowing one tells about the other.
+pub fn mutual_information(first: &[u8], second: &[u8]) -> f64 {
+    // Rounding can leave independent data a hair below zero
+    (calculate_entropy(first) + calculate_entropy(second) - joint_entropy(first, second)).max(0.0)
+}
+
+fn entropy_from_counts(counts: impl Iterator<Item = u64>, total: usize) -> f64 {
+    let total_pixels = total as f64;
+    counts
+        .map(|count| {
+            let probability = count as f64 / total_pixels;
+            -probability * probability.log2()
+        })
+        .sum()
+}
+
+/// Splits the image into its red, green, and blue color channels, honoring the sample order.
+pub fn split_rgb_channels(img: &DynamicImage, order: ChannelOrder) -> RgbChannels {
+    split_channels_where(img, order, |_| true)
+}
+
+/// Converts the pixels to the color space and splits them into its three planes, one byte per
+/// sample. For HSV, hue is quantized to 256 steps around the color wheel, and gray pixels, which
+/// have no hue, get hue 0.
+pub fn colorspace_planes(img: &DynamicImage, order: ChannelOrder, colorspace: Colorspace) -> [Vec<u8>; 3] {
+    let (red, green, blue) = split_rgb_channels(img, order);
+    match colorspace {
+        Colorspace::Hsv => {
+            let mut planes: [Vec<u8>; 3] = std::array::from_fn(|_| Vec::with_capacity(red.len()));
+            for ((&r, &g), &b) in red.iter().zip(&green).zip(&blue) {
+                for (plane, sample) in planes.iter_mut().zip(rgb_to_hsv(r, g, b)) {
+                    plane.push(sample);
+                }
+            }
+            planes
+        },
+    }
+}
+
+/// One pixel's hue, saturation and value, each scaled to a byte.
+fn rgb_to_hsv(r: u8, g: u8, b: u8) -> [u8; 3] {
+    let max = r.max(g).max(b);
+    let delta = (max - r.min(g).min(b)) as f64;
+    let saturation = if max == 0 { 0.0 } else { delta / max as f64 * 255.0 };
+    let hue = if delta == 0.0 {
+        0.0
+    } else {
+        // Sixths of the wheel: red at 0, green at 2, blue at 4
+        let sextant = match max {
+            _ if max == r => (g as f64 - b as f64) / delta,
+            _ if max == g => 2.0 + (b as f64 - r as f64) / delta,
+            _ => 4.0 + (r as f64 - g as f64) / delta,
+        };
+        sextant.rem_euclid(6.0) / 6.0 * 256.0
+    };
+    [(hue as u32).min(255) as u8, saturation.round() as u8, max]
+}
+
+/// Like `split_rgb_channels`, but leaves out fully transparent pixels, whose color is never seen.
+pub fn split_opaque_rgb_channels(img: &DynamicImage, order: ChannelOrder) -> RgbChannels {
+    split_channels_where(img, order, |alpha| alpha != 0)
+}
+
+/// Counts the pixels with an alpha of zero.
+pub fn count_transparent_pixels(img: &DynamicImage) -> usize {
+    img.pixels().filter(|pixel| pixel.2 .0[3] == 0).count()
+}
+
+/// Counts the distinct RGBA colors at 8 bits per sample. With a limit, counting stops as soon as
+/// there are more than `limit` colors and `None` is returned, which keeps the check cheap on photos.
+pub fn count_unique_colors(img: &DynamicImage, limit: Option<usize>) -> Option<usize> {
+    let mut colors = HashSet::new();
+    for (_, _, pixel) in img.pixels() {
+        colors.insert(pixel.0);
+        if limit.is_some_and(|limit| colors.len() > limit) {
+            return None;
+        }
+    }
+    Some(colors.len())
+}
+
+fn split_channels_where(img: &DynamicImage, order: ChannelOrder, keep_alpha: impl Fn(u8) -> bool) -> RgbChannels {
+    let (width, height) = img.dimensions();
+    let mut red_channel = Vec::with_capacity((width * height) as usize);
+    let mut green_channel = Vec::with_capacity((width * height) as usize);
+    let mut blue_channel = Vec::with_capacity((width * height) as usize);
+
+    for pixel in img.pixels() {
+        let [first, g, third, alpha] = pixel.2 .0; // Access inner array using `.0`
+        if !keep_alpha(alpha) {
+            continue;
+        }
+        let (r, b) = match order {
+            ChannelOrder::Rgb => (first, third),
+            ChannelOrder::Bgr => (third, first),
+        };
+        red_channel.push(r);
+        green_channel.push(g);
+        blue_channel.push(b);
+    }
+
+    (red_channel, green_channel, blue_channel)
+}
+
+/// Largest width or height, in pixels, that a WebP image can have.
+pub const WEBP_MAX_DIMENSION: u32 = 16383;
+
+/// Rejects images too large for WebP, which libwebp would otherwise fail on opaquely.
+pub fn check_webp_dimensions(image: &DynamicImage) -> Result<(), String> {
+    let (width, height) = image.dimensions();
+    if width > WEBP_MAX_DIMENSION || height > WEBP_MAX_DIMENSION {
+        return Err(format!(
+            "Error: The image is {}x{}, which exceeds WebP's maximum dimension of {} pixels. Use \
+             --compressor ppm or qoi, analyze it in tiles with --tile-grid, or downscale it first.",
+            width, height, WEBP_MAX_DIMENSION
+        ));
+    }
+    Ok(())
+}
+
+/// Whether the image stores 16 bits per sample.
+pub fn is_16_bit(image: &DynamicImage) -> bool {
+    matches!(image.color(), ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16)
+}
+
+/// Narrows 16-bit samples to 8-bit RGB, or RGBA when there is alpha, rounding each to the nearest
+/// 8-bit value: `(v * 255 + 32767) / 65535`. The `image` conversions truncate (`v >> 8`) instead,
+/// which darkens every sample by up to one step.
+pub fn round_to_8_bit(image: &DynamicImage) -> DynamicImage {
+    let round = |samples: Vec<u16>| -> Vec<u8> {
+        samples.into_iter().map(|sample| ((sample as u32 * 255 + 32767) / 65535) as u8).collect()
+    };
+    let (width, height) = image.dimensions();
+    if image.color().has_alpha() {
+        let samples = round(image.to_rgba16().into_raw());
+        DynamicImage::ImageRgba8(RgbaImage::from_raw(width, height, samples).expect("Buffer matches the dimensions"))
+    } else {
+        let samples = round(image.to_rgb16().into_raw());
+        DynamicImage::ImageRgb8(RgbImage::from_raw(width, height, samples).expect("Buffer matches the dimensions"))
+    }
+}
+
+/// Encodes the image with lossless WebP compression. libwebp only takes 8-bit RGB and RGBA, so
+/// 16-bit images are rounded with `round_to_8_bit` and other color types expanded to RGBA first.
+pub fn webp_encode_lossless(image: &DynamicImage) -> Result<Vec<u8>, String> {
+    check_webp_dimensions(image)?;
+    let expanded;
+    let image = match image {
+        DynamicImage::ImageRgb8(_) | DynamicImage::ImageRgba8(_) => image,
+        _ if is_16_bit(image) => {
+            expanded = round_to_8_bit(image);
+            &expanded
+        },
+        _ => {
+            expanded = DynamicImage::ImageRgba8(image.to_rgba8());
+            &expanded
+        },
+    };
+    let encoder = Encoder::from_image(image).map_err(|err| format!("Error: WebP encoding failed: {}", err))?;
+    Ok(encoder.encode_lossless().to_vec()) // Lossless WebP compression
+}
+
+/// Splits the raw sample buffer as `channels` interleaved samples per pixel, ignoring the
+/// decoder's color type. A single channel is reported as red, green and blue alike, the same
+/// way grayscale images are expanded by `split_rgb_channels`. The buffer is split byte by byte, so
+/// 16-bit images are rejected rather than split mid-sample.
+pub fn split_assumed_channels(
+    img: &DynamicImage,
+    channels: usize,
+    order: ChannelOrder,
+) -> Result<RgbChannels, String> {
+    if is_16_bit(img) {
+        return Err(String::from(
+            "Error: --assume-channels reinterprets 8-bit sample buffers only; this image has 16-bit samples.",
+        ));
+    }
+    let samples = img.as_bytes();
+    if !samples.len().is_multiple_of(channels) {
+        return Err(format!(
+            "Error: The image buffer holds {} bytes, which is not a multiple of {} channels.",
+            samples.len(),
+            channels
+        ));
+    }
+
+    let channel = |offset: usize| samples.iter().skip(offset).step_by(channels).copied().collect::<Vec<u8>>();
+    if channels == 1 {
+        return Ok((samples.to_vec(), samples.to_vec(), samples.to_vec()));
+    }
+    let (first, green, third) = (channel(0), channel(1), channel(2));
+    Ok(match order {
+        ChannelOrder::Rgb => (first, green, third),
+        ChannelOrder::Bgr => (third, green, first),
+    })
+}
+
+/// Keeps every `stride`-th sample, a deterministic subsample for fast estimates.
+pub fn subsample(samples: &[u8], stride: usize) -> Vec<u8> {
+    samples.iter().step_by(stride).copied().collect()
+}
+
+/// Calculates the entropy of the raw interleaved RGBA byte stream, as a byte-oriented
+/// compressor would see the uncompressed buffer.
+pub fn interleaved_entropy(img: &DynamicImage) -> f64 {
+    calculate_entropy(&img.to_rgba8().into_raw())
+}
+
+/// Calculates the entropy of the Sobel gradient magnitude of the image's luma, which rises with
+/// the amount of edges and fine detail. Magnitudes reach about 1443, so they are counted as
+/// 16-bit symbols rather than clipped to bytes.
+pub fn gradient_entropy(img: &DynamicImage) -> f64 {
+    calculate_symbol_entropy(sobel_gradients(&img.to_luma8()).as_raw())
+}
+
+/// Splits the image into every channel its color type actually has, in stored order, reduced to
+/// 8 bits per sample. Unlike `split_rgb_channels`, gray stays one channel and alpha is kept.
+pub fn split_all_channels(img: &DynamicImage) -> Vec<Vec<u8>> {
+    let channel_count = img.color().channel_count() as usize;
+    let samples = match img {
+        DynamicImage::ImageLuma8(_)
+        | DynamicImage::ImageLumaA8(_)
+        | DynamicImage::ImageRgb8(_)
+        | DynamicImage::ImageRgba8(_)
+        | DynamicImage::ImageBgr8(_)
+        | DynamicImage::ImageBgra8(_) => img.as_bytes().to_vec(),
+        _ => match channel_count {
+            1 => img.to_luma8().into_raw(),
+            2 => img.to_luma_alpha8().into_raw(),
+            3 => img.to_rgb8().into_raw(),
+            _ => img.to_rgba8().into_raw(),
+        },
+    };
+
+    (0..channel_count)
+        .map(|offset| samples.iter().skip(offset).step_by(channel_count).copied().collect())
+        .collect()
+}
+
+/// Names channel `index` of the image, in the order `split_all_channels` returns them.
+/// Layouts beyond RGBA get generic "Channel N" names.
+pub fn channel_label(img: &DynamicImage, index: usize) -> String {
+    let names: &[&str] = match img {
+        DynamicImage::ImageLuma8(_) | DynamicImage::ImageLuma16(_) => &["Gray"],
+        DynamicImage::ImageLumaA8(_) | DynamicImage::ImageLumaA16(_) => &["Gray", "Alpha"],
+        DynamicImage::ImageBgr8(_) => &["Blue", "Green", "Red"],
+        DynamicImage::ImageBgra8(_) => &["Blue", "Green", "Red", "Alpha"],
+        _ => &["Red", "Green", "Blue", "Alpha"],
+    };
+    match names.get(index) {
+        Some(name) if img.color().channel_count() as usize <= names.len() => String::from(*name),
+        _ => format!("Channel {}", index),
+    }
+}
+
+/// Per-channel entropy at one level of an image pyramid.
+pub struct PyramidLevel {
+    pub width: u32,
+    pub height: u32,
+    pub red_entropy: f64,
+    pub green_entropy: f64,
+    pub blue_entropy: f64,
+}
+
+/// Builds a pyramid of successive 2x box-filtered downscales, starting at full resolution, and
+/// measures the entropy of each level. Stops early once a level would shrink below one pixel.
+pub fn pyramid_entropy(img: &DynamicImage, levels: usize, order: ChannelOrder) -> Vec<PyramidLevel> {
+    let mut pyramid = Vec::new();
+    let mut level = img.clone();
+
+    for _ in 0..levels {
+        let (width, height) = level.dimensions();
+        let (red, green, blue) = split_rgb_channels(&level, order);
+        pyramid.push(PyramidLevel {
+            width,
+            height,
+            red_entropy: calculate_entropy(&red),
+            green_entropy: calculate_entropy(&green),
+            blue_entropy: calculate_entropy(&blue),
+        });
+
+        if width < 2 || height < 2 {
+            break;
+        }
+        level = level.resize_exact(width / 2, height / 2, FilterType::Triangle);
+    }
+
+    pyramid
+}
+
+/// Per-channel entropy of one tile in a grid.
+pub struct TileEntropy {
+    pub red_entropy: f64,
+    pub green_entropy: f64,
+    pub blue_entropy: f64,
+}
+
+impl TileEntropy {
+    /// Red, green and blue entropy, in that order.
+    pub fn channels(&self) -> [f64; 3] {
+        [self.red_entropy, self.green_entropy, self.blue_entropy]
+    }
+}
+
+/// Divides the image into `columns` by `rows` tiles and measures each one's entropy, returned
+/// row by row. Dimensions that don't divide evenly leave the remainder in the last row and column.
+pub fn tile_entropy(
+    img: &DynamicImage,
+    columns: u32,
+    rows: u32,
+    order: ChannelOrder,
+) -> Result<Vec<Vec<TileEntropy>>, String> {
+    let grid = tile_regions(img, columns, rows)?
+        .into_iter()
+        .map(|regions| regions.into_iter().map(|region| region_entropy(img, region, order)).collect())
+        .collect();
+    Ok(grid)
+}
+
+/// Entropy of the tiles that hold content rather than a uniform background.
+pub struct ContentEntropy {
+    /// Entropy of the pixels of the kept tiles taken together
+    pub entropy: TileEntropy,
+    pub kept_tiles: usize,
+    pub total_tiles: usize,
+    /// Pixels in the kept tiles
+    pub kept_pixels: u64,
+}
+
+/// Measures entropy over the non-uniform parts of the image only. Each tile of a `columns` by
+/// `rows` grid, laid out as in `tile_entropy`, is dropped when even its highest-entropy channel
+/// is below `threshold` bits; the pixels of the remaining tiles are then pooled and measured as
+/// one. None when every tile is dropped.
+pub fn content_entropy(
+    img: &DynamicImage,
+    columns: u32,
+    rows: u32,
+    order: ChannelOrder,
+    threshold: f64,
+) -> Result<Option<ContentEntropy>, String> {
+    let regions: Vec<Region> = tile_regions(img, columns, rows)?.into_iter().flatten().collect();
+    let total_tiles = regions.len();
+    let kept: Vec<Region> = regions
+        .into_iter()
+        .filter(|&region| region_entropy(img, region, order).channels().iter().any(|&entropy| entropy >= threshold))
+        .collect();
+    if kept.is_empty() {
+        return Ok(None);
+    }
+
+    let (mut red, mut green, mut blue) = (Vec::new(), Vec::new(), Vec::new());
+    for &(x, y, width, height) in &kept {
+        let (tile_red, tile_green, tile_blue) = split_rgb_channels(&img.crop_imm(x, y, width, height), order);
+        red.extend(tile_red);
+        green.extend(tile_green);
+        blue.extend(tile_blue);
+    }
+    Ok(Some(ContentEntropy {
+        entropy: TileEntropy {
+            red_entropy: calculate_entropy(&red),
+            green_entropy: calculate_entropy(&green),
+            blue_entropy: calculate_entropy(&blue),
+        },
+        kept_tiles: kept.len(),
+        total_tiles,
+        kept_pixels: red.len() as u64,
+    }))
+}
+
+/// A rectangle of an image as x, y, width and height.
+type Region = (u32, u32, u32, u32);
+
+/// Each tile of a `columns` by `rows` grid, row by row.
+fn tile_regions(img: &DynamicImage, columns: u32, rows: u32) -> Result<Vec<Vec<Region>>, String> {
+    let (width, height) = img.dimensions();
+    if columns == 0 || rows == 0 || columns > width || rows > height {
+        return Err(format!(
+            "Error: A {}x{} tile grid doesn't fit a {}x{} image.",
+            columns, rows, width, height
+        ));
+    }
+    let (tile_width, tile_height) = (width / columns, height / rows);
+
+    let grid = (0..rows)
+        .map(|row| {
+            let y = row * tile_height;
+            let h = if row == rows - 1 { height - y } else { tile_height };
+            (0..columns)
+                .map(|column| {
+                    let x = column * tile_width;
+                    let w = if column == columns - 1 { width - x } else { tile_width };
+                    (x, y, w, h)
+                })
+                .collect()
+        })
+        .collect();
+    Ok(grid)
+}
+
+/// Side length of a JPEG DCT block, in pixels.
+pub const JPEG_BLOCK_SIZE: u32 = 8;
+
+/// Measures the entropy of every `JPEG_BLOCK_SIZE` square block on the grid JPEG uses, starting at
+/// the top-left corner, returned row by row. Blocks on the right and bottom edges are cut short
+/// where the image ends. Chroma subsampling is not modeled; every channel uses the full-size grid.
+pub fn jpeg_block_entropy(img: &DynamicImage, order: ChannelOrder) -> Vec<Vec<TileEntropy>> {
+    let (width, height) = img.dimensions();
+    (0..height)
+        .step_by(JPEG_BLOCK_SIZE as usize)
+        .map(|y| {
+            (0..width)
+                .step_by(JPEG_BLOCK_SIZE as usize)
+                .map(|x| {
+                    let region = (x, y, JPEG_BLOCK_SIZE.min(width - x), JPEG_BLOCK_SIZE.min(height - y));
+                    region_entropy(img, region, order)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Entropy of the channels within one region.
+fn region_entropy(img: &DynamicImage, (x, y, width, height): Region, order: ChannelOrder) -> TileEntropy {
+    let (red, green, blue) = split_rgb_channels(&img.crop_imm(x, y, width, height), order);
+    TileEntropy {
+        red_entropy: calculate_entropy(&red),
+        green_entropy: calculate_entropy(&green),
+        blue_entropy: calculate_entropy(&blue),
+    }
+}
+
+/// Compresses the image using lossless WebP compression.
+pub fn webp_compress(image: &DynamicImage) -> Result<usize, String> {
+    webp_encode_lossless(image).map(|data| data.len())
+}
+
+/// Compresses the image using QOI, as RGBA when it has alpha and as RGB otherwise.
+pub fn qoi_compress(image: &DynamicImage) -> usize {
+    let (width, height) = image.dimensions();
+    let pixels = if image.color().has_alpha() {
+        image.to_rgba8().into_raw()
+    } else {
+        image.to_rgb8().into_raw()
+    };
+    qoi::encode_to_vec(&pixels, width, height).expect("Failed to encode QOI").len()
+}
+
+/// Compresses entropy results using Prediction by Partial Matching (PPM).
+///
+/// Symbols unseen in a context are coded with an escape (PPM method C) and retried in the
+/// next lower order; symbols unseen even in order 0 cost 8 bits under the order -1 model.
+pub fn ppm_compress(image_data: &[u8], warmup: PpmWarmup) -> usize {
+    ppm_compress_order(image_data, warmup, PPM_ORDER)
+}
+
+/// `ppm_compress` with contexts of up to `max_order` symbols instead of `PPM_ORDER`.
+pub fn ppm_compress_order(image_data: &[u8], warmup: PpmWarmup, max_order: usize) -> usize {
+    let mut model = PpmModel::new(max_order);
+    let compressed_size = model.code(image_data, warmup);
+    if model.limit_reached() {
+        warn!(
+            "The PPM model reached its limit of {} contexts; later symbols fell back to lower orders, \
+             so the compressed size is somewhat overestimated.",
+            PPM_MAX_CONTEXTS
+        );
+    }
+    compressed_size
+}
+
+/// The adaptive statistics behind the PPM estimate: for every context of up to `max_order`
+/// preceding bytes, how often each byte followed it. A model trained on one image can be
+/// cloned to code another, which measures how much the two have in common.
+///
+/// Each order has its own map keyed by the context bytes packed into a `u64`, so looking up or
+/// adding a context neither allocates nor hashes a variable-length key. The order tells contexts
+/// that pack to the same integer apart, such as `[0, 7]` and `[7]`.
+#[derive(Clone, Debug)]
+pub struct PpmModel {
+    max_order: usize,
+    context_maps: Vec<HashMap<u64, HashMap<u8, usize>>>,
+    context_count: usize,
+    context_limit_reached: bool,
+}
+
+/// Packs a context of at most `PPM_MAX_ORDER` bytes into an integer, the oldest byte highest.
+fn pack_context(context: &[u8]) -> u64 {
+    context.iter().fold(0, |key, &byte| key << 8 | byte as u64)
+}
+
+/// Reverses `pack_context` for a context of `order` bytes.
+fn unpack_context(key: u64, order: usize) -> Vec<u8> {
+    (0..order).rev().map(|shift| (key >> (8 * shift)) as u8).collect()
+}
+
+impl PpmModel {
+    /// An empty model using contexts of up to `max_order` bytes.
+    ///
+    /// # Panics
+    ///
+    /// If `max_order` exceeds `PPM_MAX_ORDER`.
+    pub fn new(max_order: usize) -> Self {
+        assert!(max_order <= PPM_MAX_ORDER, "PPM order {} exceeds the maximum of {}", max_order, PPM_MAX_ORDER);
+        PpmModel {
+            max_order,
+            context_maps: vec![HashMap::new(); max_order + 1],
+            context_count: 0,
+            context_limit_reached: false,
+        }
+    }
+
+    /// Builds a model from stored context counts, as listed by `contexts`. Contexts longer than
+    /// `max_order` can never be looked up, so they are dropped.
+    pub fn from_contexts(max_order: usize, contexts: impl IntoIterator<Item = (Vec<u8>, HashMap<u8, usize>)>) -> Self {
+        let mut model = PpmModel::new(max_order);
+        for (context, counts) in contexts {
+            if let Some(context_map) = model.context_maps.get_mut(context.len()) {
+                context_map.insert(pack_context(&context), counts);
+            }
+        }
+        model.context_count = model.context_maps.iter().map(HashMap::len).sum();
+        model
+    }
+
+    pub fn max_order(&self) -> usize {
+        self.max_order
+    }
+
+    /// Every context with the counts of the bytes seen after it.
+    pub fn contexts(&self) -> impl Iterator<Item = (Vec<u8>, &HashMap<u8, usize>)> {
+        self.context_maps
+            .iter()
+            .enumerate()
+            .flat_map(|(order, context_map)| {
+                context_map.iter().map(move |(&key, counts)| (unpack_context(key, order), counts))
+            })
+    }
+
+    /// Whether a context was dropped because the model held `PPM_MAX_CONTEXTS` already.
+    pub fn limit_reached(&self) -> bool {
+        self.context_limit_reached
+    }
+
+    /// Estimates the coded size of the data in bytes, learning from every byte as it goes.
+    pub fn code(&mut self, image_data: &[u8], warmup: PpmWarmup) -> usize {
+        let max_order = self.max_order;
+        let mut compressed_bits = 0;
+        let lowest_order = match warmup {
+            PpmWarmup::Backoff => 0,
+            PpmWarmup::Exclude => max_order,
+        };
+
+        for (i, &value) in image_data.iter().enumerate() {
+            let highest_order = max_order.min(i);
+
+            if highest_order >= lowest_order {
+                let mut bits = 0.0;
+                let mut coded = false;
+
+                for order in (lowest_order..=highest_order).rev() {
+                    let key = pack_context(&image_data[i - order..i]);
+                    let Some(context_freq) = self.context_maps[order].get(&key) else {
+                        continue; // Context never seen, nothing to escape from
+                    };
+                    let total_freq: usize = context_freq.values().sum();
+                    let denominator = (total_freq + context_freq.len()) as f64;
+
+                    if let Some(&count) = context_freq.get(&value) {
+                        bits -= (count as f64 / denominator).log2();
+                        coded = true;
+                        break;
+                    }
+                    bits -= (context_freq.len() as f64 / denominator).log2(); // Escape
+                }
+                if !coded {
+                    bits += 8.0; // Order -1: uniform over all byte values
+                }
+
+                compressed_bits += bits.ceil() as usize;
+            }
+
+            for order in lowest_order..=highest_order {
+                let key = pack_context(&image_data[i - order..i]);
+                let full = self.context_count >= PPM_MAX_CONTEXTS;
+                match self.context_maps[order].get_mut(&key) {
+                    Some(context_freq) => *context_freq.entry(value).or_insert(0) += 1,
+                    None if !full => {
+                        self.context_maps[order].entry(key).or_default().insert(value, 1);
+                        self.context_count += 1;
+                    },
+                    None => self.context_limit_reached = true,
+                }
+            }
+        }
+        compressed_bits.div_ceil(8)
+    }
+}
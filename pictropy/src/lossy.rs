@@ -0,0 +1,201 @@
+use clap::ValueEnum;
+use image::{DynamicImage, GenericImageView, ImageFormat, ImageOutputFormat, RgbImage};
+use image::imageops;
+use rayon::prelude::*;
+
+/// Factor the per-sample error is multiplied by in the difference panel of a comparison image.
+pub const DIFFERENCE_GAIN: u8 = 8;
+
+/// Lossy encoders available for size/quality trade-off analysis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LossyFormat {
+    /// Baseline JPEG via the `image` crate
+    Jpeg,
+    /// Lossy WebP via libwebp
+    Webp,
+}
+
+impl LossyFormat {
+    /// Display name of the format.
+    pub fn name(self) -> &'static str {
+        match self {
+            LossyFormat::Jpeg => "JPEG",
+            LossyFormat::Webp => "WebP",
+        }
+    }
+}
+
+/// Result of encoding an image at a single quality setting.
+pub struct LossyEncoding {
+    pub quality: u8,
+    pub data: Vec<u8>,
+}
+
+/// Encodes the image at the given quality (1-100). Alpha is dropped, as JPEG cannot store it.
+pub fn lossy_encode(image: &DynamicImage, format: LossyFormat, quality: u8) -> Result<Vec<u8>, String> {
+    let rgb_image = DynamicImage::ImageRgb8(image.to_rgb8());
+    match format {
+        LossyFormat::Jpeg => {
+            let mut data = Vec::new();
+            rgb_image
+                .write_to(&mut data, ImageOutputFormat::Jpeg(quality))
+                .map_err(|err| format!("Error: JPEG encoding failed: {}", err))?;
+            Ok(data)
+        },
+        LossyFormat::Webp => {
+            crate::check_webp_dimensions(&rgb_image)?;
+            let encoder = webp::Encoder::from_image(&rgb_image)
+                .map_err(|err| format!("Error: WebP encoding failed: {}", err))?;
+            Ok(encoder.encode(quality as f32).to_vec())
+        },
+    }
+}
+
+/// Decodes data produced by `lossy_encode` back into pixels.
+pub fn lossy_decode(data: &[u8], format: LossyFormat) -> Result<DynamicImage, String> {
+    match format {
+        LossyFormat::Jpeg => image::load_from_memory_with_format(data, ImageFormat::Jpeg)
+            .map_err(|err| format!("Error: JPEG decoding failed: {}", err)),
+        LossyFormat::Webp => webp::Decoder::new(data)
+            .decode()
+            .map(|decoded| decoded.to_image())
+            .ok_or_else(|| String::from("Error: WebP decoding failed.")),
+    }
+}
+
+/// Peak signal-to-noise ratio in dB between two equally sized images, over their RGB samples.
+/// Identical images yield infinity.
+pub fn psnr(original: &DynamicImage, distorted: &DynamicImage) -> f64 {
+    let original = original.to_rgb8();
+    let distorted = distorted.to_rgb8();
+
+    let squared_error: f64 = original
+        .as_raw()
+        .iter()
+        .zip(distorted.as_raw())
+        .map(|(&a, &b)| {
+            let difference = a as f64 - b as f64;
+            difference * difference
+        })
+        .sum();
+    let mean_squared_error = squared_error / original.as_raw().len().max(1) as f64;
+
+    if mean_squared_error == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (255.0 * 255.0 / mean_squared_error).log10()
+    }
+}
+
+/// Size and fidelity of the image encoded at one quality setting.
+pub struct RateDistortionPoint {
+    pub quality: u8,
+    pub size: usize,
+    /// PSNR against the original in dB; infinite when the encoding is exact
+    pub psnr: f64,
+}
+
+/// Qualities visited by a rate-distortion sweep: 10, 20 and so on up to 100.
+pub const SWEEP_QUALITIES: [u8; 10] = [10, 20, 30, 40, 50, 60, 70, 80, 90, 100];
+
+/// Encodes the image at the given quality and measures the size and PSNR of the result.
+pub fn rate_distortion_point(image: &DynamicImage, format: LossyFormat, quality: u8) -> Result<RateDistortionPoint, String> {
+    let encoding = LossyEncoding { quality, data: lossy_encode(image, format, quality)? };
+    let psnr = encoding_psnr(image, &encoding, format)?;
+    Ok(RateDistortionPoint { quality, size: encoding.data.len(), psnr })
+}
+
+/// Encodes the image at every quality in `SWEEP_QUALITIES`, one quality per rayon task.
+pub fn sweep(image: &DynamicImage, format: LossyFormat) -> Result<Vec<RateDistortionPoint>, String> {
+    SWEEP_QUALITIES
+        .into_par_iter()
+        .map(|quality| rate_distortion_point(image, format, quality))
+        .collect()
+}
+
+/// The smallest point whose PSNR is at least `min_psnr`, the largest saving still acceptable.
+pub fn best_acceptable_point(points: &[RateDistortionPoint], min_psnr: f64) -> Option<&RateDistortionPoint> {
+    points.iter().filter(|point| point.psnr >= min_psnr).min_by_key(|point| point.size)
+}
+
+/// Binary-searches for the highest quality whose encoding fits within `target_size` bytes.
+/// Returns `None` when even the lowest quality is too large.
+pub fn search_quality_for_size(
+    image: &DynamicImage,
+    format: LossyFormat,
+    target_size: usize,
+) -> Result<Option<LossyEncoding>, String> {
+    let (mut low, mut high) = (1u8, 100u8);
+    let mut best = None;
+
+    while low <= high {
+        let quality = low + (high - low) / 2;
+        let data = lossy_encode(image, format, quality)?;
+        if data.len() <= target_size {
+            best = Some(LossyEncoding { quality, data });
+            low = quality + 1;
+        } else if quality == 1 {
+            break;
+        } else {
+            high = quality - 1;
+        }
+    }
+
+    Ok(best)
+}
+
+/// Runs `search_quality_for_size` for every lossy format, one format per rayon task, and measures
+/// the PSNR of each fitting encoding. Formats that can't fit the budget are left out, and the rest
+/// are sorted from the highest PSNR, the fewest artifacts, to the lowest.
+pub fn search_formats_for_size(
+    image: &DynamicImage,
+    target_size: usize,
+) -> Result<Vec<(LossyFormat, RateDistortionPoint)>, String> {
+    let mut candidates = LossyFormat::value_variants()
+        .par_iter()
+        .map(|&format| {
+            let Some(encoding) = search_quality_for_size(image, format, target_size)? else {
+                return Ok(None);
+            };
+            let psnr = encoding_psnr(image, &encoding, format)?;
+            Ok(Some((format, RateDistortionPoint { quality: encoding.quality, size: encoding.data.len(), psnr })))
+        })
+        .collect::<Result<Vec<_>, String>>()?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    candidates.sort_by(|(_, a), (_, b)| b.psnr.total_cmp(&a.psnr));
+    Ok(candidates)
+}
+
+/// Re-decodes an encoding and measures its PSNR against the original image.
+pub fn encoding_psnr(image: &DynamicImage, encoding: &LossyEncoding, format: LossyFormat) -> Result<f64, String> {
+    let decoded = lossy_decode(&encoding.data, format)?;
+    if decoded.dimensions() != image.dimensions() {
+        return Err(String::from("Error: Decoded image dimensions differ from the original."));
+    }
+    Ok(psnr(image, &decoded))
+}
+
+/// Composites the original, the re-decoded encoding and their amplified absolute difference side by side.
+pub fn comparison_image(original: &DynamicImage, decoded: &DynamicImage) -> Result<DynamicImage, String> {
+    let (width, height) = original.dimensions();
+    if decoded.dimensions() != (width, height) {
+        return Err(String::from("Error: Decoded image dimensions differ from the original."));
+    }
+    let original = original.to_rgb8();
+    let decoded = decoded.to_rgb8();
+
+    let mut difference = RgbImage::new(width, height);
+    for ((target, expected), actual) in difference.pixels_mut().zip(original.pixels()).zip(decoded.pixels()) {
+        for channel in 0..3 {
+            target.0[channel] = expected.0[channel].abs_diff(actual.0[channel]).saturating_mul(DIFFERENCE_GAIN);
+        }
+    }
+
+    let mut composite = RgbImage::new(width * 3, height);
+    imageops::replace(&mut composite, &original, 0, 0);
+    imageops::replace(&mut composite, &decoded, width, 0);
+    imageops::replace(&mut composite, &difference, width * 2, 0);
+    Ok(DynamicImage::ImageRgb8(composite))
+}
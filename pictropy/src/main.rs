@@ -1,211 +1,2945 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::fs;
 use std::io::{self};
-use image::{DynamicImage, GenericImageView};
-use log::{info, error};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap::parser::ValueSource;
+use image::{ColorType, DynamicImage, GenericImageView, ImageFormat};
+use log::{info, warn, error};
 use prettytable::{Table, Row, Cell};
+use rayon::prelude::*;
+use pictropy::{
+    analyze, check_max_pixels, colorspace_planes, file_extension, calculate_entropy, calculate_symbol_entropy,
+    channel_label, count_unique_colors, icc, gradient_entropy, histogram_variance, image_dimensions,
+    interleaved_entropy, is_16_bit, jpeg, jpeg_block_entropy, mutual_information, png, ppm_compress,
+    ppm_compress_order, pyramid_entropy, qoi_compress, decode_image_file, content_entropy, decode_image_file_by_content,
+    split_all_channels, split_rgb_channels, tile_entropy, top_values, webp_compress, AnalysisOptions, AnalysisResult,
+    ChannelOrder, Colorspace, Compressor, EntropyUnit, PpmModel, PpmWarmup, RgbChannels, Smoothing, TileEntropy,
+    IMAGE_EXTENSIONS, JPEG_BLOCK_SIZE, PPM_MAX_CONTEXTS, PPM_MAX_ORDER, PPM_ORDER,
+};
+use pictropy::analysis::{self, CompressedSizes};
+use pictropy::apng;
+use pictropy::borders::{self, Borders};
+use pictropy::cache::{self, CachedAnalysis};
+use pictropy::compare::{self, ReportEntry};
+use pictropy::denoise::{self, DenoiseFilter};
+use pictropy::encode::{self, EncodeFormat};
+use pictropy::fixtures;
+use pictropy::float::{self, FLOAT_EXTENSIONS};
+use pictropy::lossy::{self, LossyFormat, RateDistortionPoint};
+use pictropy::metadata;
+use pictropy::png::PngFilter;
+use pictropy::ppm_model::{self, ChannelModels};
+use pictropy::quantize;
+use pictropy::rate;
+use pictropy::recommend::{self, LossyOption, Metrics};
+use pictropy::reference::{self, ReferenceDistribution};
+use pictropy::serve;
+use pictropy::signed;
+use pictropy::stats_db::{self, AnalysisRecord};
+#[cfg(feature = "raw")]
+use pictropy::raw;
+#[cfg(feature = "clipboard")]
+use pictropy::clipboard;
 
-/// Reads an image from the specified file path, returning a Result to handle errors gracefully.
-fn read_image(image_path: &str) -> Result<DynamicImage, String> {
-    image::open(image_path).map_err(|_| {
-        format!(
-            "Error: Unable to open the image file '{}'. Please ensure it exists and is a valid JPG or PNG.",
-            image_path
-        )
-    })
+mod output;
+
+use output::{
+    Configuration, CsvWriter, DocumentSyntax, DocumentWriter, FileReport, FileResults, HtmlWriter, LineWriter,
+    MarkdownWriter, OutputWriter, PrometheusWriter, StoredChannel, TableWriter, Warning, WarningCode,
+};
+
+/// Extensions of camera RAW files, which are analyzed as undemosaiced sensor data.
+const RAW_EXTENSIONS: [&str; 5] = ["dng", "cr2", "nef", "arw", "raw"];
+
+/// Interval between "still decoding" log lines while a large image decodes.
+const DECODE_HEARTBEAT: Duration = Duration::from_secs(5);
+
+/// Exit code when --alert-exit-code is given and a channel fell outside the alert band.
+const ALERT_EXIT_CODE: i32 = 3;
+
+/// Length of the longest --entropy-histogram bar, in characters.
+const HISTOGRAM_BAR_WIDTH: usize = 40;
+
+/// Set once any channel of any analyzed image trips --alert-below or --alert-above.
+static ALERT_RAISED: AtomicBool = AtomicBool::new(false);
+
+/// Pixels and file bytes of the images timed so far, for the --timing run total.
+static TIMED_PIXELS: AtomicU64 = AtomicU64::new(0);
+static TIMED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// The --train-ppm models, trained on each image in turn and saved when the run finishes.
+static TRAINED_PPM_MODELS: Mutex<Option<ChannelModels>> = Mutex::new(None);
+
+/// The --use-ppm-model models, loaded once before the first image.
+static LOADED_PPM_MODELS: OnceLock<ChannelModels> = OnceLock::new();
+
+/// The --reference distribution, loaded once before the first image.
+static LOADED_REFERENCE: OnceLock<ReferenceDistribution> = OnceLock::new();
+
+/// Image entropy calculator.
+#[derive(Parser, Debug)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Path to the image file or a directory of images (prompted for on stdin when omitted)
+    path: Option<PathBuf>,
+
+    /// Analyze the image on the clipboard instead of a file; its size is that of the image encoded as PNG
+    #[arg(long, conflicts_with_all = ["path", "jpeg_dct", "png_idat", "apng_frames", "cache", "compare_original_format"])]
+    clipboard: bool,
+
+    /// Analyze the images listed one path per line in this file, or on stdin when it is `-`, as a batch
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["path", "clipboard"])]
+    files_from: Option<PathBuf>,
+
+    /// List the files a run would analyze, after --include and --exclude, with their sizes, without
+    /// decoding any of them
+    #[arg(long, conflicts_with = "clipboard")]
+    dry_run: bool,
+
+    /// Preset option bundle for a kind of content; options given explicitly still win, and the
+    /// --no-* flags turn off a preset's filters and switches
+    #[arg(long, value_enum)]
+    profile: Option<Profile>,
+
+    /// Also estimate the PPM size at every context order from 0 to this one, in parallel, and
+    /// report the best
+    #[arg(long, value_name = "MAX_ORDER", value_parser = clap::value_parser!(u64).range(..=PPM_MAX_ORDER as u64))]
+    ppm_order_sweep: Option<u64>,
+
+    /// How PPM codes the first symbols, before a full-order context exists
+    #[arg(long, value_enum, default_value_t = PpmWarmup::Backoff)]
+    ppm_warmup: PpmWarmup,
+
+    /// Train a PPM model for each channel on every analyzed image and save it to this file at the end
+    #[arg(long, value_name = "MODEL", conflicts_with_all = ["use_ppm_model", "jpeg_dct", "png_idat", "apng_frames"])]
+    train_ppm: Option<PathBuf>,
+
+    /// Also estimate each image's PPM size when coded by a model saved with --train-ppm, measuring
+    /// how much the images have in common
+    #[arg(long, value_name = "MODEL")]
+    use_ppm_model: Option<PathBuf>,
+
+    /// Analyze the quantized DCT coefficients stored in a JPEG instead of its decoded pixels
+    #[arg(long)]
+    jpeg_dct: bool,
+
+    /// Analyze the inflated, filtered PNG scanline bytes instead of the decoded pixels
+    #[arg(long)]
+    png_idat: bool,
+
+    /// Analyze each frame of an animated PNG and the difference from the frame before it
+    #[arg(long, conflicts_with_all = ["jpeg_dct", "png_idat"])]
+    apng_frames: bool,
+
+    /// Number of equal-width bins float samples of HDR images are quantized into before their entropy is measured
+    #[arg(long, value_name = "N", default_value_t = 256, value_parser = clap::value_parser!(u32).range(2..=1 << 24))]
+    float_bins: u32,
+
+    /// Report sizes in bytes or relative to the original file size
+    #[arg(long, value_enum, default_value_t = RelativeTo::Absolute)]
+    relative_to: RelativeTo,
+
+    /// Find the highest lossy quality whose encoding fits within this many bytes
+    #[arg(long, value_name = "BYTES")]
+    target_size: Option<usize>,
+
+    /// Search every lossy format for the highest quality that fits within this many bytes and report
+    /// the one with the best PSNR
+    #[arg(long, value_name = "BYTES")]
+    compression_target: Option<usize>,
+
+    /// Lossy encoder used by --target-size and --compare-lossy-sweep
+    #[arg(long, value_enum, default_value_t = LossyFormat::Jpeg)]
+    lossy_format: LossyFormat,
+
+    /// Write a PNG of the original, the --target-size encoding and their amplified difference side by side
+    #[arg(long, value_name = "PATH", requires = "target_size")]
+    comparison_image: Option<PathBuf>,
+
+    /// Encode at qualities 10, 20, ..., 100 with --lossy-format and report the size and PSNR of each
+    #[arg(long)]
+    compare_lossy_sweep: bool,
+
+    /// Also write the --compare-lossy-sweep rate-distortion points to this CSV file, for plotting
+    #[arg(long, value_name = "PATH", requires = "compare_lossy_sweep")]
+    sweep_csv: Option<PathBuf>,
+
+    /// Sweep the --lossy-format qualities and pick the smallest encoding that keeps --min-psnr; with
+    /// --output, write that encoding instead of the usual one
+    #[arg(long, conflicts_with_all = ["output_format", "output_quality"])]
+    output_quality_table: bool,
+
+    /// Lowest PSNR, in dB, that --output-quality-table accepts
+    #[arg(long, value_name = "DB", default_value_t = recommend::MIN_LOSSY_PSNR, requires = "output_quality_table")]
+    min_psnr: f64,
+
+    /// Treat pixel values as sRGB even when an embedded ICC profile says otherwise
+    #[arg(long)]
+    assume_srgb: bool,
+
+    /// Report how many bytes of the file are metadata (EXIF, XMP, ICC and the like) rather than image data
+    #[arg(long, conflicts_with = "clipboard")]
+    analyze_metadata: bool,
+
+    /// Refuse images with more than this many pixels (checked before decoding)
+    #[arg(long, value_name = "N")]
+    max_pixels: Option<u64>,
+
+    /// Abort the analysis if it runs longer than this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+
+    /// Report the wall-clock time of each image and the run, with throughput in megapixels and megabytes per second
+    #[arg(long)]
+    timing: bool,
+
+    /// Order of the color samples in the decoded data, for correct channel labels
+    #[arg(long, value_enum, default_value_t = ChannelOrder::Rgb)]
+    channel_order: ChannelOrder,
+
+    /// Interpret the decoded buffer as this many channels, overriding the detected color type
+    #[arg(long, value_name = "N", value_parser = parse_channel_count)]
+    assume_channels: Option<usize>,
+
+    /// Leave fully transparent pixels out of the color histograms
+    #[arg(long, conflicts_with = "assume_channels")]
+    ignore_transparent: bool,
+
+    /// Keep fully transparent pixels in the histograms, even when --profile leaves them out
+    #[arg(long, overrides_with = "ignore_transparent")]
+    no_ignore_transparent: bool,
+
+    /// Append this analysis to a SQLite database of past runs (created if absent)
+    #[arg(long, value_name = "PATH", conflicts_with = "skip_entropy")]
+    stats_db: Option<PathBuf>,
+
+    /// When analyzing a directory, stop at the first file that fails instead of reporting it and moving on
+    #[arg(long)]
+    strict: bool,
+
+    /// When analyzing a directory, walk only files with these extensions instead of every supported one
+    #[arg(long, value_name = "EXT,...", value_delimiter = ',')]
+    include: Vec<String>,
+
+    /// When analyzing a directory, skip files with these extensions
+    #[arg(long, value_name = "EXT,...", value_delimiter = ',')]
+    exclude: Vec<String>,
+
+    /// Warn when a channel's entropy, in the chosen --units, is below this (a blank or corrupt channel)
+    #[arg(long, value_name = "X", conflicts_with = "skip_entropy")]
+    alert_below: Option<f64>,
+
+    /// Warn when a channel's entropy, in the chosen --units, is above this (likely noise)
+    #[arg(long, value_name = "Y", conflicts_with = "skip_entropy")]
+    alert_above: Option<f64>,
+
+    /// Exit with code 3 when any channel triggered --alert-below or --alert-above
+    #[arg(long)]
+    alert_exit_code: bool,
+
+    /// Echo the effective analysis configuration (compressor, PPM order, channel handling, smoothing...)
+    /// with the results, so a report records how to reproduce it
+    #[arg(long)]
+    print_flags: bool,
+
+    /// Include a BLAKE3 hash of each file's content in the results, to identify files across renames
+    #[arg(long)]
+    with_hash: bool,
+
+    /// Skip the file extension check and decode whatever the content turns out to be, reporting the
+    /// decoder's own error if that fails (combine with --include to walk other extensions)
+    #[arg(long)]
+    force: bool,
+
+    /// Output format for the results
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+
+    /// Unit of the displayed entropy; sizes are always derived from bits
+    #[arg(long, value_enum, default_value_t = EntropyUnit::Bits)]
+    units: EntropyUnit,
+
+    /// Leave out the results table and print only the summary lines
+    #[arg(long)]
+    no_table: bool,
+
+    /// Also report the smallest size any compressor (PPM, WebP, QOI, gzip) reaches as a fraction of the
+    /// file size, an approximation of normalized Kolmogorov complexity
+    #[arg(long)]
+    complexity: bool,
+
+    /// Report entropy at this many levels of a 2x downscaling pyramid; 32 levels take any image
+    /// down to a single row or column
+    #[arg(long, value_name = "LEVELS", value_parser = clap::value_parser!(u32).range(1..=32))]
+    pyramid: Option<u32>,
+
+    /// Don't report the downscaling pyramid, even when --profile asks for it
+    #[arg(long, overrides_with = "pyramid")]
+    no_pyramid: bool,
+
+    /// Also convert the pixels to this color space and report the entropy of each of its planes
+    #[arg(long, value_enum)]
+    colorspace: Option<Colorspace>,
+
+    /// Print a TSV matrix of per-tile entropy for each channel, splitting the image into W by H tiles
+    #[arg(long, value_name = "WxH", value_parser = parse_tile_grid)]
+    tile_grid: Option<TileGrid>,
+
+    /// With --tile-grid, report the entropy of the tiles at or above this many bits in some channel
+    /// instead of the matrix, leaving uniform background tiles out
+    #[arg(long, value_name = "BITS", requires = "tile_grid")]
+    exclude_uniform_regions: Option<f64>,
+
+    /// Report the distribution of entropy over the 8x8 pixel blocks of JPEG's DCT grid
+    #[arg(long, conflicts_with = "tile_grid")]
+    jpeg_blocks: bool,
+
+    /// Also report the entropy of every channel the image actually stores, including gray and alpha
+    #[arg(long)]
+    all_channels: bool,
+
+    /// With --all-channels, list only the K highest- and K lowest-entropy channels plus their
+    /// mean, once there are more than 2K
+    #[arg(long, value_name = "K", requires = "all_channels", value_parser = clap::value_parser!(u32).range(1..))]
+    limit_channels_output: Option<u32>,
+
+    /// Also report the entropy of the interleaved RGBA byte stream as a whole
+    #[arg(long)]
+    channels_combined: bool,
+
+    /// Don't report the combined byte stream, even when --profile asks for it
+    #[arg(long, overrides_with = "channels_combined")]
+    no_channels_combined: bool,
+
+    /// Also report the entropy of the luma's Sobel gradient magnitude, which rises with edges and fine detail
+    #[arg(long)]
+    gradient_entropy: bool,
+
+    /// Also print a matrix of pairwise mutual information between the color channels, with each
+    /// channel's entropy on the diagonal
+    #[arg(long, conflicts_with = "skip_entropy")]
+    show_channel_correlation_matrix: bool,
+
+    /// Also estimate each channel's entropy rate from gzip sizes of growing prefixes, which
+    /// accounts for the long-range correlations that order-0 entropy ignores
+    #[arg(long)]
+    entropy_rate: bool,
+
+    /// Also list each channel's K most frequent byte values with their frequency and share of the entropy
+    #[arg(long, value_name = "K", value_parser = clap::value_parser!(u16).range(1..=256))]
+    top_values: Option<u16>,
+
+    /// Also report the variance of each channel's histogram-bin probabilities, which tells a few
+    /// dominant values (high) from an even spread such as noise (low) at similar entropy
+    #[arg(long)]
+    channel_histogram_entropy_variance: bool,
+
+    /// Also suggest how to store the image, from its colors, entropy and trial encodings, listing
+    /// the metrics behind the advice
+    #[arg(long)]
+    recommend: bool,
+
+    /// Also report the number of distinct colors, to judge whether an indexed (palette) PNG would fit
+    #[arg(long)]
+    unique_colors: bool,
+
+    /// Stop counting unique colors past this many and report only that the image has more
+    #[arg(long, value_name = "N", default_value_t = 256, requires = "unique_colors")]
+    max_unique_colors: usize,
+
+    /// Count every unique color instead of stopping at --max-unique-colors
+    #[arg(long, requires = "unique_colors", conflicts_with = "max_unique_colors")]
+    exact_unique_colors: bool,
+
+    /// Analyze only every Rth pixel (a fixed stride) for a fast estimate
+    #[arg(long, value_name = "R", default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    sample_rate: u64,
+
+    /// Also measure entropy after this denoising filter and report the drop
+    #[arg(long, value_enum, conflicts_with = "skip_entropy")]
+    denoise: Option<DenoiseFilter>,
+
+    /// Don't measure a denoised copy, even when --profile asks for it
+    #[arg(long, overrides_with = "denoise")]
+    no_denoise: bool,
+
+    /// Keep only the top N bits of each color channel before analysis, previewing posterization;
+    /// --output then writes the posterized image
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u8).range(1..=8),
+        conflicts_with_all = ["jpeg_dct", "png_idat", "apng_frames"],
+    )]
+    msb_only: Option<u8>,
+
+    /// Reduce the colors to an N-color palette by median cut and report the entropy of the palette
+    /// indices, the estimated indexed size and the PSNR of the reduced image
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(2..=256))]
+    palette_preview: Option<u16>,
+
+    /// Report the width of the uniform margins (such as a scanner's black frame) on each edge
+    #[arg(long, conflicts_with_all = ["jpeg_dct", "png_idat", "apng_frames"])]
+    detect_borders: bool,
+
+    /// Leave uniform margins out of the analysis, so only the content is measured; implies --detect-borders
+    #[arg(long, conflicts_with_all = ["jpeg_dct", "png_idat", "apng_frames"])]
+    trim_borders: bool,
+
+    /// Convert every image to 8-bit RGB before analysis, so files of mixed color types are measured alike
+    #[arg(long, conflicts_with_all = ["force_gray", "jpeg_dct", "png_idat", "apng_frames"])]
+    force_rgb: bool,
+
+    /// Convert every image to 8-bit grayscale before analysis, so files of mixed color types are measured alike
+    #[arg(long, conflicts_with_all = ["jpeg_dct", "png_idat", "apng_frames"])]
+    force_gray: bool,
+
+    /// Read 16-bit samples as signed integers, offset by 32768 into value order, as scientific TIFFs
+    /// such as elevation rasters store them
+    #[arg(long, conflicts_with_all = ["clipboard", "jpeg_dct", "png_idat", "apng_frames"])]
+    signed: bool,
+
+    /// Smoothing of the channel histograms, for a less biased estimate on small images
+    #[arg(long, value_enum, default_value_t = Smoothing::None)]
+    smoothing: Smoothing,
+
+    /// Pseudocount added to every byte value by --smoothing laplace
+    #[arg(long, value_name = "K", default_value_t = 1.0, value_parser = parse_pseudocount)]
+    smoothing_k: f64,
+
+    /// Report cross-entropy against a reference image, or a text file of red, green and blue lines of 256 counts
+    #[arg(long, value_name = "FILE", conflicts_with = "skip_entropy")]
+    reference: Option<PathBuf>,
+
+    /// Real compressor whose output size is reported
+    #[arg(long, value_enum, default_value_t = Compressor::Ppm)]
+    compressor: Compressor,
+
+    /// Skip the entropy and theoretical-minimum computation; report only the compressor's size
+    #[arg(long)]
+    skip_entropy: bool,
+
+    /// Report only entropy and the theoretical minimum, without running any compressor
+    #[arg(long, conflicts_with_all = ["skip_entropy", "compressor", "stats_db"])]
+    entropy_only: bool,
+
+    /// Reuse results stored in this directory, keyed by a hash of the file content and the analysis options
+    #[arg(long, value_name = "DIR")]
+    cache: Option<PathBuf>,
+
+    /// Re-encode the image in its own format at maximum effort and report whether that is smaller
+    #[arg(long)]
+    compare_original_format: bool,
+
+    /// Apply this PNG scanline filter to every row and report the entropy of the filtered stream
+    #[arg(long, value_enum, value_name = "FILTER")]
+    png_filter: Option<PngFilter>,
+
+    /// Re-encode the image as PNG in every color type that holds it exactly and report each size
+    #[arg(long)]
+    png_color_types: bool,
+
+    /// Compare this run with a previous --format json report and list files whose size or entropy changed
+    #[arg(long, value_name = "REPORT")]
+    compare_report: Option<PathBuf>,
+
+    /// After a batch, print a histogram and percentiles of the files' total entropy
+    #[arg(long)]
+    entropy_histogram: bool,
+
+    /// Number of equal-width bins of the --entropy-histogram, spanning the lowest to the highest entropy
+    #[arg(long, value_name = "N", default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..=100))]
+    histogram_bins: u32,
+
+    /// Write the analyzed image to this path
+    #[arg(long, value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// Format of the written image (defaults to the --output extension, else PNG)
+    #[arg(long, value_enum)]
+    output_format: Option<EncodeFormat>,
+
+    /// JPEG quality of the written image
+    #[arg(long, default_value_t = 90, value_parser = clap::value_parser!(u8).range(1..=100))]
+    output_quality: u8,
+}
+
+/// Auxiliary tasks besides analyzing images.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Write synthetic images with known entropy, for tests and as a fuzzing seed corpus
+    #[command(hide = true)]
+    GenFixtures {
+        /// Directory to write the images into (created if absent)
+        dir: PathBuf,
+    },
+    /// Serve the analysis over HTTP: POST an image file to /analyze to get its results as JSON
+    Serve {
+        /// Address and port to listen on
+        #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1:8080")]
+        listen: String,
+        /// Requests analyzed at once; further ones get 503 Service Unavailable
+        #[arg(long, value_name = "N", default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+        max_concurrent_requests: u32,
+        /// Largest upload accepted, in bytes; larger ones get 413 Content Too Large
+        #[arg(long, value_name = "BYTES", default_value_t = 64 << 20)]
+        max_request_bytes: usize,
+        /// Most pixels an upload's header may declare; larger ones get 413 Content Too Large
+        #[arg(long, value_name = "N", default_value_t = 50_000_000)]
+        max_pixels: u64,
+        /// Real compressor whose output size is reported
+        #[arg(long, value_enum, default_value_t = Compressor::Ppm)]
+        compressor: Compressor,
+    },
+    /// Combine `--format json` reports, such as those of shards scanned on several machines, into
+    /// one batch report with its summary recomputed
+    MergeReports {
+        /// Reports to combine; a file in more than one keeps its entry from the last
+        #[arg(required = true)]
+        reports: Vec<PathBuf>,
+    },
 }
 
-/// Calculates entropy of a given data array.
-fn calculate_entropy(image_data: &[u8]) -> f64 {
-    let mut histogram = HashMap::new();
-    let total_pixels = image_data.len() as f64;
+/// Named bundles of option defaults for common kinds of content.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Profile {
+    /// Camera photos: --denoise gaussian --pyramid 3 --sample-rate 4. The sampling makes every
+    /// entropy an estimate from a quarter of the pixels; give --sample-rate 1 for exact figures
+    Photo,
+    /// Screenshots and UI assets: --ignore-transparent --channels-combined --compressor webp
+    Screenshot,
+    /// Scanned documents: --denoise median --compressor webp
+    Document,
+}
+
+/// Option values a profile sets; `None` and `false` leave the option alone.
+#[derive(Default)]
+struct ProfileOptions {
+    denoise: Option<DenoiseFilter>,
+    pyramid: Option<u32>,
+    sample_rate: Option<u64>,
+    compressor: Option<Compressor>,
+    ignore_transparent: bool,
+    channels_combined: bool,
+}
 
-    for &value in image_data {
-        *histogram.entry(value).or_insert(0) += 1;
+impl Profile {
+    fn options(self) -> ProfileOptions {
+        match self {
+            Profile::Photo => ProfileOptions {
+                denoise: Some(DenoiseFilter::Gaussian),
+                pyramid: Some(3),
+                sample_rate: Some(4),
+                ..ProfileOptions::default()
+            },
+            Profile::Screenshot => ProfileOptions {
+                compressor: Some(Compressor::Webp),
+                ignore_transparent: true,
+                channels_combined: true,
+                ..ProfileOptions::default()
+            },
+            Profile::Document => ProfileOptions {
+                denoise: Some(DenoiseFilter::Median),
+                compressor: Some(Compressor::Webp),
+                ..ProfileOptions::default()
+            },
+        }
     }
 
-    histogram
-        .iter()
-        .map(|(_, &count)| {
-            let probability = count as f64 / total_pixels;
-            -probability * probability.log2()
-        })
-        .sum()
+    /// Fills in the profile's options wherever the command line didn't set them or turn them off.
+    fn apply(self, args: &mut Args, matches: &ArgMatches) {
+        let unset = |id: &str| matches!(matches.value_source(id), None | Some(ValueSource::DefaultValue));
+        let options = self.options();
+
+        // Skip options that would clash with ones given explicitly
+        if options.denoise.is_some() && unset("denoise") && unset("no_denoise") && !args.skip_entropy {
+            args.denoise = options.denoise;
+        }
+        if options.pyramid.is_some() && unset("pyramid") && unset("no_pyramid") {
+            args.pyramid = options.pyramid;
+        }
+        if let Some(sample_rate) = options.sample_rate
+            && unset("sample_rate")
+        {
+            args.sample_rate = sample_rate;
+        }
+        if let Some(compressor) = options.compressor
+            && unset("compressor")
+        {
+            args.compressor = compressor;
+        }
+        if options.ignore_transparent
+            && unset("ignore_transparent")
+            && unset("no_ignore_transparent")
+            && args.assume_channels.is_none()
+        {
+            args.ignore_transparent = true;
+        }
+        if options.channels_combined && unset("channels_combined") && unset("no_channels_combined") {
+            args.channels_combined = true;
+        }
+    }
 }
 
-/// Splits the image into its red, green, and blue color channels.
-fn split_rgb_channels(img: &DynamicImage) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
-    let (width, height) = img.dimensions();
-    let mut red_channel = Vec::with_capacity((width * height) as usize);
-    let mut green_channel = Vec::with_capacity((width * height) as usize);
-    let mut blue_channel = Vec::with_capacity((width * height) as usize);
+/// Presentation of the analysis results.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Formatted table followed by a prose summary
+    Table,
+    /// One space-separated line: filename total_entropy original_size webp_size savings_pct
+    Line,
+    /// Prometheus text exposition format, for node exporter textfile collectors
+    Prometheus,
+    /// Self-contained HTML report with the results table and per-channel histogram charts
+    Html,
+    /// JSON object per file; a directory yields one document with a "files" array and a "summary"
+    Json,
+    /// Comma-separated values with a header row, one row per file
+    Csv,
+    /// Markdown section with a results table per file
+    Markdown,
+    /// TOML document with the same fields as the JSON output
+    Toml,
+}
+
+impl OutputFormat {
+    /// Whether prose reports can share stdout with the results. Every other format is read by
+    /// programs or has a fixed structure that stray text would break.
+    fn allows_prose(self) -> bool {
+        self == OutputFormat::Table
+    }
+}
+
+/// Creates the writer for the selected output format; `batch` is set when a directory is analyzed.
+fn output_writer(args: &Args, batch: bool) -> Box<dyn OutputWriter> {
+    match args.format {
+        OutputFormat::Table => {
+            Box::new(TableWriter { relative_to: args.relative_to, unit: args.units, show_table: !args.no_table })
+        },
+        OutputFormat::Line => Box::new(LineWriter { unit: args.units }),
+        OutputFormat::Prometheus => Box::new(PrometheusWriter::default()),
+        OutputFormat::Html => Box::new(HtmlWriter::new(args.units, smoothing_description(args))),
+        OutputFormat::Json => Box::new(DocumentWriter::new(DocumentSyntax::Json, batch)),
+        OutputFormat::Toml => Box::new(DocumentWriter::new(DocumentSyntax::Toml, batch)),
+        OutputFormat::Csv => Box::new(CsvWriter::new(args.units, args.with_hash, args.all_channels)),
+        OutputFormat::Markdown => Box::new(MarkdownWriter { unit: args.units, smoothing: smoothing_description(args) }),
+    }
+}
 
-    for pixel in img.pixels() {
-        let [r, g, b, _] = pixel.2 .0; // Access inner array using `.0`
-        red_channel.push(r);
-        green_channel.push(g);
-        blue_channel.push(b);
+/// The settings that shape this run's figures, for --print-flags.
+fn configuration<'a>(args: &Args, options: &'a AnalysisOptions) -> Configuration<'a> {
+    Configuration {
+        version: env!("CARGO_PKG_VERSION"),
+        options,
+        ppm_order: PPM_ORDER,
+        ppm_max_contexts: PPM_MAX_CONTEXTS,
+        msb_only: args.msb_only,
+        trim_borders: args.trim_borders,
+        force_color: forced_color(args).map(|color| format!("{:?}", color)),
+        signed: args.signed,
+        units: args.units,
     }
+}
+
+/// Parses a --smoothing-k value, which must be a positive number.
+fn parse_pseudocount(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(pseudocount) if pseudocount > 0.0 && pseudocount.is_finite() => Ok(pseudocount),
+        _ => Err(String::from("must be a positive number")),
+    }
+}
 
-    (red_channel, green_channel, blue_channel)
+/// Number of tile columns and rows requested with --tile-grid.
+#[derive(Clone, Copy, Debug)]
+struct TileGrid {
+    columns: u32,
+    rows: u32,
 }
 
-/// Compresses entropy results using Prediction by Partial Matching (PPM).
-fn ppm_compress(image_data: &[u8]) -> usize {
-    let mut context_map: HashMap<Vec<u8>, HashMap<u8, usize>> = HashMap::new();
-    let mut compressed_size = 0;
+/// Parses a --tile-grid value such as `8x6`.
+fn parse_tile_grid(value: &str) -> Result<TileGrid, String> {
+    let invalid = || String::from("must be COLUMNSxROWS, e.g. 8x6, with both at least 1");
+    let (columns, rows) = value.split_once(['x', 'X']).ok_or_else(invalid)?;
+    match (columns.parse::<u32>(), rows.parse::<u32>()) {
+        (Ok(columns), Ok(rows)) if columns > 0 && rows > 0 => Ok(TileGrid { columns, rows }),
+        _ => Err(invalid()),
+    }
+}
 
-    for (i, &value) in image_data.iter().enumerate() {
-        let context = image_data[i.saturating_sub(3)..i].to_vec(); // Use last 3 bytes as context
-        let context_freq = context_map.entry(context).or_insert_with(HashMap::new);
-        *context_freq.entry(value).or_insert(0) += 1;
+/// Parses an --assume-channels value; only gray, RGB and RGBA layouts are meaningful.
+fn parse_channel_count(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(count @ (1 | 3 | 4)) => Ok(count),
+        _ => Err(String::from("must be 1, 3 or 4")),
+    }
+}
 
-        let total_freq: usize = context_freq.values().sum();
-        let prob = context_freq[&value] as f64 / total_freq as f64;
+/// Reference that reported sizes are expressed against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum RelativeTo {
+    /// Absolute byte counts
+    Absolute,
+    /// Percentages of the original file size
+    Original,
+}
 
-        compressed_size += prob.log2().abs().ceil() as usize; // Calculate compressed size
+impl RelativeTo {
+    /// Unit label for sizes rendered in this mode.
+    fn unit(self) -> &'static str {
+        match self {
+            RelativeTo::Absolute => "bytes",
+            RelativeTo::Original => "% of original",
+        }
     }
 
-    compressed_size
+    /// Renders a size in bytes with the given precision, or as a percentage of the original size.
+    fn render(self, size: f64, precision: usize, original_size: u64) -> String {
+        match self {
+            RelativeTo::Absolute => format!("{:.*}", precision, size),
+            RelativeTo::Original => format!("{:.2}", size / original_size as f64 * 100.0),
+        }
+    }
 }
 
-/// Displays results in a formatted table for better readability.
-fn display_results(
-    red_entropy: f64,
-    green_entropy: f64,
-    blue_entropy: f64,
+/// Per-channel entropy and the lossless size limit it implies.
+struct EntropyLimit {
+    channel_entropy: [f64; 3],
     total_entropy: f64,
-    original_size: usize,
-    theoretical_size: f64,
-    red_compressed_size: usize,
-    green_compressed_size: usize,
-    blue_compressed_size: usize,
+    theoretical_minimum_size: f64,
+    /// Size the order-0 entropy implies, before capping at the file size
+    entropy_size: f64,
     compression_percentage: f64,
-    model_message: &str,
+    /// Whether the entropy implied a size above the file's, so the limit was capped at the file size
+    capped: bool,
+}
+
+impl EntropyLimit {
+    /// Derives the theoretical lossless limit for the given file from its channel entropies.
+    fn new(channel_entropy: [f64; 3], total_pixels: f64, file_size: u64) -> Self {
+        let total_entropy = channel_entropy.iter().sum::<f64>();
+
+        // Calculate theoretical lossless limit
+        let entropy_size = (total_entropy * total_pixels) / 8.0;
+        let mut theoretical_minimum_size = entropy_size;
+
+        // Ensure theoretical size does not exceed original size
+        let capped = theoretical_minimum_size > file_size as f64;
+        if capped {
+            theoretical_minimum_size = file_size as f64;
+        }
+
+        // Calculate compression percentage
+        let compression_percentage = if theoretical_minimum_size > file_size as f64 {
+            0.0 // No further compression is achievable
+        } else {
+            (1.0 - theoretical_minimum_size / file_size as f64) * 100.0
+        };
+
+        EntropyLimit {
+            channel_entropy,
+            total_entropy,
+            theoretical_minimum_size,
+            entropy_size,
+            compression_percentage,
+            capped,
+        }
+    }
+
+    /// A compressor's output as a percentage of the size the order-0 entropy implies. Near 100% the
+    /// compressor codes about as well as the histogram allows; below it, it also exploits structure
+    /// between pixels; above it, it wastes bits even on the histogram. None when the entropy is zero.
+    fn coding_efficiency(&self, compressed_size: usize) -> Option<f64> {
+        (self.entropy_size > 0.0).then(|| compressed_size as f64 / self.entropy_size * 100.0)
+    }
+
+    /// Advisories about how far the limit can be trusted.
+    fn warnings(&self) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        if self.capped {
+            warnings.push(Warning::new(
+                WarningCode::ModelIneffective,
+                "The model isn't effective enough to predict a better compression for this image.",
+            ));
+        }
+        if self.compression_percentage <= 0.0 {
+            warnings.push(Warning::already_compressed());
+        }
+        warnings
+    }
+}
+
+/// Like `analyze`, but consults and fills the result cache under the given key.
+fn analyze_cached(
+    img: &DynamicImage,
+    options: &AnalysisOptions,
+    cache_dir: &Path,
+    key: &str,
+) -> Result<AnalysisResult, String> {
+    if let Some(cached) = cache::load(cache_dir, key)
+        && let Some(compressed_sizes) = match cached.compressed_sizes.as_slice() {
+            [] => Some(None), // Stored by an entropy-only run
+            sizes => CompressedSizes::from_slice(sizes).map(Some),
+        }
+    {
+        info!("Cache hit for {}.", key);
+        return Ok(AnalysisResult::new(img, options, cached.channel_entropy, compressed_sizes));
+    }
+
+    let (channel_entropy, compressed_sizes) = analysis::measure(img, options)?;
+    let entry = CachedAnalysis {
+        channel_entropy,
+        compressed_sizes: compressed_sizes.as_ref().map_or_else(Vec::new, |sizes| sizes.as_slice().to_vec()),
+    };
+    if let Err(error_message) = cache::store(cache_dir, key, &entry) {
+        error!("{}", error_message);
+    }
+    Ok(AnalysisResult::new(img, options, channel_entropy, compressed_sizes))
+}
+
+/// Gathers the options of the analysis pipeline from the command line.
+fn analysis_options(args: &Args) -> AnalysisOptions {
+    AnalysisOptions {
+        channel_order: args.channel_order,
+        assume_channels: args.assume_channels,
+        ignore_transparent: args.ignore_transparent,
+        smoothing: args.smoothing,
+        smoothing_k: args.smoothing_k,
+        sample_rate: args.sample_rate as usize,
+        compressor: args.compressor,
+        ppm_warmup: args.ppm_warmup,
+        skip_entropy: args.skip_entropy,
+        entropy_only: args.entropy_only,
+    }
+}
+
+/// Names the histogram smoothing in effect, with its parameter, so results can be reproduced.
+fn smoothing_description(args: &Args) -> String {
+    match args.smoothing {
+        Smoothing::None => String::from("none"),
+        Smoothing::Laplace => format!("laplace (k = {})", args.smoothing_k),
+    }
+}
+
+/// Describes every option that affects the measurement, including conversion and masking of the decoded pixels,
+/// so changing one invalidates cached results.
+fn analysis_parameters(
+    options: &AnalysisOptions,
+    msb_only: Option<u8>,
+    trim_borders: bool,
+    force_color: Option<ColorType>,
+    signed: bool,
+) -> String {
+    format!(
+        "pictropy {} {:?} msb_only: {:?} trim_borders: {} force_color: {:?} signed: {}",
+        env!("CARGO_PKG_VERSION"), options, msb_only, trim_borders, force_color, signed
+    )
+}
+
+/// The color type --force-rgb or --force-gray converts every image to, if either is given.
+fn forced_color(args: &Args) -> Option<ColorType> {
+    if args.force_rgb {
+        Some(ColorType::Rgb8)
+    } else if args.force_gray {
+        Some(ColorType::L8)
+    } else {
+        None
+    }
+}
+
+/// Reports the uniform margins found on each edge and whether they were left out of the analysis.
+fn report_borders(found: &Borders, width: u32, height: u32, trimmed: bool) {
+    if found.is_empty() {
+        println!("Borders: None found");
+        return;
+    }
+    println!(
+        "Borders (pixels): top {}, bottom {}, left {}, right {} ({:.2}% of the image)",
+        found.top,
+        found.bottom,
+        found.left,
+        found.right,
+        found.border_pixels(width, height) as f64 / (width as u64 * height as u64) as f64 * 100.0
+    );
+    if trimmed {
+        println!(
+            "Note: The borders were trimmed; only the {}x{} content was analyzed.",
+            width - found.left - found.right,
+            height - found.top - found.bottom
+        );
+    }
+}
+
+/// Formats a number as an English ordinal ("2nd", "10th").
+fn ordinal(number: usize) -> String {
+    let suffix = match (number % 10, number % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", number, suffix)
+}
+
+/// Lists the files whose size or entropy changed since a previous report, then counts added and removed files.
+fn display_report_diff(
+    report_path: &Path,
+    previous: &BTreeMap<String, ReportEntry>,
+    reports: &[FileReport],
+    unit: EntropyUnit,
 ) {
+    let current: BTreeMap<String, ReportEntry> = reports
+        .iter()
+        .map(|report| {
+            let entry = ReportEntry { original_size: report.original_size, total_entropy: report.result.total_entropy };
+            (report.file.clone(), entry)
+        })
+        .collect();
+    let diff = compare::diff_reports(previous, &current);
+
+    println!("Changes Since {}:", report_path.display());
+    for (file, before, after) in &diff.changed {
+        let size_change = after.original_size as i64 - before.original_size as i64;
+        let size_percentage = if before.original_size > 0 {
+            size_change as f64 / before.original_size as f64 * 100.0
+        } else {
+            0.0
+        };
+        let mut line = format!(
+            "{}: size {} -> {} bytes ({:+}, {:+.1}%)",
+            file, before.original_size, after.original_size, size_change, size_percentage
+        );
+        if let (Some(before_entropy), Some(after_entropy)) = (before.total_entropy, after.total_entropy) {
+            line += &format!(
+                ", entropy {:.2} -> {:.2} {}/pixel ({:+.2})",
+                unit.from_bits(before_entropy),
+                unit.from_bits(after_entropy),
+                unit.name(),
+                unit.from_bits(after_entropy - before_entropy)
+            );
+        }
+        println!("{}", line);
+    }
+    for file in &diff.added {
+        println!("{}: new since the previous report", file);
+    }
+    for file in &diff.removed {
+        println!("{}: no longer present", file);
+    }
+
+    let grown = diff.changed.iter().filter(|(_, before, after)| after.original_size > before.original_size).count();
+    println!(
+        "{} files changed ({} got bigger), {} new, {} no longer present.",
+        diff.changed.len(), grown, diff.added.len(), diff.removed.len()
+    );
+}
+
+/// Analyzes the single Bayer (CFA) channel of a camera RAW file before demosaicing.
+#[cfg(feature = "raw")]
+fn analyze_raw(path: &Path, file_size: u64, relative_to: RelativeTo, unit: EntropyUnit) -> Result<(), String> {
+    let samples = raw::read_bayer_samples(path)?;
+    info!("RAW sensor data successfully loaded.");
+
+    let bayer_entropy = calculate_symbol_entropy(&samples);
+    let theoretical_minimum_size = (bayer_entropy * samples.len() as f64) / 8.0;
+
     let mut table = Table::new();
-    table.add_row(Row::new(vec![Cell::new("Color Channel"), Cell::new("Entropy (bits/pixel)")])); // Header
-    table.add_row(Row::new(vec![Cell::new("Red"), Cell::new(&format!("{:.2}", red_entropy))]));
-    table.add_row(Row::new(vec![Cell::new("Green"), Cell::new(&format!("{:.2}", green_entropy))]));
-    table.add_row(Row::new(vec![Cell::new("Blue"), Cell::new(&format!("{:.2}", blue_entropy))]));
-    table.add_row(Row::new(vec![Cell::new("Total"), Cell::new(&format!("{:.2}", total_entropy))]));
-    table.add_row(Row::new(vec![Cell::new("Compressed Size (bytes)"), Cell::new(&format!(
-        "Red: {}, Green: {}, Blue: {}",
-        red_compressed_size, green_compressed_size, blue_compressed_size
-    ))]));
+    table.add_row(Row::new(vec![
+        Cell::new("Sensor Channel"),
+        Cell::new(&format!("Entropy ({}/sample)", unit.name())),
+    ])); // Header
+    table.add_row(Row::new(vec![
+        Cell::new("Bayer (CFA)"),
+        Cell::new(&format!("{:.2}", unit.from_bits(bayer_entropy))),
+    ]));
     table.printstd();
 
-    println!("Original Size: {} bytes", original_size);
-    println!("{}", model_message); // Display model message instead of invalid theoretical size
+    println!("Original Size: {} bytes", file_size);
     println!(
-        "Compression Percentage (Based on Entropy Limit): {:.2}%",
-        compression_percentage
+        "Theoretical Minimum Size (Lossless Limit): {} {}",
+        relative_to.render(theoretical_minimum_size, 2, file_size),
+        relative_to.unit()
     );
+    if theoretical_minimum_size < file_size as f64 {
+        println!(
+            "Compression Percentage (Based on Entropy Limit): {:.2}%",
+            (1.0 - theoretical_minimum_size / file_size as f64) * 100.0
+        );
+    } else {
+        println!("{}", Warning::already_compressed());
+    }
+    Ok(())
+}
 
-    let compressed_total_size = red_compressed_size + green_compressed_size + blue_compressed_size;
-    println!("Total Compressed Size (bytes): {}", compressed_total_size);
-
-    // Suppress unused variable warning by prefixing with an underscore
-    let _theoretical_size = theoretical_size;
+/// Analyzes a floating-point HDR image, binning each channel's samples into `bins` levels first.
+fn analyze_float(path: &Path, file_size: u64, bins: u32, relative_to: RelativeTo, unit: EntropyUnit) -> Result<(), String> {
+    let image = float::read_float_image(path)?;
+    info!("HDR image ({}x{}) successfully loaded.", image.width, image.height);
+    let pixel_count = image.channels[0].len();
 
-    if compression_percentage <= 0.0 {
-        println!("Note: This file is already highly compressed and may not benefit from additional compression.");
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Channel"),
+        Cell::new(&format!("Entropy at {} bins ({}/pixel)", bins, unit.name())),
+    ])); // Header
+    let mut total_entropy = 0.0;
+    for (label, samples) in ["Red", "Green", "Blue"].iter().zip(&image.channels) {
+        let entropy = float::binned_entropy(samples, bins);
+        total_entropy += entropy;
+        table.add_row(Row::new(vec![Cell::new(label), Cell::new(&format!("{:.2}", unit.from_bits(entropy)))]));
     }
+    table.add_row(Row::new(vec![Cell::new("Total"), Cell::new(&format!("{:.2}", unit.from_bits(total_entropy)))]));
+    table.printstd();
+
+    let theoretical_minimum_size = (total_entropy * pixel_count as f64) / 8.0;
+    println!("Original Size: {} bytes", file_size);
+    println!(
+        "Theoretical Minimum Size at {} bins: {} {}",
+        bins,
+        relative_to.render(theoretical_minimum_size, 2, file_size),
+        relative_to.unit()
+    );
+    println!("Note: Entropy of float samples depends on the bin count; compare results at the same --float-bins.");
+    Ok(())
 }
 
-fn main() {
-    env_logger::init(); // Initialize logger
-    info!("Program started...");
+/// Analyzes the entropy of the quantized DCT coefficients stored in a JPEG file.
+fn analyze_jpeg_dct(path: &Path, file_size: u64, relative_to: RelativeTo, unit: EntropyUnit) -> Result<(), String> {
+    let data = fs::read(path).map_err(|err| format!("Error: Unable to read the file '{}': {}", path.display(), err))?;
+    let components = jpeg::read_dct_coefficients(&data)?;
+    info!("JPEG DCT coefficients successfully loaded.");
 
-    println!("Enter the path to the image file:");
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Component"),
+        Cell::new(&format!("Entropy ({}/coefficient)", unit.name())),
+    ])); // Header
 
-    let mut path = String::new();
-    io::stdin()
-        .read_line(&mut path)
-        .expect("Failed to read input.");
-    let path = path.trim(); // Trim newline or extra spaces
+    let mut theoretical_minimum_size = 0.0;
+    for (index, component) in components.iter().enumerate() {
+        let label = match (components.len(), index) {
+            (1, _) => String::from("Luma"),
+            (3, 0) => String::from("Y"),
+            (3, 1) => String::from("Cb"),
+            (3, 2) => String::from("Cr"),
+            _ => format!("Component {}", component.id),
+        };
+        let entropy = calculate_symbol_entropy(&component.coefficients);
+        theoretical_minimum_size += entropy * component.coefficients.len() as f64 / 8.0;
+        table.add_row(Row::new(vec![Cell::new(&label), Cell::new(&format!("{:.2}", unit.from_bits(entropy)))]));
+    }
+    table.printstd();
 
-    // Validate file path and format
-    let ext = path.split('.').last().unwrap_or("").to_lowercase();
-    if !["jpg", "jpeg", "png"].contains(&ext.as_str()) {
-        println!("Unsupported file format. Please use JPG or PNG files.");
-        return;
+    println!("Original Size: {} bytes", file_size);
+    println!(
+        "Theoretical Minimum Size (DCT Coefficient Entropy): {} {}",
+        relative_to.render(theoretical_minimum_size, 2, file_size),
+        relative_to.unit()
+    );
+    if theoretical_minimum_size < file_size as f64 {
+        println!(
+            "Compression Percentage (Based on Entropy Limit): {:.2}%",
+            (1.0 - theoretical_minimum_size / file_size as f64) * 100.0
+        );
+    } else {
+        println!("{}", Warning::already_compressed());
     }
+    Ok(())
+}
 
-    // Get the actual file size from metadata
-    let metadata = match fs::metadata(path) {
-        Ok(meta) => meta,
-        Err(err) => {
-            error!("Error accessing file metadata: {}", err);
-            println!("Failed to access file: {}", err); // User-friendly error message
-            return;
-        },
+/// Aborts the process if the analysis is still running after the given number of seconds.
+fn start_timeout_watchdog(seconds: u64) {
+    thread::spawn(move || {
+        thread::sleep(Duration::from_secs(seconds));
+        error!("Analysis exceeded the {} second timeout and was aborted.", seconds);
+        process::exit(1);
+    });
+}
+
+/// Reports an embedded ICC profile, since channel values are analyzed as stored.
+fn report_icc_profile(path: &Path, assume_srgb: bool) {
+    let Some(profile) = fs::read(path).ok().and_then(|data| icc::read_icc_profile(&data)) else {
+        return;
     };
-    let file_size = metadata.len(); // File size in bytes
 
-    let img = match read_image(path) {
-        Ok(image) => image,
+    println!(
+        "ICC Profile: {} ({}, {} bytes)",
+        profile.name.as_deref().unwrap_or("unnamed"),
+        profile.color_space,
+        profile.size
+    );
+    if !assume_srgb && !profile.is_srgb() {
+        println!("Note: Pixel values are analyzed as stored, without conversion from the embedded color space.");
+    }
+}
+
+/// Splits the file into image data and metadata, which no pixel entropy accounts for and which
+/// stripping removes for free.
+fn report_metadata(path: &Path, file_size: u64) {
+    let layout = match fs::read(path)
+        .map_err(|err| format!("Error: Unable to read the file '{}': {}", path.display(), err))
+        .and_then(|data| metadata::file_layout(&data))
+    {
+        Ok(layout) => layout,
         Err(error_message) => {
             error!("{}", error_message);
-            println!("{}", error_message); // User-friendly error message
             return;
         },
     };
-    info!("Image successfully loaded.");
-
-    let (width, height) = img.dimensions();
-    let total_pixels = (width * height) as f64;
 
-    // Separate data into color channels
-    let (red_channel, green_channel, blue_channel) = split_rgb_channels(&img);
+    let share = |size: usize| format!("{:.2}", size as f64 / file_size as f64 * 100.0);
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![Cell::new("File Part"), Cell::new("Size (bytes)"), Cell::new("Of File (%)")])); // Header
+    table.add_row(Row::new(vec![
+        Cell::new("Image Data"),
+        Cell::new(&layout.image_data.to_string()),
+        Cell::new(&share(layout.image_data)),
+    ]));
+    for &(kind, size) in &layout.metadata {
+        table.add_row(Row::new(vec![Cell::new(kind.name()), Cell::new(&size.to_string()), Cell::new(&share(size))]));
+    }
+    table.printstd();
 
-    // Calculate entropies in parallel
-    let red_entropy = calculate_entropy(&red_channel);
-    let green_entropy = calculate_entropy(&green_channel);
-    let blue_entropy = calculate_entropy(&blue_channel);
+    let metadata_size = layout.metadata_size();
+    if metadata_size > 0 {
+        println!(
+            "Stripping Metadata Saves: {} bytes ({}% of the file), leaving {} bytes",
+            metadata_size,
+            share(metadata_size),
+            layout.image_data
+        );
+    } else {
+        println!("Metadata: none; the file is all image data.");
+    }
+}
 
-    let total_entropy = red_entropy + green_entropy + blue_entropy;
+/// Displays the entropy of each composited APNG frame and of its difference from the previous frame.
+/// A difference entropy well below the frame's own means a delta-based codec would pay off.
+fn display_apng_frames(path: &Path, order: ChannelOrder, unit: EntropyUnit) -> Result<(), String> {
+    let frames = apng::frame_entropy(path, order)?;
+    info!("Decoded {} APNG frames.", frames.len());
 
-    // Calculate theoretical lossless limit
-    let mut theoretical_minimum_size = (total_entropy * total_pixels) / 8.0;
-    let mut model_message = format!(
-        "Theoretical Minimum Size (Lossless Limit): {:.2} bytes",
-        theoretical_minimum_size
-    );
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Frame"),
+        Cell::new("Region"),
+        Cell::new(&format!("Entropy ({}/pixel)", unit.name())),
+        Cell::new("Difference Entropy"),
+    ])); // Header
+    for (index, frame) in frames.iter().enumerate() {
+        let (width, height, x_offset, y_offset) = frame.region;
+        let difference = frame
+            .difference_entropy
+            .map_or_else(|| String::from("-"), |entropy| format!("{:.2}", unit.from_bits(entropy)));
+        table.add_row(Row::new(vec![
+            Cell::new(&index.to_string()),
+            Cell::new(&format!("{}x{}+{}+{}", width, height, x_offset, y_offset)),
+            Cell::new(&format!("{:.2}", unit.from_bits(frame.entropy))),
+            Cell::new(&difference),
+        ]));
+    }
+    table.printstd();
 
-    // Ensure theoretical size does not exceed original size
-    if theoretical_minimum_size > file_size as f64 {
-        theoretical_minimum_size = file_size as f64;
-        model_message = String::from(
-            "The model isn't effective enough to predict a better compression for this image.",
+    let differences: Vec<f64> = frames.iter().filter_map(|frame| frame.difference_entropy).collect();
+    if !differences.is_empty() {
+        let mean_entropy = frames.iter().map(|frame| frame.entropy).sum::<f64>() / frames.len() as f64;
+        let mean_difference = differences.iter().sum::<f64>() / differences.len() as f64;
+        println!(
+            "Mean Frame Entropy: {:.2} {unit}/pixel, Mean Difference Entropy: {:.2} {unit}/pixel",
+            unit.from_bits(mean_entropy),
+            unit.from_bits(mean_difference),
+            unit = unit.name()
         );
     }
+    Ok(())
+}
 
-    // Compress entropy results using PPM
-    let red_compressed_size = ppm_compress(&red_channel);
-    let green_compressed_size = ppm_compress(&green_channel);
-    let blue_compressed_size = ppm_compress(&blue_channel);
+/// Analyzes the entropy of a PNG's inflated IDAT stream, the filtered bytes DEFLATE compresses.
+fn analyze_png_idat(path: &Path, file_size: u64, relative_to: RelativeTo, unit: EntropyUnit) -> Result<(), String> {
+    let data = fs::read(path).map_err(|err| format!("Error: Unable to read the file '{}': {}", path.display(), err))?;
+    let scanlines = png::read_idat_stream(&data)?;
+    info!("PNG IDAT data successfully inflated.");
 
-    // Calculate compression percentage
-    let compression_percentage = if theoretical_minimum_size > file_size as f64 {
-        0.0 // No further compression is achievable
-    } else {
-        (1.0 - theoretical_minimum_size / file_size as f64) * 100.0
-    };
-
-    // Display results
-    display_results(
-        red_entropy,
-        green_entropy,
-        blue_entropy,
-        total_entropy,
-        file_size as usize,
-        theoretical_minimum_size,
-        red_compressed_size,
-        green_compressed_size,
-        blue_compressed_size,
-        compression_percentage,
-        &model_message,
+    let entropy = calculate_entropy(&scanlines);
+    let theoretical_minimum_size = entropy * scanlines.len() as f64 / 8.0;
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![Cell::new("Stream"), Cell::new(&format!("Entropy ({}/byte)", unit.name()))])); // Header
+    table.add_row(Row::new(vec![
+        Cell::new("Filtered Scanlines"),
+        Cell::new(&format!("{:.2}", unit.from_bits(entropy))),
+    ]));
+    table.printstd();
+
+    println!("Original Size: {} bytes", file_size);
+    println!("Inflated IDAT Size: {} bytes", scanlines.len());
+    println!(
+        "Theoretical Minimum Size (Filtered Scanline Entropy): {} {}",
+        relative_to.render(theoretical_minimum_size, 2, file_size),
+        relative_to.unit()
     );
-    info!("Program completed.");
+    if theoretical_minimum_size < file_size as f64 {
+        println!(
+            "Compression Percentage (Based on Entropy Limit): {:.2}%",
+            (1.0 - theoretical_minimum_size / file_size as f64) * 100.0
+        );
+    } else {
+        println!("{}", Warning::already_compressed());
+    }
+    Ok(())
+}
+
+/// Reports the distinct color count and whether it fits the 256-entry palette of an indexed PNG.
+fn report_unique_colors(img: &DynamicImage, limit: Option<usize>) {
+    let palette = |fits: bool| if fits { "fits an indexed PNG palette" } else { "too many for an indexed PNG palette" };
+    match (count_unique_colors(img, limit), limit) {
+        (Some(count), _) => println!("Unique Colors: {} ({})", count, palette(count <= 256)),
+        (None, Some(limit)) if limit >= 256 => println!("Unique Colors: >{} ({})", limit, palette(false)),
+        // Stopped below the palette size, so suitability is unknown
+        (None, limit) => println!("Unique Colors: >{}", limit.unwrap_or_default()),
+    }
+}
+
+/// Prints the pairwise mutual information of the color channels as a matrix; the diagonal is
+/// each channel's own entropy, since I(X; X) = H(X).
+fn display_mutual_information(channels: &RgbChannels, unit: EntropyUnit) {
+    let (red_channel, green_channel, blue_channel) = channels;
+    let named = [("Red", red_channel), ("Green", green_channel), ("Blue", blue_channel)];
+
+    let mut table = Table::new();
+    let mut header = vec![Cell::new(&format!("Mutual Information ({}/pixel)", unit.name()))];
+    header.extend(named.iter().map(|(name, _)| Cell::new(name)));
+    table.add_row(Row::new(header));
+    for (row, (row_name, row_channel)) in named.iter().enumerate() {
+        let mut cells = vec![Cell::new(row_name)];
+        for (column, (_, column_channel)) in named.iter().enumerate() {
+            let information = if row == column {
+                calculate_entropy(row_channel)
+            } else {
+                mutual_information(row_channel, column_channel)
+            };
+            cells.push(Cell::new(&format!("{:.2}", unit.from_bits(information))));
+        }
+        table.add_row(Row::new(cells));
+    }
+    table.printstd();
+}
+
+/// Compares each channel's order-0 entropy with its estimated entropy rate; the gap is what
+/// modelling context and repetition can save beyond symbol frequencies.
+fn display_entropy_rate(channels: &RgbChannels, unit: EntropyUnit) {
+    let (red_channel, green_channel, blue_channel) = channels;
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new(&format!("Entropy Rate ({}/pixel)", unit.name())),
+        Cell::new("Order-0 Entropy"),
+        Cell::new("Estimated Rate (gzip)"),
+        Cell::new("Gap"),
+    ])); // Header
+    for (name, channel) in [("Red", red_channel), ("Green", green_channel), ("Blue", blue_channel)] {
+        let entropy = calculate_entropy(channel);
+        let (rate, gap) = match rate::estimate_entropy_rate(channel) {
+            Some(rate) => (
+                format!("{:.2}", unit.from_bits(rate)),
+                format!("{:.2}", unit.from_bits((entropy - rate).max(0.0))),
+            ),
+            None => (String::from("-"), String::from("-")),
+        };
+        table.add_row(Row::new(vec![
+            Cell::new(name),
+            Cell::new(&format!("{:.2}", unit.from_bits(entropy))),
+            Cell::new(&rate),
+            Cell::new(&gap),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Prints the most frequent values of each channel with the part of the channel's entropy each
+/// accounts for. One value holding most samples points to run-length or palette coding.
+fn display_top_values(channels: &RgbChannels, k: usize, unit: EntropyUnit) {
+    let (red_channel, green_channel, blue_channel) = channels;
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Channel"),
+        Cell::new("Value"),
+        Cell::new("Count"),
+        Cell::new("Frequency (%)"),
+        Cell::new(&format!("Entropy Contribution ({}/pixel)", unit.name())),
+        Cell::new("Share of Entropy (%)"),
+    ])); // Header
+    for (name, channel) in [("Red", red_channel), ("Green", green_channel), ("Blue", blue_channel)] {
+        let entropy = calculate_entropy(channel);
+        for share in top_values(channel, k) {
+            let entropy_share = if entropy > 0.0 { share.entropy_contribution / entropy * 100.0 } else { 0.0 };
+            table.add_row(Row::new(vec![
+                Cell::new(name),
+                Cell::new(&share.value.to_string()),
+                Cell::new(&share.count.to_string()),
+                Cell::new(&format!("{:.2}", share.frequency * 100.0)),
+                Cell::new(&format!("{:.4}", unit.from_bits(share.entropy_contribution))),
+                Cell::new(&format!("{:.2}", entropy_share)),
+            ]));
+        }
+    }
+    table.printstd();
+}
+
+/// Prints each channel's entropy next to the variance of its histogram-bin probabilities.
+fn display_histogram_variance(channels: &RgbChannels, unit: EntropyUnit) {
+    let (red_channel, green_channel, blue_channel) = channels;
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Channel"),
+        Cell::new(&format!("Entropy ({}/pixel)", unit.name())),
+        Cell::new("Histogram Variance"),
+    ])); // Header
+    for (name, channel) in [("Red", red_channel), ("Green", green_channel), ("Blue", blue_channel)] {
+        table.add_row(Row::new(vec![
+            Cell::new(name),
+            Cell::new(&format!("{:.2}", unit.from_bits(calculate_entropy(channel)))),
+            Cell::new(&format!("{:.3e}", histogram_variance(channel))),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Displays one entropy row per channel present in the decoded image. With a `limit`, images with
+/// more than twice that many channels show only the `limit` highest- and lowest-entropy ones, from
+/// highest to lowest, followed by the mean over all channels.
+fn display_all_channels(channels: &[StoredChannel], limit: Option<u32>, unit: EntropyUnit) {
+    let entropies: Vec<(&str, f64)> = channels.iter().map(|stored| (stored.channel.as_str(), stored.entropy)).collect();
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Stored Channel"),
+        Cell::new(&format!("Entropy ({}/pixel)", unit.name())),
+    ])); // Header
+    let entropy_row = |(label, entropy): &(&str, f64)| {
+        Row::new(vec![Cell::new(label), Cell::new(&format!("{:.2}", unit.from_bits(*entropy)))])
+    };
+    match limit.map(|limit| limit as usize) {
+        Some(limit) if entropies.len() > 2 * limit => {
+            let mut ranked: Vec<&(&str, f64)> = entropies.iter().collect();
+            ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+            for entry in &ranked[..limit] {
+                table.add_row(entropy_row(entry));
+            }
+            table.add_row(Row::new(vec![
+                Cell::new(&format!("({} not shown)", ranked.len() - 2 * limit)),
+                Cell::new("..."),
+            ]));
+            for entry in &ranked[ranked.len() - limit..] {
+                table.add_row(entropy_row(entry));
+            }
+            let mean = entropies.iter().map(|(_, entropy)| entropy).sum::<f64>() / entropies.len() as f64;
+            table.add_row(Row::new(vec![
+                Cell::new(&format!("Mean of {} Channels", entropies.len())),
+                Cell::new(&format!("{:.2}", unit.from_bits(mean))),
+            ]));
+        },
+        _ => {
+            for entry in &entropies {
+                table.add_row(entropy_row(entry));
+            }
+        },
+    }
+    table.printstd();
+}
+
+/// Measures the entropy of every channel the image stores, for --all-channels.
+fn stored_channel_entropy(img: &DynamicImage) -> Vec<StoredChannel> {
+    split_all_channels(img)
+        .iter()
+        .enumerate()
+        .map(|(index, channel)| StoredChannel {
+            channel: channel_label(img, index),
+            entropy: calculate_entropy(channel),
+        })
+        .collect()
+}
+
+/// Displays the entropy at each pyramid level; fast-falling entropy indicates fine detail.
+fn display_pyramid(img: &DynamicImage, levels: usize, order: ChannelOrder, unit: EntropyUnit) {
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Level"),
+        Cell::new("Dimensions"),
+        Cell::new("Red"),
+        Cell::new("Green"),
+        Cell::new("Blue"),
+        Cell::new("Total"),
+    ])); // Header
+
+    for (index, level) in pyramid_entropy(img, levels, order).iter().enumerate() {
+        let total_entropy = level.red_entropy + level.green_entropy + level.blue_entropy;
+        table.add_row(Row::new(vec![
+            Cell::new(&index.to_string()),
+            Cell::new(&format!("{}x{}", level.width, level.height)),
+            Cell::new(&format!("{:.2}", unit.from_bits(level.red_entropy))),
+            Cell::new(&format!("{:.2}", unit.from_bits(level.green_entropy))),
+            Cell::new(&format!("{:.2}", unit.from_bits(level.blue_entropy))),
+            Cell::new(&format!("{:.2}", unit.from_bits(total_entropy))),
+        ]));
+    }
+
+    println!("Pyramid Entropy ({}/pixel):", unit.name());
+    table.printstd();
+}
+
+/// Displays the entropy of each plane of the image converted to another color space.
+fn display_colorspace_entropy(img: &DynamicImage, colorspace: Colorspace, order: ChannelOrder, unit: EntropyUnit) {
+    let planes = colorspace_planes(img, order, colorspace);
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![Cell::new("Plane"), Cell::new(&format!("Entropy ({}/pixel)", unit.name()))])); // Header
+    for (name, plane) in colorspace.plane_names().iter().zip(&planes) {
+        let entropy = unit.from_bits(calculate_entropy(plane));
+        table.add_row(Row::new(vec![Cell::new(name), Cell::new(&format!("{:.2}", entropy))]));
+    }
+
+    println!("{} Entropy ({}/pixel):", colorspace.name(), unit.name());
+    table.printstd();
+    if colorspace == Colorspace::Hsv {
+        println!("Note: Hue is quantized to 256 steps around the color wheel; gray pixels have no hue and count as 0.");
+    }
+}
+
+/// Compares the entropy of the posterized image with that of the full-precision original.
+fn report_msb_only(unmasked: &DynamicImage, bits: u8, order: ChannelOrder, masked_entropy: f64, unit: EntropyUnit) {
+    let (red_channel, green_channel, blue_channel) = split_rgb_channels(unmasked, order);
+    let unmasked_entropy =
+        calculate_entropy(&red_channel) + calculate_entropy(&green_channel) + calculate_entropy(&blue_channel);
+    let drop = unmasked_entropy - masked_entropy;
+    let drop_percentage = if unmasked_entropy > 0.0 { drop / unmasked_entropy * 100.0 } else { 0.0 };
+
+    println!(
+        "Note: Only the top {} bits of each color channel were analyzed; full-precision total entropy is {:.2} {}/pixel \
+         (drop of {:.2}, {:.1}%).",
+        bits,
+        unit.from_bits(unmasked_entropy),
+        unit.name(),
+        unit.from_bits(drop),
+        drop_percentage
+    );
+}
+
+/// Estimates the PPM size of each channel at every order up to `max_order`, one order per rayon
+/// task, and marks the smallest total.
+fn display_ppm_order_sweep(
+    channels: &RgbChannels,
+    max_order: usize,
+    options: &AnalysisOptions,
+    relative_to: RelativeTo,
+    file_size: u64,
+) {
+    let (red_channel, green_channel, blue_channel) = channels;
+    let sizes: Vec<[usize; 3]> = (0..=max_order)
+        .into_par_iter()
+        .map(|order| {
+            [red_channel, green_channel, blue_channel]
+                .map(|channel| ppm_compress_order(channel, options.ppm_warmup, order) * options.sample_rate)
+        })
+        .collect();
+    let best_order = (0..sizes.len()).min_by_key(|&order| sizes[order].iter().sum::<usize>()).unwrap_or_default();
+
+    let size = |bytes: usize| relative_to.render(bytes as f64, 0, file_size);
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("PPM Order"),
+        Cell::new("Red"),
+        Cell::new("Green"),
+        Cell::new("Blue"),
+        Cell::new(&format!("Total ({})", relative_to.unit())),
+    ])); // Header
+    for (order, [red_size, green_size, blue_size]) in sizes.iter().enumerate() {
+        let marker = if order == best_order { " (best)" } else { "" };
+        table.add_row(Row::new(vec![
+            Cell::new(&format!("{}{}", order, marker)),
+            Cell::new(&size(*red_size)),
+            Cell::new(&size(*green_size)),
+            Cell::new(&size(*blue_size)),
+            Cell::new(&size(red_size + green_size + blue_size)),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Reports the PPM size of each channel when coded by a pre-trained model, next to its size under
+/// a fresh model. The saving is the redundancy the image shares with the images the model saw.
+fn report_ppm_model(
+    channels: &RgbChannels,
+    models: &ChannelModels,
+    options: &AnalysisOptions,
+    computed: Option<&CompressedSizes>,
+) {
+    let (red_channel, green_channel, blue_channel) = channels;
+    let channels = [red_channel, green_channel, blue_channel];
+    let with_model: Vec<usize> = channels
+        .iter()
+        .zip(models)
+        .map(|(channel, model)| model.clone().code(channel, options.ppm_warmup) * options.sample_rate)
+        .collect();
+    let without_model = match computed {
+        Some(CompressedSizes::PerChannel(sizes)) if options.compressor == Compressor::Ppm => sizes.iter().sum(),
+        _ => channels.iter().map(|channel| ppm_compress(channel, options.ppm_warmup) * options.sample_rate).sum::<usize>(),
+    };
+
+    let total = with_model.iter().sum::<usize>();
+    println!(
+        "PPM Size With Trained Model (bytes): Red: {}, Green: {}, Blue: {}, Total: {}",
+        with_model[0], with_model[1], with_model[2], total
+    );
+    println!(
+        "PPM Size Without Model (bytes): {} (the model saves {:.2}%)",
+        without_model,
+        (1.0 - total as f64 / without_model.max(1) as f64) * 100.0
+    );
+}
+
+/// Prints storage advice for the image with the metrics it rests on. The lossless candidates are
+/// real encoders, and the lossy one is the smallest WebP sweep quality that stays visually transparent.
+fn report_recommendation(img: &DynamicImage, file_size: u64, limit: Option<&EntropyLimit>) {
+    let has_alpha = img.color().has_alpha();
+    let best_lossless = [("lossless WebP", webp_compress(img).ok()), ("QOI", Some(qoi_compress(img)))]
+        .into_iter()
+        .filter_map(|(name, size)| Some((name, size?)))
+        .min_by_key(|&(_, size)| size);
+    let points = if has_alpha { Vec::new() } else { lossy::sweep(img, LossyFormat::Webp).unwrap_or_default() };
+    let lossy = lossy::best_acceptable_point(&points, recommend::MIN_LOSSY_PSNR)
+        .map(|point| LossyOption { format: LossyFormat::Webp.name(), quality: point.quality, size: point.size, psnr: point.psnr });
+
+    let recommendation = recommend::recommend(&Metrics {
+        file_size,
+        unique_colors: count_unique_colors(img, Some(recommend::PALETTE_SIZE)),
+        entropy_limit: limit.map(|limit| limit.theoretical_minimum_size),
+        best_lossless,
+        has_alpha,
+        lossy,
+    });
+    let (advice, alternatives) = recommendation.advice.split_first().expect("There is always some advice");
+    println!("Recommendation: {}", advice);
+    for alternative in alternatives {
+        println!("Also Consider: {}", alternative);
+    }
+    println!("Based On:");
+    for line in &recommendation.evidence {
+        println!("  - {}", line);
+    }
+}
+
+/// Reports the best compressed size over every available compressor relative to the file size. Real
+/// compressors bound the Kolmogorov complexity from above, so a ratio near 1 means none of them found
+/// structure to exploit. The selected compressor's size is reused unless it came from a sample. A
+/// compressor that fails is left out, as the file's results have already been written.
+fn report_complexity(
+    img: &DynamicImage,
+    options: &AnalysisOptions,
+    computed: Option<&CompressedSizes>,
+    file_size: u64,
+    warnings: &mut Vec<Warning>,
+) {
+    let mut sizes = Vec::new();
+    for compressor in [Compressor::Ppm, Compressor::Webp, Compressor::Qoi] {
+        let size = match computed {
+            Some(computed) if compressor == options.compressor && options.sample_rate == 1 => computed.total(),
+            _ => {
+                let options = AnalysisOptions {
+                    compressor,
+                    sample_rate: 1,
+                    skip_entropy: true,
+                    entropy_only: false,
+                    ..options.clone()
+                };
+                match analysis::measure(img, &options) {
+                    Ok((_, sizes)) => sizes.map_or(usize::MAX, |sizes| sizes.total()),
+                    Err(error_message) => {
+                        warn!("{} was skipped for --complexity: {}", compressor.name(), error_message);
+                        continue;
+                    },
+                }
+            },
+        };
+        sizes.push((compressor.name(), size));
+    }
+    sizes.push(("gzip", rate::gzip_size(img.as_bytes())));
+
+    let (best_name, best_size) = sizes.into_iter().min_by_key(|&(_, size)| size).expect("Compressors were tried");
+    let ratio = best_size as f64 / file_size as f64;
+    println!(
+        "Normalized Compression Complexity: {:.3} (best: {}, {} of {} bytes)",
+        ratio, best_name, best_size, file_size
+    );
+    if ratio >= 0.95 {
+        warnings.push(Warning::new(
+            WarningCode::Incompressible,
+            "No compressor shrank the data much; it is essentially incompressible.",
+        ));
+    }
+}
+
+/// Reports how much entropy a denoising filter removes; a large drop means the image is noise-dominated.
+fn report_denoise(
+    img: &DynamicImage,
+    filter: DenoiseFilter,
+    order: ChannelOrder,
+    total_entropy: f64,
+    unit: EntropyUnit,
+    warnings: &mut Vec<Warning>,
+) {
+    let denoised = denoise::denoise(img, filter);
+    let (red_channel, green_channel, blue_channel) = split_rgb_channels(&denoised, order);
+    let denoised_entropy =
+        calculate_entropy(&red_channel) + calculate_entropy(&green_channel) + calculate_entropy(&blue_channel);
+    let drop = total_entropy - denoised_entropy;
+    let drop_percentage = if total_entropy > 0.0 { drop / total_entropy * 100.0 } else { 0.0 };
+
+    println!(
+        "Total Entropy After {} Denoise: {:.2} {}/pixel (drop of {:.2}, {:.1}%)",
+        filter.name(),
+        unit.from_bits(denoised_entropy),
+        unit.name(),
+        unit.from_bits(drop),
+        drop_percentage
+    );
+    if drop_percentage >= 25.0 {
+        warnings.push(Warning::new(
+            WarningCode::NoiseDominated,
+            "This image is noise-dominated; lossless compression will struggle, consider a lossy format.",
+        ));
+    }
+}
+
+/// Reports the entropy of the scanlines after a PNG filter, next to that of the unfiltered bytes,
+/// and for `optimal` how many rows chose each filter.
+fn report_png_filter(img: &DynamicImage, filter: PngFilter, file_size: u64, relative_to: RelativeTo, unit: EntropyUnit) {
+    let (samples, bytes_per_pixel) = png::png_samples(img);
+    let row_bytes = img.width() as usize * bytes_per_pixel;
+    let stream = png::filter_scanlines(&samples, row_bytes, bytes_per_pixel, filter);
+    let entropy = calculate_entropy(&stream);
+    let theoretical_minimum_size = entropy * stream.len() as f64 / 8.0;
+
+    println!(
+        "PNG {} Filter Entropy: {:.2} {}/byte (unfiltered {:.2})",
+        filter.name(),
+        unit.from_bits(entropy),
+        unit.name(),
+        unit.from_bits(calculate_entropy(&samples))
+    );
+    println!(
+        "Theoretical Minimum Size ({} Filtered Scanline Entropy): {} {}",
+        filter.name(),
+        relative_to.render(theoretical_minimum_size, 2, file_size),
+        relative_to.unit()
+    );
+    if filter == PngFilter::Optimal {
+        let mut row_counts = [0; png::ROW_FILTERS.len()];
+        for &type_byte in stream.iter().step_by(row_bytes + 1) {
+            row_counts[type_byte as usize] += 1;
+        }
+        let counts: Vec<String> = png::ROW_FILTERS
+            .iter()
+            .zip(row_counts)
+            .map(|(row_filter, count)| format!("{} {}", row_filter.name(), count))
+            .collect();
+        println!("Rows Per Filter: {}", counts.join(", "));
+    }
+}
+
+/// Previews reducing the image to a palette of `colors`: the entropy of the index stream, the indexed
+/// size it implies with the palette stored alongside, and how far the reduced colors stray.
+fn report_palette_preview(img: &DynamicImage, colors: usize, file_size: u64, relative_to: RelativeTo, unit: EntropyUnit) {
+    let reduction = quantize::median_cut(img, colors);
+    let entropy = calculate_entropy(&reduction.indices);
+    let palette_size = reduction.palette.len() * 3;
+    let indexed_size = entropy * reduction.indices.len() as f64 / 8.0 + palette_size as f64;
+    let reduced = reduction.to_image(img.width(), img.height());
+
+    println!(
+        "Palette Preview: {} of {} colors used, index entropy {:.2} {}/pixel",
+        reduction.palette.len(),
+        colors,
+        unit.from_bits(entropy),
+        unit.name()
+    );
+    println!(
+        "Estimated Indexed Size (Index Entropy + {}-byte Palette): {} {}",
+        palette_size,
+        relative_to.render(indexed_size, 2, file_size),
+        relative_to.unit()
+    );
+    println!("Palette PSNR: {:.2} dB", lossy::psnr(img, &reduced));
+}
+
+/// Prints per-tile entropy as TSV: one matrix per channel, keyed by the channel and row columns,
+/// so `pandas.read_csv(path, sep="\t", index_col=[0, 1]).loc["red"]` yields the red heatmap.
+fn display_tile_grid(img: &DynamicImage, grid: TileGrid, order: ChannelOrder, unit: EntropyUnit) -> Result<(), String> {
+    let tiles = tile_entropy(img, grid.columns, grid.rows, order)?;
+
+    let header: Vec<String> = (0..grid.columns).map(|column| column.to_string()).collect();
+    println!("channel\trow\t{}", header.join("\t"));
+    for (channel, name) in ["red", "green", "blue"].iter().enumerate() {
+        for (row, tile_row) in tiles.iter().enumerate() {
+            let values: Vec<String> = tile_row
+                .iter()
+                .map(|tile| format!("{:.4}", unit.from_bits(tile.channels()[channel])))
+                .collect();
+            println!("{}\t{}\t{}", name, row, values.join("\t"));
+        }
+    }
+    Ok(())
+}
+
+/// Compares the entropy of the image's non-uniform tiles with that of the whole image, so a plain
+/// backdrop doesn't dilute the measure of the subject.
+fn display_content_entropy(
+    img: &DynamicImage,
+    grid: TileGrid,
+    threshold: f64,
+    order: ChannelOrder,
+    unit: EntropyUnit,
+) -> Result<(), String> {
+    let Some(content) = content_entropy(img, grid.columns, grid.rows, order, threshold)? else {
+        println!("Every tile is below {} bits; the image has no content outside uniform regions.", threshold);
+        return Ok(());
+    };
+    let (width, height) = img.dimensions();
+    println!(
+        "Content Tiles: {} of {} ({:.1}% of pixels)",
+        content.kept_tiles,
+        content.total_tiles,
+        content.kept_pixels as f64 / (width as u64 * height as u64) as f64 * 100.0
+    );
+
+    let (red_channel, green_channel, blue_channel) = split_rgb_channels(img, order);
+    let whole = [calculate_entropy(&red_channel), calculate_entropy(&green_channel), calculate_entropy(&blue_channel)];
+    let mut table = Table::new();
+    table.add_row(Row::new(
+        ["Region", "Red", "Green", "Blue", "Total"].iter().map(|title| Cell::new(title)).collect(),
+    )); // Header
+    for (name, channels) in [("Content", content.entropy.channels()), ("Whole Image", whole)] {
+        let mut cells = vec![Cell::new(name)];
+        for value in channels.into_iter().chain([channels.iter().sum()]) {
+            cells.push(Cell::new(&format!("{:.2}", unit.from_bits(value))));
+        }
+        table.add_row(Row::new(cells));
+    }
+    println!("Content Entropy ({}/pixel):", unit.name());
+    table.printstd();
+    Ok(())
+}
+
+/// Columns of `distribution_statistics`.
+const DISTRIBUTION_COLUMNS: [&str; 7] = ["Min", "P25", "Median", "P75", "P90", "Max", "Mean"];
+
+/// Sorts the values and returns their minimum, quartiles, 90th percentile, maximum and mean, with
+/// nearest-rank percentiles. There must be at least one value.
+fn distribution_statistics(values: &mut [f64]) -> [f64; 7] {
+    values.sort_by(f64::total_cmp);
+    let percentile = |fraction: f64| values[((values.len() - 1) as f64 * fraction).round() as usize];
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    [percentile(0.0), percentile(0.25), percentile(0.5), percentile(0.75), percentile(0.9), percentile(1.0), mean]
+}
+
+/// Summarizes the entropy of the image's JPEG-aligned 8x8 blocks per channel. High-entropy blocks
+/// are where a JPEG encoder spends its bits.
+fn display_jpeg_blocks(img: &DynamicImage, order: ChannelOrder, unit: EntropyUnit) {
+    let blocks: Vec<TileEntropy> = jpeg_block_entropy(img, order).into_iter().flatten().collect();
+    println!("JPEG Blocks: {} ({}x{} pixels each)", blocks.len(), JPEG_BLOCK_SIZE, JPEG_BLOCK_SIZE);
+
+    let mut table = Table::new();
+    table.add_row(Row::new(
+        std::iter::once(&"Channel").chain(&DISTRIBUTION_COLUMNS).map(|title| Cell::new(title)).collect(),
+    )); // Header
+    let channel_values = |channel: usize| blocks.iter().map(|block| block.channels()[channel]).collect::<Vec<f64>>();
+    let rows = [
+        ("Red", channel_values(0)),
+        ("Green", channel_values(1)),
+        ("Blue", channel_values(2)),
+        ("Total", blocks.iter().map(|block| block.channels().iter().sum()).collect()),
+    ];
+    for (name, mut values) in rows {
+        let mut cells = vec![Cell::new(name)];
+        for value in distribution_statistics(&mut values) {
+            cells.push(Cell::new(&format!("{:.2}", unit.from_bits(value))));
+        }
+        table.add_row(Row::new(cells));
+    }
+    table.printstd();
+    println!(
+        "Entropy is per pixel within each block; a full block holds at most {:.2} {} per channel.",
+        unit.from_bits(6.0),
+        unit.name()
+    );
+}
+
+/// Reports how surprising the channels are under a reference distribution. The excess over the
+/// channel's own entropy is the extra cost of coding it with the reference's statistics.
+fn report_cross_entropy(
+    channels: &RgbChannels,
+    reference: &ReferenceDistribution,
+    channel_entropy: [f64; 3],
+    unit: EntropyUnit,
+) {
+    let (red_channel, green_channel, blue_channel) = channels;
+    let cross_entropy = [
+        reference::cross_entropy(red_channel, &reference[0]),
+        reference::cross_entropy(green_channel, &reference[1]),
+        reference::cross_entropy(blue_channel, &reference[2]),
+    ];
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Color Channel"),
+        Cell::new(&format!("Cross-Entropy ({}/pixel)", unit.name())),
+        Cell::new("Excess over Entropy"),
+    ])); // Header
+    for (name, (cross, own)) in ["Red", "Green", "Blue"].iter().zip(cross_entropy.iter().zip(channel_entropy)) {
+        table.add_row(Row::new(vec![
+            Cell::new(name),
+            Cell::new(&format!("{:.2}", unit.from_bits(*cross))),
+            Cell::new(&format!("{:.2}", unit.from_bits(cross - own))),
+        ]));
+    }
+    let total_cross_entropy = cross_entropy.iter().sum::<f64>();
+    table.add_row(Row::new(vec![
+        Cell::new("Total"),
+        Cell::new(&format!("{:.2}", unit.from_bits(total_cross_entropy))),
+        Cell::new(&format!("{:.2}", unit.from_bits(total_cross_entropy - channel_entropy.iter().sum::<f64>()))),
+    ]));
+
+    println!("Cross-Entropy Against Reference:");
+    table.printstd();
+}
+
+/// Encodes the image in the chosen format and writes it to disk.
+fn write_output(img: &DynamicImage, output_path: &Path, format: Option<EncodeFormat>, quality: u8) {
+    let format = format
+        .or_else(|| EncodeFormat::from_extension(&file_extension(output_path)))
+        .unwrap_or(EncodeFormat::Png);
+
+    let result = encode::encode_image(img, format, quality).and_then(|data| {
+        fs::write(output_path, &data)
+            .map(|_| data.len())
+            .map_err(|err| format!("Error: Unable to write '{}': {}", output_path.display(), err))
+    });
+    match result {
+        Ok(written_size) => println!("Wrote {} ({}, {} bytes)", output_path.display(), format.name(), written_size),
+        Err(error_message) => {
+            error!("{}", error_message);
+        },
+    }
+}
+
+/// Re-encodes the image losslessly in its own format at maximum effort and compares the size
+/// with the original file. JPEG is skipped, since decoding and re-encoding it is lossy.
+fn report_original_format(img: &DynamicImage, path: &Path, file_size: u64) {
+    let format = match EncodeFormat::from_extension(&file_extension(path)) {
+        Some(EncodeFormat::Png) => EncodeFormat::Png,
+        Some(format) => {
+            println!(
+                "Note: {} can't be re-encoded without further loss, so no same-format comparison is made.",
+                format.name()
+            );
+            return;
+        },
+        None => return,
+    };
+
+    let optimized_size = match encode::encode_png_smallest(img) {
+        Ok(data) => data.len() as u64,
+        Err(error_message) => {
+            error!("{}", error_message);
+            return;
+        },
+    };
+    let savings_percentage = (1.0 - optimized_size as f64 / file_size as f64) * 100.0;
+    println!("Optimized {} Size (best effort, same format): {} bytes", format.name(), optimized_size);
+    if optimized_size < file_size {
+        println!("Re-encoding would save {} bytes ({:.2}%).", file_size - optimized_size, savings_percentage);
+    } else {
+        println!("Note: The original is already at least as small as a best-effort re-encode.");
+    }
+}
+
+/// Reports the PNG size of the image under each viable color type and names the smallest.
+fn report_png_color_types(img: &DynamicImage, file_size: u64) {
+    let variants = match encode::encode_png_color_types(img) {
+        Ok(variants) => variants,
+        Err(error_message) => {
+            error!("{}", error_message);
+            return;
+        },
+    };
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![Cell::new("PNG Color Type"), Cell::new("Size (bytes)"), Cell::new("Of Original (%)")]));
+    for (color_type, data) in &variants {
+        table.add_row(Row::new(vec![
+            Cell::new(color_type.name()),
+            Cell::new(&data.len().to_string()),
+            Cell::new(&format!("{:.2}", data.len() as f64 / file_size as f64 * 100.0)),
+        ]));
+    }
+    table.printstd();
+
+    if let Some((color_type, data)) = variants.iter().min_by_key(|(_, data)| data.len()) {
+        println!("Smallest PNG Color Type: {} ({} bytes)", color_type.name(), data.len());
+    }
+}
+
+/// Reports the highest quality of each lossy format that fits the byte budget, and the format whose
+/// encoding has the fewest artifacts by PSNR.
+fn report_compression_target(img: &DynamicImage, target_size: usize) {
+    let candidates = match lossy::search_formats_for_size(img, target_size) {
+        Ok(candidates) => candidates,
+        Err(error_message) => {
+            error!("{}", error_message);
+            return;
+        },
+    };
+    println!("Compression Target: {} bytes", target_size);
+    let Some((winner, best)) = candidates.first() else {
+        println!("No lossy format fits within {} bytes; even quality 1 is larger.", target_size);
+        return;
+    };
+
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Format"),
+        Cell::new("Quality"),
+        Cell::new("Size (bytes)"),
+        Cell::new("PSNR (dB)"),
+    ])); // Header
+    for (format, point) in &candidates {
+        table.add_row(Row::new(vec![
+            Cell::new(format.name()),
+            Cell::new(&point.quality.to_string()),
+            Cell::new(&point.size.to_string()),
+            Cell::new(&format!("{:.2}", point.psnr)),
+        ]));
+    }
+    table.printstd();
+    println!(
+        "Best Fit: {} quality {} ({} bytes, PSNR {:.2} dB)",
+        winner.name(), best.quality, best.size, best.psnr
+    );
+}
+
+/// Encodes the image at every sweep quality and reports the rate-distortion curve as a table and,
+/// if asked, a CSV file.
+fn report_lossy_sweep(img: &DynamicImage, format: LossyFormat, file_size: u64, csv_path: Option<&Path>) {
+    let points = match lossy::sweep(img, format) {
+        Ok(points) => points,
+        Err(error_message) => {
+            error!("{}", error_message);
+            return;
+        },
+    };
+    display_lossy_sweep(&points, format, file_size);
+
+    if let Some(csv_path) = csv_path {
+        let mut csv = String::from("quality,size_bytes,psnr_db\n");
+        for point in &points {
+            csv.push_str(&format!("{},{},{:.4}\n", point.quality, point.size, point.psnr));
+        }
+        match fs::write(csv_path, csv) {
+            Ok(()) => println!("Wrote rate-distortion points to {}", csv_path.display()),
+            Err(err) => error!("Error: Unable to write '{}': {}", csv_path.display(), err),
+        }
+    }
+}
+
+/// Sweeps the lossy qualities and reports the one with the largest saving whose PSNR stays at or
+/// above `min_psnr`, writing that encoding to `output_path` if given.
+fn report_output_quality(img: &DynamicImage, format: LossyFormat, file_size: u64, min_psnr: f64, output_path: Option<&Path>) {
+    let points = match lossy::sweep(img, format) {
+        Ok(points) => points,
+        Err(error_message) => {
+            error!("{}", error_message);
+            return;
+        },
+    };
+    display_lossy_sweep(&points, format, file_size);
+
+    let Some(best) = lossy::best_acceptable_point(&points, min_psnr) else {
+        println!("No {} quality reaches the minimum PSNR of {:.2} dB.", format.name(), min_psnr);
+        return;
+    };
+    println!("Minimum PSNR: {:.2} dB", min_psnr);
+    println!("Chosen {} Quality: {}", format.name(), best.quality);
+    println!("Encoded Size: {} bytes", best.size);
+    println!("PSNR: {:.2} dB", best.psnr);
+    println!("Savings: {:.2}%", (1.0 - best.size as f64 / file_size as f64) * 100.0);
+    if best.size as u64 >= file_size {
+        println!("Note: Even this encoding is no smaller than the original.");
+    }
+
+    if let Some(output_path) = output_path {
+        let result = lossy::lossy_encode(img, format, best.quality).and_then(|data| {
+            fs::write(output_path, &data)
+                .map(|_| data.len())
+                .map_err(|err| format!("Error: Unable to write '{}': {}", output_path.display(), err))
+        });
+        match result {
+            Ok(written_size) => println!(
+                "Wrote {} ({} quality {}, {} bytes)",
+                output_path.display(), format.name(), best.quality, written_size
+            ),
+            Err(error_message) => {
+                error!("{}", error_message);
+            },
+        }
+    }
+}
+
+/// Prints the size and PSNR of each sweep quality.
+fn display_lossy_sweep(points: &[RateDistortionPoint], format: LossyFormat, file_size: u64) {
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new(&format!("{} Quality", format.name())),
+        Cell::new("Size (bytes)"),
+        Cell::new("% of Original"),
+        Cell::new("PSNR (dB)"),
+    ])); // Header
+    for point in points {
+        table.add_row(Row::new(vec![
+            Cell::new(&point.quality.to_string()),
+            Cell::new(&point.size.to_string()),
+            Cell::new(&format!("{:.2}", point.size as f64 / file_size as f64 * 100.0)),
+            Cell::new(&format!("{:.2}", point.psnr)),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Searches for the highest lossy quality that fits a byte budget and reports its PSNR.
+fn report_target_size(img: &DynamicImage, format: LossyFormat, target_size: usize, comparison_path: Option<&Path>) {
+    let encoding = match lossy::search_quality_for_size(img, format, target_size) {
+        Ok(Some(encoding)) => encoding,
+        Ok(None) => {
+            println!(
+                "No {} quality fits within {} bytes; even quality 1 is larger.",
+                format.name(), target_size
+            );
+            return;
+        },
+        Err(error_message) => {
+            error!("{}", error_message);
+            return;
+        },
+    };
+
+    println!("Target Size: {} bytes", target_size);
+    println!("Chosen {} Quality: {}", format.name(), encoding.quality);
+    println!("Encoded Size: {} bytes", encoding.data.len());
+    match lossy::encoding_psnr(img, &encoding, format) {
+        Ok(psnr) => println!("PSNR: {:.2} dB", psnr),
+        Err(error_message) => {
+            error!("{}", error_message);
+        },
+    }
+
+    if let Some(comparison_path) = comparison_path {
+        let result = lossy::lossy_decode(&encoding.data, format)
+            .and_then(|decoded| lossy::comparison_image(img, &decoded))
+            .and_then(|composite| {
+                composite
+                    .save_with_format(comparison_path, ImageFormat::Png)
+                    .map_err(|err| format!("Error: Unable to write '{}': {}", comparison_path.display(), err))
+            });
+        match result {
+            Ok(()) => println!(
+                "Wrote comparison {} (original | {} | difference x{})",
+                comparison_path.display(), format.name(), lossy::DIFFERENCE_GAIN
+            ),
+            Err(error_message) => {
+                error!("{}", error_message);
+            },
+        }
+    }
+}
+
+/// Sensor dimensions of a RAW file, read from its metadata without decoding the samples.
+#[cfg(feature = "raw")]
+fn raw_dimensions(path: &Path) -> Option<(u32, u32)> {
+    raw::raw_dimensions(path)
+}
+
+/// Without the `raw` feature RAW files can't be read, so neither can their dimensions.
+#[cfg(not(feature = "raw"))]
+fn raw_dimensions(_path: &Path) -> Option<(u32, u32)> {
+    None
+}
+
+/// Reports that RAW support was not compiled in.
+#[cfg(not(feature = "raw"))]
+fn analyze_raw(path: &Path, _file_size: u64, _relative_to: RelativeTo, _unit: EntropyUnit) -> Result<(), String> {
+    Err(format!(
+        "Error: Cannot analyze '{}': RAW files require the `raw` feature. Rebuild with `cargo build --features raw`.",
+        path.display()
+    ))
+}
+
+fn main() {
+    let started = Instant::now();
+    // Initialize logger, showing info and above unless RUST_LOG says otherwise
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    if let Some(profile) = args.profile {
+        profile.apply(&mut args, &matches);
+    }
+    info!("Program started...");
+
+    if let Some(Command::GenFixtures { dir }) = &args.command {
+        match fixtures::write_fixtures(dir) {
+            Ok(written) => {
+                for fixture in written {
+                    let [red, green, blue] = fixture.channel_entropy;
+                    info!("Wrote {} (entropy {:.4}, {:.4}, {:.4} bits/pixel).", fixture.name, red, green, blue);
+                }
+            },
+            Err(error_message) => {
+                error!("{}", error_message);
+                process::exit(1);
+            },
+        }
+        return;
+    }
+
+    if let Some(Command::Serve { listen, max_concurrent_requests, max_request_bytes, max_pixels, compressor }) =
+        &args.command
+    {
+        let config = serve::ServeConfig {
+            listen: listen.clone(),
+            max_concurrent_requests: *max_concurrent_requests as usize,
+            max_request_bytes: *max_request_bytes,
+            max_pixels: *max_pixels,
+            options: AnalysisOptions { compressor: *compressor, ..AnalysisOptions::default() },
+        };
+        if let Err(error_message) = serve::serve(config) {
+            error!("{}", error_message);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(Command::MergeReports { reports }) = &args.command {
+        if let Err(error_message) = merge_reports(reports) {
+            error!("{}", error_message);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(seconds) = args.timeout {
+        start_timeout_watchdog(seconds);
+    }
+
+    if let Some(model_path) = &args.use_ppm_model {
+        match ppm_model::load_models(model_path) {
+            Ok(models) => {
+                let _ = LOADED_PPM_MODELS.set(models);
+            },
+            Err(error_message) => {
+                error!("{}", error_message);
+                process::exit(2);
+            },
+        }
+    }
+
+    if let Some(reference_path) = &args.reference {
+        match reference::load_reference(reference_path, args.channel_order) {
+            Ok(reference) => {
+                let _ = LOADED_REFERENCE.set(reference);
+            },
+            Err(error_message) => {
+                error!("{}", error_message);
+                process::exit(2);
+            },
+        }
+    }
+
+    if args.clipboard {
+        let mut writer = output_writer(&args, false);
+        if args.print_flags {
+            writer.write_configuration(&configuration(&args, &analysis_options(&args)));
+        }
+        let reports = match analyze_clipboard(&args, writer.as_mut()) {
+            Ok(report) => report.into_iter().collect(),
+            Err(error_message) => {
+                error!("{}", error_message);
+                Vec::new()
+            },
+        };
+        writer.finish();
+        compare_with_report(&args, &reports);
+        display_entropy_histogram(&args, &reports);
+        finish(&args, started);
+        return;
+    }
+
+    if !args.format.allows_prose() && args.compare_report.is_some() {
+        error!("--compare-report prints text, so it needs --format table.");
+        process::exit(2);
+    }
+    if !args.format.allows_prose() && args.entropy_histogram {
+        error!("--entropy-histogram prints text, so it needs --format table.");
+        process::exit(2);
+    }
+
+    if let Some(list_path) = &args.files_from {
+        let paths = match read_file_list(list_path) {
+            Ok(paths) => paths,
+            Err(error_message) => {
+                error!("{}", error_message);
+                process::exit(2);
+            },
+        };
+        if args.dry_run {
+            list_files(&paths);
+            finish(&args, started);
+            return;
+        }
+        let mut writer = output_writer(&args, true);
+        if args.print_flags {
+            writer.write_configuration(&configuration(&args, &analysis_options(&args)));
+        }
+        let batch = analyze_files(&paths, &args, writer.as_mut());
+        writer.finish();
+        let reports = batch_reports(batch);
+        compare_with_report(&args, &reports);
+        display_entropy_histogram(&args, &reports);
+        finish(&args, started);
+        return;
+    }
+
+    let path = match args.path.clone() {
+        Some(path) => path,
+        None => {
+            println!("Enter the path to the image file:");
+
+            let mut path = String::new();
+            io::stdin()
+                .read_line(&mut path)
+                .expect("Failed to read input.");
+            PathBuf::from(path.trim()) // Trim newline or extra spaces
+        },
+    };
+
+    if args.dry_run {
+        let paths = if path.is_dir() { directory_files(&path, &args) } else { vec![path] };
+        list_files(&paths);
+        finish(&args, started);
+        return;
+    }
+
+    let mut writer = output_writer(&args, path.is_dir());
+    if args.print_flags {
+        writer.write_configuration(&configuration(&args, &analysis_options(&args)));
+    }
+    let batch = if path.is_dir() {
+        analyze_directory(&path, &args, writer.as_mut())
+    } else {
+        // A lone file that fails is reported like a batch entry, then fails the run
+        analyze_file(&path, &args, writer.as_mut())
+            .map(|report| report.into_iter().collect())
+            .inspect_err(|error_message| writer.write_error(&path.display().to_string(), error_message))
+    };
+    writer.finish();
+    let reports = batch_reports(batch);
+    compare_with_report(&args, &reports);
+    display_entropy_histogram(&args, &reports);
+    finish(&args, started);
+}
+
+/// Saves the --train-ppm models, logs the --timing total and completion, and exits with the alert
+/// code if --alert-exit-code applies.
+fn finish(args: &Args, started: Instant) {
+    if let Some(model_path) = &args.train_ppm {
+        match TRAINED_PPM_MODELS.lock().expect("PPM training lock poisoned").take() {
+            Some(models) => match ppm_model::save_models(model_path, &models) {
+                Ok(()) => info!("Saved the trained PPM model to '{}'.", model_path.display()),
+                Err(error_message) => error!("{}", error_message),
+            },
+            None => warn!("No image was analyzed, so no PPM model was saved."),
+        }
+    }
+    if args.timing {
+        let pixels = TIMED_PIXELS.load(Ordering::Relaxed);
+        let bytes = TIMED_BYTES.load(Ordering::Relaxed);
+        info!("Total time: {}", throughput(started.elapsed(), pixels, bytes));
+    }
+    info!("Program completed.");
+    if args.alert_exit_code && ALERT_RAISED.load(Ordering::Relaxed) {
+        process::exit(ALERT_EXIT_CODE);
+    }
+}
+
+/// Warns about each channel whose entropy lies outside the --alert-below/--alert-above band.
+fn check_entropy_alerts(name: &str, channel_entropy: [f64; 3], args: &Args) {
+    for (channel, entropy) in ["Red", "Green", "Blue"].iter().zip(channel_entropy) {
+        let entropy = args.units.from_bits(entropy);
+        let alert = match (args.alert_below, args.alert_above) {
+            (Some(below), _) if entropy < below => Some(("below", below)),
+            (_, Some(above)) if entropy > above => Some(("above", above)),
+            _ => None,
+        };
+        if let Some((side, threshold)) = alert {
+            warn!(
+                "{}: {} channel entropy {:.2} {}/pixel is {} the alert threshold of {}.",
+                name, channel, entropy, args.units.name(), side, threshold
+            );
+            ALERT_RAISED.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Prints how this run differs from the `--compare-report` report, if one was given.
+fn compare_with_report(args: &Args, reports: &[FileReport]) {
+    if let Some(report_path) = &args.compare_report {
+        match compare::load_report(report_path) {
+            Ok(previous) => display_report_diff(report_path, &previous, reports, args.units),
+            Err(error_message) => error!("{}", error_message),
+        }
+    }
+}
+
+/// Prints how the total entropy of the analyzed files is distributed, if --entropy-histogram was
+/// given: a bar per equal-width bin, then the percentiles. Files whose entropy was skipped are left out.
+fn display_entropy_histogram(args: &Args, reports: &[FileReport]) {
+    if !args.entropy_histogram {
+        return;
+    }
+    let mut entropies: Vec<f64> =
+        reports.iter().filter_map(|report| report.result.total_entropy).map(|entropy| args.units.from_bits(entropy)).collect();
+    if entropies.is_empty() {
+        warn!("No file's entropy was measured, so there is no entropy histogram.");
+        return;
+    }
+    let statistics = distribution_statistics(&mut entropies);
+    let (min, max) = (statistics[0], statistics[5]);
+
+    // Files that all share one entropy fill a single bin
+    let bins = if max > min { args.histogram_bins as usize } else { 1 };
+    let width = (max - min) / bins as f64;
+    let mut counts = vec![0usize; bins];
+    for &entropy in &entropies {
+        let bin = if width > 0.0 { ((entropy - min) / width) as usize } else { 0 };
+        counts[bin.min(bins - 1)] += 1;
+    }
+
+    println!("Entropy Distribution ({} files, {}/pixel):", entropies.len(), args.units.name());
+    let largest = counts.iter().copied().max().unwrap_or(0);
+    let labels: Vec<String> = (0..bins)
+        .map(|index| format!("{:.2} - {:.2}", min + width * index as f64, min + width * (index + 1) as f64))
+        .collect();
+    let label_width = labels.iter().map(String::len).max().unwrap_or(0);
+    for (label, &count) in labels.iter().zip(&counts) {
+        let bar = "#".repeat((count * HISTOGRAM_BAR_WIDTH).div_ceil(largest));
+        println!("{:>label_width$} | {:<bar_width$} {}", label, bar, count, bar_width = HISTOGRAM_BAR_WIDTH);
+    }
+
+    let mut table = Table::new();
+    table.add_row(Row::new(DISTRIBUTION_COLUMNS.iter().map(|title| Cell::new(title)).collect())); // Header
+    table.add_row(Row::new(statistics.iter().map(|value| Cell::new(&format!("{:.2}", value))).collect()));
+    table.printstd();
+}
+
+/// Analyzes every supported image directly inside the directory, as `analyze_files` does.
+fn analyze_directory(dir_path: &Path, args: &Args, writer: &mut dyn OutputWriter) -> Result<Vec<FileReport>, String> {
+    analyze_files(&directory_files(dir_path, args), args, writer)
+}
+
+/// The files directly inside the directory that --include and --exclude select, in name order.
+fn directory_files(dir_path: &Path, args: &Args) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(dir_path) {
+        Ok(entries) => entries,
+        Err(err) => {
+            error!("Failed to read directory '{}': {}", dir_path.display(), err);
+            return Vec::new();
+        },
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && is_selected_extension(path, &args.include, &args.exclude))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Prints the files with their sizes for --dry-run, reading only their metadata.
+fn list_files(paths: &[PathBuf]) {
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![Cell::new("File"), Cell::new("Size (bytes)")])); // Header
+    let mut total_size = 0;
+    let mut missing = 0;
+    for path in paths {
+        let size = match fs::metadata(path) {
+            Ok(metadata) => {
+                total_size += metadata.len();
+                metadata.len().to_string()
+            },
+            Err(err) => {
+                warn!("{}: {}", path.display(), err);
+                missing += 1;
+                String::from("-")
+            },
+        };
+        table.add_row(Row::new(vec![Cell::new(&path.display().to_string()), Cell::new(&size)]));
+    }
+    table.printstd();
+    println!("Would Analyze: {} files, {} bytes", paths.len() - missing, total_size);
+    if missing > 0 {
+        println!("Unreadable: {} files", missing);
+    }
+}
+
+/// Reads the --files-from list: one path per line, from stdin for `-`. Blank lines are skipped.
+fn read_file_list(list_path: &Path) -> Result<Vec<PathBuf>, String> {
+    let contents = if list_path.as_os_str() == "-" {
+        io::read_to_string(io::stdin()).map_err(|err| format!("Error: Unable to read the file list from stdin: {}", err))?
+    } else {
+        fs::read_to_string(list_path)
+            .map_err(|err| format!("Error: Unable to read the file list '{}': {}", list_path.display(), err))?
+    };
+    Ok(contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Analyzes each file in turn. A file that fails is logged and reported as an error entry, and the
+/// batch moves on; with --strict it stops there and returns an error instead, once the failure is
+/// recorded, so the caller can still finish the output before exiting.
+fn analyze_files(paths: &[PathBuf], args: &Args, writer: &mut dyn OutputWriter) -> Result<Vec<FileReport>, String> {
+    let mut failures = 0;
+    let mut reports = Vec::new(); // Kept for --compare-report
+    for path in paths {
+        if args.format == OutputFormat::Table {
+            println!("File: {}", path.display());
+        }
+        match analyze_file(path, args, writer) {
+            Ok(report) => reports.extend(report),
+            Err(error_message) => {
+                let name = path.display().to_string();
+                error!("{}: {}", name, error_message);
+                failures += 1;
+                writer.write_error(&name, &error_message);
+                if args.strict {
+                    return Err(String::from("Stopping at the first failure, as --strict was given."));
+                }
+            },
+        }
+    }
+    info!("Analyzed {} files, {} failed.", paths.len(), failures);
+    Ok(reports)
+}
+
+/// The reports of a finished batch; a batch that --strict stopped exits with status 1 instead.
+fn batch_reports(batch: Result<Vec<FileReport>, String>) -> Vec<FileReport> {
+    batch.unwrap_or_else(|error_message| {
+        error!("{}", error_message);
+        process::exit(1);
+    })
+}
+
+/// Whether the path has an extension the analyzer accepts.
+fn is_supported_extension(path: &Path) -> bool {
+    let ext = file_extension(path);
+    RAW_EXTENSIONS.contains(&ext.as_str()) || FLOAT_EXTENSIONS.contains(&ext.as_str())
+        || IMAGE_EXTENSIONS.contains(&ext.as_str())
+}
+
+/// Applies `--include` and `--exclude` to a walked file; extensions match without case or a leading dot.
+/// A non-empty include list replaces the supported-extension check.
+fn is_selected_extension(path: &Path, include: &[String], exclude: &[String]) -> bool {
+    let ext = file_extension(path);
+    let matches = |list: &[String]| list.iter().any(|entry| entry.trim_start_matches('.').eq_ignore_ascii_case(&ext));
+    let included = if include.is_empty() { is_supported_extension(path) } else { matches(include) };
+    included && !matches(exclude)
+}
+
+/// Decodes the image, logging its dimensions up front and a heartbeat while a long decode runs.
+/// The decoders report no progress of their own, so elapsed time is the best signal available.
+fn decode_with_feedback(path: &Path, data: &[u8], force: bool, signed: bool) -> Result<DynamicImage, String> {
+    if let Some((width, height)) = image_dimensions(path) {
+        info!("Decoding {}x{} image...", width, height);
+    }
+
+    let started = Instant::now();
+    let (done_sender, done_receiver) = mpsc::channel::<()>();
+    let heartbeat = thread::spawn(move || {
+        while let Err(RecvTimeoutError::Timeout) = done_receiver.recv_timeout(DECODE_HEARTBEAT) {
+            info!("Still decoding after {} seconds...", started.elapsed().as_secs());
+        }
+    });
+
+    let img = if signed {
+        signed::decode_signed_image(path, data)
+    } else if force {
+        decode_image_file_by_content(path, data)
+    } else {
+        decode_image_file(path, data)
+    };
+    drop(done_sender); // Disconnecting the channel stops the heartbeat
+    let _ = heartbeat.join();
+
+    if img.is_ok() {
+        info!("Image successfully loaded in {:.2} seconds.", started.elapsed().as_secs_f64());
+    }
+    img
+}
+
+/// Reads the `--format json` reports and prints them combined as one batch report.
+fn merge_reports(paths: &[PathBuf]) -> Result<(), String> {
+    let reports = paths
+        .iter()
+        .map(|path| {
+            let contents = fs::read_to_string(path)
+                .map_err(|err| format!("Error: Unable to read the report '{}': {}", path.display(), err))?;
+            let document = serde_json::from_str(&contents)
+                .map_err(|err| format!("Error: '{}' is not a pictropy JSON report: {}", path.display(), err))?;
+            Ok((path.display().to_string(), document))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    output::print_merged_report(reports)
+}
+
+/// Analyzes a single image file and prints its results.
+/// With `--compare-report` the report is also returned to the caller.
+fn analyze_file(path: &Path, args: &Args, writer: &mut dyn OutputWriter) -> Result<Option<FileReport>, String> {
+    // Label used for the file in the results; lossy only for names that aren't valid UTF-8
+    let name = path.display().to_string();
+
+    // Validate file path and format
+    let ext = file_extension(path);
+    let is_raw = RAW_EXTENSIONS.contains(&ext.as_str());
+    let is_float = FLOAT_EXTENSIONS.contains(&ext.as_str());
+    if !is_raw && !is_float && !args.force && !IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+        return Err(String::from("Unsupported file format. Please use JPG, PNG, QOI, TIFF or HDR files."));
+    }
+
+    // Everything on stdout must belong to the format's own output
+    let text_only = is_raw || is_float || args.jpeg_dct || args.png_idat || args.apng_frames || args.tile_grid.is_some()
+        || args.jpeg_blocks || args.output.is_some() || args.compare_original_format || args.target_size.is_some()
+        || args.compare_lossy_sweep || args.png_color_types || args.output_quality_table
+        || args.compression_target.is_some();
+    if !args.format.allows_prose() && text_only {
+        return Err(String::from(
+            "Error: Only --format table has room for RAW and HDR files, --jpeg-dct, --png-idat, \
+             --apng-frames, --tile-grid, --jpeg-blocks, --output, --compare-original-format, --target-size, \
+             --compare-lossy-sweep, --png-color-types, --output-quality-table or --compression-target.",
+        ));
+    }
+
+    // Get the actual file size from metadata
+    let metadata = fs::metadata(path).map_err(|err| format!("Failed to access file: {}", err))?;
+    let file_size = metadata.len(); // File size in bytes
+
+    // Reject oversized images from their header, before any large allocation
+    if let Some(max_pixels) = args.max_pixels {
+        let dimensions = if is_raw { raw_dimensions(path) } else { image_dimensions(path) };
+        check_max_pixels(&name, dimensions, max_pixels)?;
+    }
+
+    if is_raw {
+        analyze_raw(path, file_size, args.relative_to, args.units)?;
+        return Ok(None);
+    }
+    if is_float {
+        analyze_float(path, file_size, args.float_bins, args.relative_to, args.units)?;
+        return Ok(None);
+    }
+
+    if args.jpeg_dct {
+        if !["jpg", "jpeg"].contains(&ext.as_str()) {
+            return Err(String::from("DCT coefficient analysis is only available for JPEG files."));
+        }
+        analyze_jpeg_dct(path, file_size, args.relative_to, args.units)?;
+        return Ok(None);
+    }
+
+    if args.png_idat {
+        if ext != "png" {
+            return Err(String::from("IDAT analysis is only available for PNG files."));
+        }
+        analyze_png_idat(path, file_size, args.relative_to, args.units)?;
+        return Ok(None);
+    }
+
+    if args.apng_frames {
+        if ext != "png" {
+            return Err(String::from("Frame analysis is only available for animated PNG files."));
+        }
+        return display_apng_frames(path, args.channel_order, args.units).map(|()| None);
+    }
+
+    let started = Instant::now();
+    let data = fs::read(path).map_err(|err| format!("Error: Unable to read '{}': {}", name, err))?;
+    // Hash the bytes that are decoded, once, for both the cache key and --with-hash
+    let content_hash = (args.cache.is_some() || args.with_hash).then(|| cache::content_hash(&data));
+    let decoded = decode_with_feedback(path, &data, args.force, args.signed)?;
+    drop(data);
+    let (width, height) = decoded.dimensions();
+    let report = analyze_image(&name, Some(path), content_hash, file_size, decoded, args, writer);
+    if args.timing {
+        record_timing(&name, started, width as u64 * height as u64, file_size);
+    }
+    report
+}
+
+/// Logs the time one image took, from decoding to its last report, and adds it to the run total.
+fn record_timing(name: &str, started: Instant, pixels: u64, bytes: u64) {
+    TIMED_PIXELS.fetch_add(pixels, Ordering::Relaxed);
+    TIMED_BYTES.fetch_add(bytes, Ordering::Relaxed);
+    info!("{}: {}", name, throughput(started.elapsed(), pixels, bytes));
+}
+
+/// Formats an elapsed time with the megapixels and megabytes (10^6) processed per second.
+fn throughput(elapsed: Duration, pixels: u64, bytes: u64) -> String {
+    let seconds = elapsed.as_secs_f64();
+    format!(
+        "{:.3} seconds for {:.2} MP and {:.2} MB ({:.2} MP/s, {:.2} MB/s)",
+        seconds,
+        pixels as f64 / 1e6,
+        bytes as f64 / 1e6,
+        pixels as f64 / 1e6 / seconds,
+        bytes as f64 / 1e6 / seconds
+    )
+}
+
+/// Analyzes the clipboard image as if it were a PNG file holding it.
+fn analyze_clipboard(args: &Args, writer: &mut dyn OutputWriter) -> Result<Option<FileReport>, String> {
+    let started = Instant::now();
+    let img = read_clipboard_image()?;
+    let (width, height) = img.dimensions();
+    info!("Read a {}x{} image from the clipboard.", width, height);
+    let file_size = encode::encode_png_best(&img)?.len() as u64;
+    let report = analyze_image("clipboard", None, None, file_size, img, args, writer);
+    if args.timing {
+        record_timing("clipboard", started, width as u64 * height as u64, file_size);
+    }
+    report
+}
+
+#[cfg(feature = "clipboard")]
+fn read_clipboard_image() -> Result<DynamicImage, String> {
+    clipboard::read_clipboard_image()
+}
+
+/// Reports that clipboard support was not compiled in.
+#[cfg(not(feature = "clipboard"))]
+fn read_clipboard_image() -> Result<DynamicImage, String> {
+    Err(String::from(
+        "Error: --clipboard requires the `clipboard` feature. Rebuild with `cargo build --features clipboard`.",
+    ))
+}
+
+/// Runs the analysis and the requested reports on a decoded image. `path` is the file it was
+/// read from, if any; the reports that read the file itself are skipped without one.
+/// `content_hash` is the hash of the file's bytes, with --cache or --with-hash.
+fn analyze_image(
+    name: &str,
+    path: Option<&Path>,
+    content_hash: Option<blake3::Hash>,
+    file_size: u64,
+    decoded: DynamicImage,
+    args: &Args,
+    writer: &mut dyn OutputWriter,
+) -> Result<Option<FileReport>, String> {
+    analysis::check_dimensions(&decoded)?;
+
+    // Convert first, so every later step sees the same color type whatever the file held
+    let native_color = decoded.color();
+    let decoded = match forced_color(args) {
+        Some(ColorType::Rgb8) if native_color != ColorType::Rgb8 => DynamicImage::ImageRgb8(decoded.to_rgb8()),
+        Some(ColorType::L8) if native_color != ColorType::L8 => DynamicImage::ImageLuma8(decoded.to_luma8()),
+        _ => decoded,
+    };
+
+    // Trim before posterizing, so the margins are found in the original colors
+    let (full_width, full_height) = decoded.dimensions();
+    let found_borders = (args.detect_borders || args.trim_borders).then(|| borders::detect_borders(&decoded));
+    let decoded = match &found_borders {
+        Some(found) if args.trim_borders && !found.is_empty() => borders::trim_borders(&decoded, found),
+        _ => decoded,
+    };
+
+    // Posterize before anything measures the pixels, so every figure and --output reflect the preview
+    let (img, unmasked) = match args.msb_only {
+        Some(bits) => (quantize::keep_msb(&decoded, bits), Some(decoded)),
+        None => (decoded, None),
+    };
+
+    if let (Some(grid), Some(threshold)) = (args.tile_grid, args.exclude_uniform_regions) {
+        return display_content_entropy(&img, grid, threshold, args.channel_order, args.units).map(|()| None);
+    }
+    if let Some(grid) = args.tile_grid {
+        return display_tile_grid(&img, grid, args.channel_order, args.units).map(|()| None);
+    }
+    if args.jpeg_blocks {
+        display_jpeg_blocks(&img, args.channel_order, args.units);
+        return Ok(None);
+    }
+
+    let options = analysis_options(args);
+    let result = match (&args.cache, &content_hash) {
+        (Some(cache_dir), Some(content_hash)) => {
+            let parameters = analysis_parameters(&options, args.msb_only, args.trim_borders, forced_color(args), args.signed);
+            let key = cache::cache_key(content_hash, &parameters);
+            analyze_cached(&img, &options, cache_dir, &key)
+        },
+        _ => analyze(&img, &options),
+    }?;
+    let content_hash = content_hash.filter(|_| args.with_hash).map(|hash| hash.to_hex().to_string());
+    let AnalysisResult { transparent_pixels, analyzed_pixels, channel_entropy, .. } = result;
+
+    // Derive the theoretical lossless limit
+    let entropy_limit = channel_entropy
+        .map(|channel_entropy| EntropyLimit::new(channel_entropy, analyzed_pixels as f64, file_size));
+    let sample_rate = args.sample_rate as usize;
+    if let Some(channel_entropy) = channel_entropy {
+        check_entropy_alerts(name, channel_entropy, args);
+    }
+
+    // Advisories are gathered through the reports and presented together after them
+    let mut warnings = entropy_limit.as_ref().map_or_else(Vec::new, EntropyLimit::warnings);
+    if sample_rate > 1 {
+        warnings.push(Warning::new(
+            WarningCode::Sampled,
+            format!(
+                "Only every {} pixel was analyzed; entropy is estimated and sizes are extrapolated.",
+                ordinal(sample_rate)
+            ),
+        ));
+    }
+    if transparent_pixels > 0 {
+        warnings.push(Warning::new(
+            WarningCode::TransparentExcluded,
+            format!("{} fully transparent pixels were left out of the color histograms.", transparent_pixels),
+        ));
+    }
+    if args.signed {
+        warnings.push(Warning::new(
+            WarningCode::SignedSamples,
+            "The 16-bit samples were read as signed integers, offset by 32768 so they run in value order.",
+        ));
+    }
+    if let Some(color) = forced_color(args).filter(|&color| color != native_color) {
+        warnings.push(Warning::new(
+            WarningCode::Converted,
+            format!("The image was converted from {:?} to {:?} before analysis.", native_color, color),
+        ));
+    }
+    if options.compressor == Compressor::Webp && !options.entropy_only && is_16_bit(&img) {
+        warnings.push(Warning::new(
+            WarningCode::Downconverted,
+            "WebP holds 8 bits per sample, so the 16-bit samples were rounded to 8 bits for the WebP size.",
+        ));
+    }
+    // A strip has no 2D neighborhoods to filter or downscale, so only the plain entropy applies
+    let strip = analysis::is_strip(&img);
+    if strip && (args.denoise.is_some() || args.pyramid.is_some()) {
+        warnings.push(Warning::new(
+            WarningCode::StripSkipped,
+            "The image is a single row or column, so --denoise and --pyramid were skipped.",
+        ));
+    }
+
+    let stored_channels = args.all_channels.then(|| stored_channel_entropy(&img));
+    writer.write_results(&FileResults {
+        name,
+        file_size,
+        result: &result,
+        entropy_limit: entropy_limit.as_ref(),
+        content_hash: content_hash.as_deref(),
+        stored_channels: stored_channels.as_deref(),
+        img: &img,
+        options: &options,
+    })?;
+
+    if args.train_ppm.is_some() {
+        let (red_channel, green_channel, blue_channel) = analysis::split_channels(&img, &options)?;
+        let mut trained = TRAINED_PPM_MODELS.lock().expect("PPM training lock poisoned");
+        let models = trained.get_or_insert_with(|| std::array::from_fn(|_| PpmModel::new(PPM_ORDER)));
+        for (model, channel) in models.iter_mut().zip([red_channel, green_channel, blue_channel]) {
+            model.code(&channel, args.ppm_warmup);
+        }
+    }
+
+    // The extra reports are prose, so they only accompany the table
+    if args.format.allows_prose() {
+        if let Some(path) = path {
+            report_icc_profile(path, args.assume_srgb);
+            if args.analyze_metadata {
+                report_metadata(path, file_size);
+            }
+        }
+
+        if let Some(found) = &found_borders {
+            report_borders(found, full_width, full_height, args.trim_borders);
+        }
+
+        if args.smoothing != Smoothing::None && entropy_limit.is_some() {
+            println!("Entropy Smoothing: {}", smoothing_description(args));
+        }
+
+        if let Some(stored_channels) = &stored_channels {
+            display_all_channels(stored_channels, args.limit_channels_output, args.units);
+        }
+
+        if args.channels_combined {
+            println!(
+                "Combined RGBA Byte Entropy: {:.2} {}/byte",
+                args.units.from_bits(interleaved_entropy(&img)),
+                args.units.name()
+            );
+        }
+
+        if args.gradient_entropy {
+            println!(
+                "Luma Gradient Entropy (Sobel): {:.2} {}/pixel",
+                args.units.from_bits(gradient_entropy(&img)),
+                args.units.name()
+            );
+        }
+
+        if args.unique_colors {
+            let limit = (!args.exact_unique_colors).then_some(args.max_unique_colors);
+            report_unique_colors(&img, limit);
+        }
+
+        if args.show_channel_correlation_matrix {
+            let channels = analysis::split_channels(&img, &options)?;
+            display_mutual_information(&channels, args.units);
+        }
+
+        if args.entropy_rate {
+            let channels = analysis::split_channels(&img, &options)?;
+            display_entropy_rate(&channels, args.units);
+        }
+
+        if let Some(k) = args.top_values {
+            let channels = analysis::split_channels(&img, &options)?;
+            display_top_values(&channels, k as usize, args.units);
+        }
+
+        if args.channel_histogram_entropy_variance {
+            let channels = analysis::split_channels(&img, &options)?;
+            display_histogram_variance(&channels, args.units);
+        }
+
+        if let (Some(filter), Some(limit), false) = (args.denoise, &entropy_limit, strip) {
+            report_denoise(&img, filter, args.channel_order, limit.total_entropy, args.units, &mut warnings);
+        }
+
+        if let Some(filter) = args.png_filter {
+            report_png_filter(&img, filter, file_size, args.relative_to, args.units);
+        }
+
+        if let Some(colors) = args.palette_preview {
+            report_palette_preview(&img, colors as usize, file_size, args.relative_to, args.units);
+        }
+
+        if let (Some(bits), Some(unmasked), Some(limit)) = (args.msb_only, &unmasked, &entropy_limit) {
+            report_msb_only(unmasked, bits, args.channel_order, limit.total_entropy, args.units);
+        }
+
+        if let (Some(reference), Some(channel_entropy)) = (LOADED_REFERENCE.get(), channel_entropy) {
+            let channels = analysis::split_channels(&img, &options)?;
+            report_cross_entropy(&channels, reference, channel_entropy, args.units);
+        }
+
+        if let (Some(levels), false) = (args.pyramid, strip) {
+            display_pyramid(&img, levels as usize, args.channel_order, args.units);
+        }
+
+        if let Some(colorspace) = args.colorspace {
+            display_colorspace_entropy(&img, colorspace, args.channel_order, args.units);
+        }
+
+        if let Some(max_order) = args.ppm_order_sweep {
+            let channels = analysis::split_channels(&img, &options)?;
+            display_ppm_order_sweep(&channels, max_order as usize, &options, args.relative_to, file_size);
+        }
+
+        if let Some(models) = LOADED_PPM_MODELS.get() {
+            let channels = analysis::split_channels(&img, &options)?;
+            report_ppm_model(&channels, models, &options, result.compressed_sizes.as_ref());
+        }
+
+        if args.recommend {
+            report_recommendation(&img, file_size, entropy_limit.as_ref());
+        }
+
+        if args.complexity {
+            report_complexity(&img, &options, result.compressed_sizes.as_ref(), file_size, &mut warnings);
+        }
+    }
+
+    if let (Some(db_path), Some(limit), Some(compressed_sizes)) = (&args.stats_db, &entropy_limit, &result.compressed_sizes) {
+        let [red_entropy, green_entropy, blue_entropy] = limit.channel_entropy;
+        let record = AnalysisRecord {
+            filename: name,
+            red_entropy,
+            green_entropy,
+            blue_entropy,
+            total_entropy: limit.total_entropy,
+            original_size: file_size,
+            theoretical_size: limit.theoretical_minimum_size,
+            compressed_size: compressed_sizes.total(),
+        };
+        if let Err(error_message) = stats_db::append_record(db_path, &record) {
+            error!("{}", error_message);
+        }
+    }
+
+    if let (true, Some(path)) = (args.compare_original_format, path) {
+        report_original_format(&img, path, file_size);
+    }
+
+    if args.png_color_types {
+        report_png_color_types(&img, file_size);
+    }
+
+    if let Some(target_size) = args.target_size {
+        report_target_size(&img, args.lossy_format, target_size, args.comparison_image.as_deref());
+    }
+
+    if let Some(target_size) = args.compression_target {
+        report_compression_target(&img, target_size);
+    }
+
+    if args.compare_lossy_sweep {
+        report_lossy_sweep(&img, args.lossy_format, file_size, args.sweep_csv.as_deref());
+    }
+
+    if args.output_quality_table {
+        report_output_quality(&img, args.lossy_format, file_size, args.min_psnr, args.output.as_deref());
+    } else if let Some(output_path) = &args.output {
+        write_output(&img, output_path, args.output_format, args.output_quality);
+    }
+
+    writer.write_warnings(name, &warnings);
+    Ok((args.compare_report.is_some() || args.entropy_histogram).then_some(FileReport {
+        file: name.to_string(),
+        original_size: file_size,
+        content_hash,
+        result,
+        stored_channels,
+        warnings,
+    }))
 }
@@ -0,0 +1,190 @@
+/// A kind of bytes in an image file that the pixels don't depend on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MetadataKind {
+    Exif,
+    /// The preview image embedded in the EXIF block, counted apart from the rest of it
+    ExifThumbnail,
+    Xmp,
+    Icc,
+    /// Photoshop image resources (JPEG APP13)
+    Photoshop,
+    /// JPEG comments and PNG text chunks
+    Text,
+    /// Other application segments and ancillary chunks
+    Other,
+    /// Bytes after the end of the image
+    Trailing,
+}
+
+impl MetadataKind {
+    /// Display name of the kind.
+    pub fn name(self) -> &'static str {
+        match self {
+            MetadataKind::Exif => "EXIF",
+            MetadataKind::ExifThumbnail => "EXIF Thumbnail",
+            MetadataKind::Xmp => "XMP",
+            MetadataKind::Icc => "ICC Profile",
+            MetadataKind::Photoshop => "Photoshop Resources",
+            MetadataKind::Text => "Comments and Text",
+            MetadataKind::Other => "Other Metadata",
+            MetadataKind::Trailing => "Trailing Data",
+        }
+    }
+}
+
+/// How a file's bytes divide between the image and its metadata.
+pub struct FileLayout {
+    /// Bytes needed to decode the pixels, container structure included
+    pub image_data: usize,
+    /// Metadata bytes per kind, in the order each kind first appears
+    pub metadata: Vec<(MetadataKind, usize)>,
+}
+
+impl FileLayout {
+    pub fn metadata_size(&self) -> usize {
+        self.metadata.iter().map(|&(_, size)| size).sum()
+    }
+
+    fn add(&mut self, kind: MetadataKind, size: usize) {
+        match self.metadata.iter_mut().find(|(existing, _)| *existing == kind) {
+            Some((_, total)) => *total += size,
+            None if size > 0 => self.metadata.push((kind, size)),
+            None => {},
+        }
+    }
+}
+
+/// Divides a JPEG, PNG or QOI file into image data and metadata by walking its segments or
+/// chunks. Each segment or chunk counts in full, marker, length and checksum included.
+pub fn file_layout(data: &[u8]) -> Result<FileLayout, String> {
+    if data.starts_with(&[0xFF, 0xD8]) {
+        jpeg_layout(data)
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_layout(data)
+    } else if data.starts_with(b"qoif") {
+        Ok(FileLayout { image_data: data.len(), metadata: Vec::new() }) // QOI has no place for metadata
+    } else {
+        Err(String::from("Error: Metadata analysis is only available for JPEG, PNG and QOI files."))
+    }
+}
+
+fn jpeg_layout(data: &[u8]) -> Result<FileLayout, String> {
+    let mut layout = FileLayout { image_data: 2, metadata: Vec::new() }; // SOI
+    let mut position = 2;
+    loop {
+        let Some(&[0xFF, marker]) = data.get(position..position + 2) else {
+            // Scan data or fill bytes ran to the end without an EOI
+            layout.image_data += data.len() - position;
+            return Ok(layout);
+        };
+        match marker {
+            0xFF => {
+                layout.image_data += 1; // Fill byte
+                position += 1;
+                continue;
+            },
+            0xD9 => {
+                layout.image_data += 2;
+                layout.add(MetadataKind::Trailing, data.len() - position - 2);
+                return Ok(layout);
+            },
+            0x01 | 0xD0..=0xD7 => {
+                layout.image_data += 2; // Markers without a length field
+                position += 2;
+                continue;
+            },
+            _ => {},
+        }
+
+        let length = data
+            .get(position + 2..position + 4)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]) as usize)
+            .ok_or_else(|| String::from("Error: JPEG segment extends past the end of the file."))?;
+        let segment = data
+            .get(position + 4..position + 2 + length)
+            .ok_or_else(|| String::from("Error: JPEG segment extends past the end of the file."))?;
+        let size = 2 + length;
+        position += size;
+
+        match marker {
+            0xE1 if segment.starts_with(b"Exif\0\0") => {
+                let thumbnail = exif_thumbnail_size(&segment[6..]).unwrap_or(0).min(size);
+                layout.add(MetadataKind::Exif, size - thumbnail);
+                layout.add(MetadataKind::ExifThumbnail, thumbnail);
+            },
+            0xE1 if segment.starts_with(b"http://ns.adobe.com/") => layout.add(MetadataKind::Xmp, size),
+            0xE2 if segment.starts_with(b"ICC_PROFILE\0") => layout.add(MetadataKind::Icc, size),
+            0xED => layout.add(MetadataKind::Photoshop, size),
+            0xFE => layout.add(MetadataKind::Text, size),
+            // JFIF and Adobe segments tell the decoder how to read the samples
+            0xE0 | 0xEE => layout.image_data += size,
+            0xE1..=0xEF => layout.add(MetadataKind::Other, size),
+            0xDA => {
+                // Entropy-coded data runs to the next marker other than a stuffed byte or a restart
+                let scan = data[position..]
+                    .windows(2)
+                    .position(|pair| pair[0] == 0xFF && !matches!(pair[1], 0x00 | 0xD0..=0xD7))
+                    .unwrap_or(data.len() - position);
+                layout.image_data += size + scan;
+                position += scan;
+            },
+            _ => layout.image_data += size,
+        }
+    }
+}
+
+/// Length of the JPEG thumbnail that IFD1 of an EXIF (TIFF) block points to.
+fn exif_thumbnail_size(tiff: &[u8]) -> Option<usize> {
+    let big_endian = match tiff.get(..2)? {
+        b"MM" => true,
+        b"II" => false,
+        _ => return None,
+    };
+    let read = |position: usize, length: usize| -> Option<usize> {
+        let bytes = tiff.get(position..position + length)?;
+        let fold = |value: usize, &byte: &u8| value << 8 | byte as usize;
+        Some(if big_endian { bytes.iter().fold(0, fold) } else { bytes.iter().rev().fold(0, fold) })
+    };
+
+    let ifd0 = read(4, 4)?;
+    let ifd1 = read(ifd0 + 2 + 12 * read(ifd0, 2)?, 4)?;
+    if ifd1 == 0 {
+        return None;
+    }
+    (0..read(ifd1, 2)?).find_map(|index| {
+        let entry = ifd1 + 2 + 12 * index;
+        // JPEGInterchangeFormatLength, a LONG
+        (read(entry, 2)? == 0x0202).then(|| read(entry + 8, 4)).flatten()
+    })
+}
+
+fn png_layout(data: &[u8]) -> Result<FileLayout, String> {
+    let mut layout = FileLayout { image_data: 8, metadata: Vec::new() }; // Signature
+    let mut position = 8;
+    while position + 8 <= data.len() {
+        let length = u32::from_be_bytes([data[position], data[position + 1], data[position + 2], data[position + 3]]) as usize;
+        let chunk_type = &data[position + 4..position + 8];
+        let size = 12 + length; // Length, type, data and CRC
+        let chunk = data
+            .get(position + 8..position + size)
+            .ok_or_else(|| String::from("Error: PNG chunk extends past the end of the file."))?;
+        position += size;
+
+        match chunk_type {
+            b"eXIf" => layout.add(MetadataKind::Exif, size),
+            b"iTXt" if chunk.starts_with(b"XML:com.adobe.xmp\0") => layout.add(MetadataKind::Xmp, size),
+            b"iCCP" => layout.add(MetadataKind::Icc, size),
+            b"tEXt" | b"zTXt" | b"iTXt" => layout.add(MetadataKind::Text, size),
+            // Besides the critical chunks, transparency and animation change what is drawn
+            b"tRNS" | b"acTL" | b"fcTL" | b"fdAT" => layout.image_data += size,
+            _ if chunk_type[0].is_ascii_lowercase() => layout.add(MetadataKind::Other, size),
+            b"IEND" => {
+                layout.image_data += size;
+                break;
+            },
+            _ => layout.image_data += size,
+        }
+    }
+    layout.add(MetadataKind::Trailing, data.len() - position);
+    Ok(layout)
+}
@@ -0,0 +1,912 @@
+use std::collections::HashMap;
+use image::DynamicImage;
+use log::{error, info, warn};
+use prettytable::{Table, Row, Cell};
+use serde::Serialize;
+use pictropy::analysis::{self, CompressedSizes};
+use pictropy::{byte_histogram, webp_compress, AnalysisOptions, AnalysisResult, Compressor, EntropyUnit};
+use crate::{EntropyLimit, RelativeTo};
+
+/// One analyzed file, as handed to an `OutputWriter`.
+pub struct FileResults<'a> {
+    pub name: &'a str,
+    pub file_size: u64,
+    pub result: &'a AnalysisResult,
+    /// Derived from the result's entropy; absent when entropy was skipped
+    pub entropy_limit: Option<&'a EntropyLimit>,
+    /// Hex BLAKE3 hash of the file, with --with-hash
+    pub content_hash: Option<&'a str>,
+    /// Entropy of every stored channel, with --all-channels
+    pub stored_channels: Option<&'a [StoredChannel]>,
+    /// The analyzed pixels, for formats that measure more than the result holds
+    pub img: &'a DynamicImage,
+    pub options: &'a AnalysisOptions,
+}
+
+/// Presents analysis results in one output format. A single writer serves the whole run, so formats
+/// with a header or a closing document keep their state between files.
+pub trait OutputWriter {
+    /// Presents one analyzed file.
+    fn write_results(&mut self, results: &FileResults) -> Result<(), String>;
+
+    /// Presents a file that could not be analyzed.
+    fn write_error(&mut self, path: &str, error_message: &str);
+
+    /// Records the effective configuration, for --print-flags. Formats without a place for it log it.
+    fn write_configuration(&mut self, configuration: &Configuration) {
+        info!("Configuration: {}", configuration.describe());
+    }
+
+    /// Presents the advisories raised while analyzing a file, after its results and reports.
+    /// Formats without a place for them log them.
+    fn write_warnings(&mut self, name: &str, warnings: &[Warning]) {
+        for warning in warnings {
+            warn!("{}: {}", name, warning);
+        }
+    }
+
+    /// Completes the output after the last file.
+    fn finish(&mut self) {}
+}
+
+/// Kind of advisory, serialized as a stable code that scripts can match on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WarningCode {
+    /// The entropy limit is no smaller than the file
+    AlreadyCompressed,
+    /// The entropy limit exceeds the file, so it was capped at the file size
+    ModelIneffective,
+    /// Only a sample of the pixels was analyzed
+    Sampled,
+    /// Fully transparent pixels were left out
+    TransparentExcluded,
+    /// The image is a single row or column, so spatial reports were skipped
+    StripSkipped,
+    /// No compressor shrank the data much
+    Incompressible,
+    /// Denoising removed a large share of the entropy
+    NoiseDominated,
+    /// 16-bit samples were rounded to 8 bits for an encoder
+    Downconverted,
+    /// The image was converted to another color type by --force-rgb or --force-gray
+    Converted,
+    /// The 16-bit samples were read as signed integers by --signed
+    SignedSamples,
+}
+
+impl WarningCode {
+    /// The code as serialized.
+    pub fn code(self) -> &'static str {
+        match self {
+            WarningCode::AlreadyCompressed => "already-compressed",
+            WarningCode::ModelIneffective => "model-ineffective",
+            WarningCode::Sampled => "sampled",
+            WarningCode::TransparentExcluded => "transparent-excluded",
+            WarningCode::StripSkipped => "strip-skipped",
+            WarningCode::Incompressible => "incompressible",
+            WarningCode::NoiseDominated => "noise-dominated",
+            WarningCode::Downconverted => "downconverted",
+            WarningCode::Converted => "converted",
+            WarningCode::SignedSamples => "signed-samples",
+        }
+    }
+}
+
+/// An advisory about a file's results, such as a figure that needs care to interpret.
+#[derive(Clone, Debug, Serialize)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(code: WarningCode, message: impl Into<String>) -> Self {
+        Warning { code, message: message.into() }
+    }
+
+    /// The file is at or below its entropy limit already.
+    pub fn already_compressed() -> Self {
+        Warning::new(
+            WarningCode::AlreadyCompressed,
+            "This file is already highly compressed and may not benefit from additional compression.",
+        )
+    }
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "Warning [{}]: {}", self.code.code(), self.message)
+    }
+}
+
+/// The settings that determine a run's figures, echoed by --print-flags so a report can be reproduced.
+#[derive(Serialize)]
+pub struct Configuration<'a> {
+    pub version: &'static str,
+    #[serde(flatten)]
+    pub options: &'a AnalysisOptions,
+    pub ppm_order: usize,
+    pub ppm_max_contexts: usize,
+    pub msb_only: Option<u8>,
+    pub trim_borders: bool,
+    pub force_color: Option<String>,
+    pub signed: bool,
+    pub units: EntropyUnit,
+}
+
+impl Configuration<'_> {
+    /// The settings as comma-separated `name=value` pairs.
+    pub fn describe(&self) -> String {
+        let serde_json::Value::Object(fields) = serde_json::to_value(self).expect("Configuration should serialize") else {
+            unreachable!("Configuration serializes as an object");
+        };
+        let pairs: Vec<String> = fields
+            .iter()
+            .map(|(name, value)| match value {
+                serde_json::Value::String(text) => format!("{}={}", name, text),
+                serde_json::Value::Null => format!("{}=none", name),
+                value => format!("{}={}", name, value),
+            })
+            .collect();
+        pairs.join(", ")
+    }
+}
+
+/// Formatted table followed by a prose summary.
+pub struct TableWriter {
+    pub relative_to: RelativeTo,
+    pub unit: EntropyUnit,
+    pub show_table: bool,
+}
+
+impl OutputWriter for TableWriter {
+    fn write_results(&mut self, results: &FileResults) -> Result<(), String> {
+        let unit = self.unit;
+        let original_size = results.file_size as usize;
+        let compressed_sizes = results.result.compressed_sizes.as_ref();
+        let size = |bytes: usize| self.relative_to.render(bytes as f64, 0, results.file_size);
+
+        let mut table = Table::new();
+        if let Some(limit) = results.entropy_limit {
+            let [red_entropy, green_entropy, blue_entropy] = limit.channel_entropy;
+            let entropy = |bits: f64| format!("{:.2}", unit.from_bits(bits));
+            table.add_row(Row::new(vec![
+                Cell::new("Color Channel"),
+                Cell::new(&format!("Entropy ({}/pixel)", unit.name())),
+            ])); // Header
+            table.add_row(Row::new(vec![Cell::new("Red"), Cell::new(&entropy(red_entropy))]));
+            table.add_row(Row::new(vec![Cell::new("Green"), Cell::new(&entropy(green_entropy))]));
+            table.add_row(Row::new(vec![Cell::new("Blue"), Cell::new(&entropy(blue_entropy))]));
+            table.add_row(Row::new(vec![Cell::new("Total"), Cell::new(&entropy(limit.total_entropy))]));
+        }
+        if let Some(compressed_sizes) = compressed_sizes {
+            let marker = expansion_marker(compressed_sizes.total(), original_size);
+            let compressed_description = match compressed_sizes {
+                CompressedSizes::PerChannel([red_compressed_size, green_compressed_size, blue_compressed_size]) => {
+                    format!(
+                        "Red: {}, Green: {}, Blue: {}{}",
+                        size(*red_compressed_size), size(*green_compressed_size), size(*blue_compressed_size), marker
+                    )
+                },
+                CompressedSizes::Whole(whole_compressed_size) => {
+                    format!("{}: {}{}", results.options.compressor.name(), size(*whole_compressed_size), marker)
+                },
+            };
+            table.add_row(Row::new(vec![
+                Cell::new(&format!("Compressed Size ({})", self.relative_to.unit())),
+                Cell::new(&compressed_description),
+            ]));
+        }
+        if self.show_table {
+            table.printstd();
+        }
+
+        println!("Original Size: {} bytes", original_size);
+        if let Some(content_hash) = results.content_hash {
+            println!("Content Hash (BLAKE3): {}", content_hash);
+        }
+        if let Some(limit) = results.entropy_limit {
+            println!(
+                "Theoretical Minimum Size (Lossless Limit): {} {}",
+                self.relative_to.render(limit.theoretical_minimum_size, 2, results.file_size),
+                self.relative_to.unit()
+            );
+            println!(
+                "Compression Percentage (Based on Entropy Limit): {:.2}%",
+                limit.compression_percentage
+            );
+        }
+
+        if let Some(compressed_sizes) = compressed_sizes {
+            let compressed_total_size = compressed_sizes.total();
+            let marker = expansion_marker(compressed_total_size, original_size);
+            println!("Total Compressed Size ({}): {}{}", self.relative_to.unit(), size(compressed_total_size), marker);
+            if let Some(limit) = results.entropy_limit
+                && let Some(efficiency) = limit.coding_efficiency(compressed_total_size)
+            {
+                // The ratio is against the uncapped size, which differs from the limit shown when capped
+                println!(
+                    "Entropy Size (Uncapped): {} {}",
+                    self.relative_to.render(limit.entropy_size, 2, results.file_size),
+                    self.relative_to.unit()
+                );
+                println!("Coding Efficiency (Compressed Size / Entropy Size): {:.2}%", efficiency);
+            }
+        }
+        Ok(())
+    }
+
+    fn write_warnings(&mut self, _name: &str, warnings: &[Warning]) {
+        for warning in warnings {
+            println!("{}", warning);
+        }
+    }
+
+    fn write_error(&mut self, _path: &str, error_message: &str) {
+        println!("Analysis failed: {}", error_message);
+    }
+
+    fn write_configuration(&mut self, configuration: &Configuration) {
+        println!("Configuration: {}", configuration.describe());
+    }
+}
+
+/// One space-separated line per file, for scripts.
+pub struct LineWriter {
+    pub unit: EntropyUnit,
+}
+
+impl OutputWriter for LineWriter {
+    fn write_results(&mut self, results: &FileResults) -> Result<(), String> {
+        let webp_size = match &results.result.compressed_sizes {
+            Some(CompressedSizes::Whole(webp_size)) if results.options.compressor == Compressor::Webp => Some(*webp_size),
+            Some(_) => Some(webp_compress(results.img)?),
+            None => None,
+        };
+        let total_entropy = results.entropy_limit.map_or_else(
+            || String::from("-"),
+            |limit| format!("{:.4}", self.unit.from_bits(limit.total_entropy)),
+        );
+        let (webp_size, savings_percentage) = match webp_size {
+            Some(webp_size) => (
+                webp_size.to_string(),
+                format!("{:.2}", (1.0 - webp_size as f64 / results.file_size as f64) * 100.0),
+            ),
+            None => (String::from("-"), String::from("-")),
+        };
+        let line = format!("{} {} {} {} {}", results.name, total_entropy, results.file_size, webp_size, savings_percentage);
+        match results.content_hash {
+            Some(content_hash) => println!("{} {}", line, content_hash),
+            None => println!("{}", line),
+        }
+        Ok(())
+    }
+
+    fn write_error(&mut self, path: &str, error_message: &str) {
+        println!("{} error {}", path, error_message);
+    }
+}
+
+/// A metric family of the Prometheus output.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MetricFamily {
+    Entropy,
+    StoredEntropy,
+    OriginalSize,
+    TheoreticalSize,
+    CompressedSize,
+    AnalysisError,
+}
+
+impl MetricFamily {
+    const ALL: [MetricFamily; 6] = [
+        MetricFamily::Entropy,
+        MetricFamily::StoredEntropy,
+        MetricFamily::OriginalSize,
+        MetricFamily::TheoreticalSize,
+        MetricFamily::CompressedSize,
+        MetricFamily::AnalysisError,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            MetricFamily::Entropy => "pictropy_entropy_bits",
+            MetricFamily::StoredEntropy => "pictropy_stored_channel_entropy_bits",
+            MetricFamily::OriginalSize => "pictropy_original_size_bytes",
+            MetricFamily::TheoreticalSize => "pictropy_theoretical_size_bytes",
+            MetricFamily::CompressedSize => "pictropy_compressed_size_bytes",
+            MetricFamily::AnalysisError => "pictropy_analysis_error",
+        }
+    }
+
+    fn help(self) -> &'static str {
+        match self {
+            MetricFamily::Entropy => "Shannon entropy in bits per pixel.",
+            MetricFamily::StoredEntropy => "Shannon entropy of each channel the image stores, in bits per pixel.",
+            MetricFamily::OriginalSize => "Size of the analyzed file.",
+            MetricFamily::TheoreticalSize => "Lossless size limit implied by the channel entropy.",
+            MetricFamily::CompressedSize => "Size estimated by a compressor.",
+            MetricFamily::AnalysisError => "Set for each file that couldn't be analyzed, with the reason.",
+        }
+    }
+}
+
+/// Prometheus text exposition format. The format wants each family's samples together under a
+/// single HELP and TYPE header, so samples are collected per file and printed in `finish`.
+#[derive(Default)]
+pub struct PrometheusWriter {
+    configuration: Option<String>,
+    samples: Vec<(MetricFamily, String)>,
+}
+
+impl PrometheusWriter {
+    fn add(&mut self, family: MetricFamily, labels: &str, value: impl std::fmt::Display) {
+        self.samples.push((family, format!("{}{{{}}} {}", family.name(), labels, value)));
+    }
+}
+
+impl OutputWriter for PrometheusWriter {
+    fn write_results(&mut self, results: &FileResults) -> Result<(), String> {
+        let file = escape_label(results.name);
+        let channels = ["red", "green", "blue"];
+
+        if let Some(limit) = results.entropy_limit {
+            for (channel, entropy) in channels.iter().zip(limit.channel_entropy) {
+                self.add(MetricFamily::Entropy, &format!("file=\"{}\",channel=\"{}\"", file, channel), entropy);
+            }
+            self.add(MetricFamily::Entropy, &format!("file=\"{}\",channel=\"total\"", file), limit.total_entropy);
+            self.add(MetricFamily::TheoreticalSize, &format!("file=\"{}\"", file), limit.theoretical_minimum_size);
+        }
+        for stored in results.stored_channels.unwrap_or_default() {
+            let labels = format!("file=\"{}\",channel=\"{}\"", file, escape_label(&stored.channel.to_lowercase()));
+            self.add(MetricFamily::StoredEntropy, &labels, stored.entropy);
+        }
+        self.add(MetricFamily::OriginalSize, &format!("file=\"{}\"", file), results.file_size);
+
+        match &results.result.compressed_sizes {
+            Some(CompressedSizes::PerChannel(sizes)) => {
+                for (channel, size) in channels.iter().zip(sizes) {
+                    let labels = format!("file=\"{}\",channel=\"{}\",compressor=\"ppm\"", file, channel);
+                    self.add(MetricFamily::CompressedSize, &labels, size);
+                }
+            },
+            Some(CompressedSizes::Whole(size)) => {
+                let compressor = results.options.compressor.name().to_lowercase();
+                self.add(MetricFamily::CompressedSize, &format!("file=\"{}\",compressor=\"{}\"", file, compressor), size);
+            },
+            None => {},
+        }
+        Ok(())
+    }
+
+    fn write_error(&mut self, path: &str, error_message: &str) {
+        let labels = format!("file=\"{}\",reason=\"{}\"", escape_label(path), escape_label(error_message));
+        self.add(MetricFamily::AnalysisError, &labels, 1);
+    }
+
+    fn write_configuration(&mut self, configuration: &Configuration) {
+        self.configuration = Some(configuration.describe());
+    }
+
+    fn finish(&mut self) {
+        if let Some(configuration) = &self.configuration {
+            println!("# Configuration: {}", configuration);
+        }
+        for family in MetricFamily::ALL {
+            let mut samples = self.samples.iter().filter(|(sample_family, _)| *sample_family == family).peekable();
+            if samples.peek().is_none() {
+                continue;
+            }
+            println!("# HELP {} {}", family.name(), family.help());
+            println!("# TYPE {} gauge", family.name());
+            for (_, sample) in samples {
+                println!("{}", sample);
+            }
+        }
+    }
+}
+
+/// One standalone HTML document for the whole run, with a section of histogram charts per file.
+pub struct HtmlWriter {
+    unit: EntropyUnit,
+    /// Description of the histogram smoothing, shown next to the entropy
+    smoothing: String,
+    /// Whether the document has been opened
+    started: bool,
+}
+
+impl HtmlWriter {
+    pub fn new(unit: EntropyUnit, smoothing: String) -> Self {
+        HtmlWriter { unit, smoothing, started: false }
+    }
+
+    /// Opens the document before the first file or error.
+    fn begin(&mut self) {
+        if self.started {
+            return;
+        }
+        self.started = true;
+        println!("<!DOCTYPE html>");
+        println!("<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">");
+        println!("<title>Pictropy</title>");
+        println!(
+            "<style>body {{ font-family: sans-serif; margin: 2em; }} table {{ border-collapse: collapse; }} \
+             td {{ border: 1px solid #ccc; padding: 0.3em 0.8em; }} td:last-child {{ text-align: right; }}</style>"
+        );
+        println!("</head>\n<body>");
+    }
+}
+
+impl OutputWriter for HtmlWriter {
+    fn write_results(&mut self, results: &FileResults) -> Result<(), String> {
+        // Histograms aren't cached, so the channels are split again here
+        let channels = analysis::split_channels(results.img, results.options)?;
+        let title = escape_html(results.name);
+        let rows = result_rows(results, &self.smoothing, self.unit);
+
+        self.begin();
+        println!("<section>\n<h1>{}</h1>\n<table>", title);
+        for (label, value) in rows {
+            println!("<tr><td>{}</td><td>{}</td></tr>", label, value);
+        }
+        println!("</table>");
+
+        let (red_channel, green_channel, blue_channel) = channels;
+        for (name, channel, color) in [
+            ("Red", red_channel, "#c0392b"),
+            ("Green", green_channel, "#27ae60"),
+            ("Blue", blue_channel, "#2c6fbb"),
+        ] {
+            println!("<h2>{} Histogram</h2>", name);
+            println!("{}", svg_histogram(&byte_histogram(&channel), color));
+        }
+        println!("</section>");
+        Ok(())
+    }
+
+    fn write_error(&mut self, path: &str, error_message: &str) {
+        self.begin();
+        println!("<p class=\"error\">{}: {}</p>", escape_html(path), escape_html(error_message));
+    }
+
+    fn finish(&mut self) {
+        // An empty run still produces a valid document
+        self.begin();
+        println!("</body>\n</html>");
+    }
+}
+
+/// Syntax of the single document written by a `DocumentWriter`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DocumentSyntax {
+    Json,
+    Toml,
+}
+
+/// One JSON or TOML document for the whole run: a file's object, or a directory's "files" array and "summary".
+pub struct DocumentWriter {
+    syntax: DocumentSyntax,
+    batch: bool,
+    outcomes: Vec<FileOutcome>,
+    /// Added as a "configuration" field when --print-flags is given
+    configuration: Option<serde_json::Value>,
+}
+
+impl DocumentWriter {
+    pub fn new(syntax: DocumentSyntax, batch: bool) -> Self {
+        DocumentWriter { syntax, batch, outcomes: Vec::new(), configuration: None }
+    }
+}
+
+impl OutputWriter for DocumentWriter {
+    fn write_results(&mut self, results: &FileResults) -> Result<(), String> {
+        self.outcomes.push(FileOutcome::Analyzed(FileReport {
+            file: results.name.to_string(),
+            original_size: results.file_size,
+            content_hash: results.content_hash.map(str::to_string),
+            result: results.result.clone(),
+            stored_channels: results.stored_channels.map(<[StoredChannel]>::to_vec),
+            warnings: Vec::new(),
+        }));
+        Ok(())
+    }
+
+    fn write_warnings(&mut self, name: &str, warnings: &[Warning]) {
+        if let Some(FileOutcome::Analyzed(report)) = self.outcomes.last_mut()
+            && report.file == name
+        {
+            report.warnings.extend_from_slice(warnings);
+        }
+    }
+
+    fn write_error(&mut self, path: &str, error_message: &str) {
+        self.outcomes.push(FileOutcome::Failed { file: path.to_string(), error: error_message.to_string() });
+    }
+
+    fn write_configuration(&mut self, configuration: &Configuration) {
+        self.configuration = serde_json::to_value(configuration).ok();
+    }
+
+    fn finish(&mut self) {
+        let document = if self.batch {
+            batch_document(self.outcomes.iter().map(|outcome| serde_json::to_value(outcome).unwrap_or_default()).collect())
+        } else if let Some(outcome) = self.outcomes.first() {
+            serde_json::to_value(outcome).unwrap_or_default()
+        } else {
+            return;
+        };
+        print_document(self.syntax, with_configuration(document, self.configuration.take()));
+    }
+}
+
+/// A batch document: the "files" array and the "summary" over them.
+fn batch_document(files: Vec<serde_json::Value>) -> serde_json::Value {
+    let summary = BatchSummary::new(&files);
+    serde_json::json!({ "files": files, "summary": summary })
+}
+
+fn with_configuration(document: serde_json::Value, configuration: Option<serde_json::Value>) -> serde_json::Value {
+    match (document, configuration) {
+        (serde_json::Value::Object(mut fields), Some(configuration)) => {
+            fields.insert(String::from("configuration"), configuration);
+            serde_json::Value::Object(fields)
+        },
+        (document, _) => document,
+    }
+}
+
+/// Combines `--format json` reports, such as those of shards of one library scanned on several
+/// machines, into one batch report with the summary recomputed over every file. The entries are
+/// copied as they are, so fields of any version of the report survive. A file found in more than
+/// one report keeps the entry of the last, so a shard that was run again can be given after the
+/// first attempt. The configuration is kept when every report has the same one.
+pub fn print_merged_report(reports: Vec<(String, serde_json::Value)>) -> Result<(), String> {
+    let report_count = reports.len();
+    let mut files: Vec<serde_json::Value> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut configurations = Vec::new();
+    for (name, document) in reports {
+        let not_a_report = || format!("Error: '{}' is not a pictropy JSON report.", name);
+        let serde_json::Value::Object(mut fields) = document else {
+            return Err(not_a_report());
+        };
+        configurations.extend(fields.remove("configuration"));
+        let entries = match fields.remove("files") {
+            Some(serde_json::Value::Array(entries)) => entries,
+            Some(_) => return Err(not_a_report()),
+            None if fields.contains_key("file") => vec![serde_json::Value::Object(fields)],
+            None => return Err(not_a_report()),
+        };
+
+        for entry in entries {
+            let file = entry.get("file").and_then(serde_json::Value::as_str).ok_or_else(not_a_report)?.to_string();
+            match positions.get(&file) {
+                Some(&position) => {
+                    warn!("'{}' appears in more than one report; keeping its entry from '{}'.", file, name);
+                    files[position] = entry;
+                },
+                None => {
+                    positions.insert(file, files.len());
+                    files.push(entry);
+                },
+            }
+        }
+    }
+
+    let agreed = configurations.windows(2).all(|pair| pair[0] == pair[1]);
+    if !agreed {
+        warn!("The reports were made with different settings, so their figures may not be comparable.");
+    }
+    let configuration = (agreed && configurations.len() == report_count).then(|| configurations.pop()).flatten();
+    print_document(DocumentSyntax::Json, with_configuration(batch_document(files), configuration));
+    Ok(())
+}
+
+/// Comma-separated values with a header row, one row per file.
+pub struct CsvWriter {
+    unit: EntropyUnit,
+    /// Adds a content_hash column after the file name
+    with_hash: bool,
+    /// Adds a stored_channel_entropy column of `channel:entropy` pairs before the error
+    all_channels: bool,
+    header_written: bool,
+}
+
+impl CsvWriter {
+    pub fn new(unit: EntropyUnit, with_hash: bool, all_channels: bool) -> Self {
+        CsvWriter { unit, with_hash, all_channels, header_written: false }
+    }
+
+    fn write_row(&mut self, fields: &[String]) {
+        if !self.header_written {
+            let unit = self.unit.name();
+            println!(
+                "file,{hash}width,height,red_entropy_{unit},green_entropy_{unit},blue_entropy_{unit},\
+                 total_entropy_{unit},original_size,theoretical_minimum_size,compressed_size,{stored}error",
+                hash = if self.with_hash { "content_hash," } else { "" },
+                stored = if self.all_channels { format!("stored_channel_entropy_{},", unit) } else { String::new() },
+                unit = unit
+            );
+            self.header_written = true;
+        }
+        let fields: Vec<String> = fields.iter().map(|field| escape_csv(field)).collect();
+        println!("{}", fields.join(","));
+    }
+}
+
+impl OutputWriter for CsvWriter {
+    fn write_results(&mut self, results: &FileResults) -> Result<(), String> {
+        let entropy = |bits: f64| format!("{:.4}", self.unit.from_bits(bits));
+        let mut fields = vec![results.name.to_string()];
+        if self.with_hash {
+            fields.push(results.content_hash.unwrap_or_default().to_string());
+        }
+        fields.push(results.result.width.to_string());
+        fields.push(results.result.height.to_string());
+        match results.entropy_limit {
+            Some(limit) => {
+                fields.extend(limit.channel_entropy.map(entropy));
+                fields.push(entropy(limit.total_entropy));
+            },
+            None => fields.extend(vec![String::new(); 4]),
+        }
+        fields.push(results.file_size.to_string());
+        fields.push(results.entropy_limit.map_or_else(String::new, |limit| format!("{:.2}", limit.theoretical_minimum_size)));
+        fields.push(results.result.compressed_size.map_or_else(String::new, |size| size.to_string()));
+        if self.all_channels {
+            let stored: Vec<String> = results
+                .stored_channels
+                .unwrap_or_default()
+                .iter()
+                .map(|stored| format!("{}:{}", stored.channel, entropy(stored.entropy)))
+                .collect();
+            fields.push(stored.join(";"));
+        }
+        fields.push(String::new());
+        self.write_row(&fields);
+        Ok(())
+    }
+
+    fn write_error(&mut self, path: &str, error_message: &str) {
+        let mut fields = vec![path.to_string()];
+        fields.extend(vec![String::new(); 9 + self.with_hash as usize + self.all_channels as usize]);
+        fields.push(error_message.to_string());
+        self.write_row(&fields);
+    }
+}
+
+/// Quotes a CSV field when it contains a delimiter, quote or line break.
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// A Markdown section with a two-column results table per file.
+pub struct MarkdownWriter {
+    pub unit: EntropyUnit,
+    /// Description of the histogram smoothing, shown next to the entropy
+    pub smoothing: String,
+}
+
+impl OutputWriter for MarkdownWriter {
+    fn write_results(&mut self, results: &FileResults) -> Result<(), String> {
+        let rows = result_rows(results, &self.smoothing, self.unit);
+        println!("## {}\n", escape_markdown(results.name));
+        println!("| Metric | Value |\n| --- | ---: |");
+        for (label, value) in rows {
+            println!("| {} | {} |", label, value);
+        }
+        println!();
+        Ok(())
+    }
+
+    fn write_error(&mut self, path: &str, error_message: &str) {
+        println!("## {}\n\nAnalysis failed: {}\n", escape_markdown(path), escape_markdown(error_message));
+    }
+
+    fn write_configuration(&mut self, configuration: &Configuration) {
+        println!("Configuration: {}\n", escape_markdown(&configuration.describe()));
+    }
+
+    fn write_warnings(&mut self, _name: &str, warnings: &[Warning]) {
+        for warning in warnings {
+            println!("> **Warning ({}):** {}\n", warning.code.code(), escape_markdown(&warning.message));
+        }
+    }
+}
+
+/// Escapes the characters that would end a table cell or start emphasis in Markdown.
+fn escape_markdown(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('|', "\\|").replace('*', "\\*").replace('_', "\\_")
+}
+
+/// Marks compressor output that is no smaller than the original file.
+fn expansion_marker(compressed_size: usize, original_size: usize) -> &'static str {
+    if compressed_size >= original_size {
+        " (expanded, not viable)"
+    } else {
+        ""
+    }
+}
+
+/// Entropy of one channel the image stores, such as gray or alpha.
+#[derive(Clone, Serialize)]
+pub struct StoredChannel {
+    pub channel: String,
+    /// Bits per pixel
+    pub entropy: f64,
+}
+
+/// One file's results in the JSON output.
+#[derive(Serialize)]
+pub struct FileReport {
+    pub file: String,
+    pub original_size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    #[serde(flatten)]
+    pub result: AnalysisResult,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stored_channels: Option<Vec<StoredChannel>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+}
+
+/// Entry of the JSON "files" array: the results, or why the file couldn't be analyzed.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum FileOutcome {
+    Analyzed(FileReport),
+    Failed { file: String, error: String },
+}
+
+/// Totals over a directory, closing the JSON document.
+#[derive(Serialize)]
+struct BatchSummary {
+    analyzed: usize,
+    failed: usize,
+    total_original_size: u64,
+    /// Sum over the files that ran a compressor
+    total_compressed_size: usize,
+    /// Mean over the files whose entropy was measured, absent if none was
+    mean_total_entropy: Option<f64>,
+}
+
+impl BatchSummary {
+    /// Totals over the entries of a "files" array, whether just written or read back from reports.
+    fn new(files: &[serde_json::Value]) -> Self {
+        let reports: Vec<&serde_json::Value> = files.iter().filter(|file| file.get("error").is_none()).collect();
+        let size = |report: &serde_json::Value, name: &str| report.get(name).and_then(serde_json::Value::as_u64);
+        let entropies: Vec<f64> =
+            reports.iter().filter_map(|report| report.get("total_entropy").and_then(serde_json::Value::as_f64)).collect();
+
+        BatchSummary {
+            analyzed: reports.len(),
+            failed: files.len() - reports.len(),
+            total_original_size: reports.iter().filter_map(|report| size(report, "original_size")).sum(),
+            total_compressed_size: reports.iter().filter_map(|report| size(report, "compressed_size")).sum::<u64>() as usize,
+            mean_total_entropy: (!entropies.is_empty())
+                .then(|| entropies.iter().sum::<f64>() / entropies.len() as f64),
+        }
+    }
+}
+
+/// Prints a value as a single pretty-printed document.
+fn print_document(syntax: DocumentSyntax, document: serde_json::Value) {
+    let rendered = match syntax {
+        DocumentSyntax::Json => serde_json::to_string_pretty(&document).map_err(|err| ("JSON", err.to_string())),
+        // Converting to a toml::Value first puts plain values ahead of tables, as TOML requires
+        DocumentSyntax::Toml => toml::Value::try_from(without_nulls(document))
+            .and_then(|value| toml::to_string_pretty(&value))
+            .map_err(|err| ("TOML", err.to_string())),
+    };
+    match rendered {
+        Ok(text) => println!("{}", text.trim_end()),
+        Err((syntax_name, err)) => error!("Failed to serialize the results as {}: {}", syntax_name, err),
+    }
+}
+
+/// Drops null fields and array entries, since TOML has no null; an absent key means the same.
+fn without_nulls(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(fields) => serde_json::Value::Object(
+            fields.into_iter().filter(|(_, value)| !value.is_null()).map(|(key, value)| (key, without_nulls(value))).collect(),
+        ),
+        serde_json::Value::Array(values) => {
+            serde_json::Value::Array(values.into_iter().filter(|value| !value.is_null()).map(without_nulls).collect())
+        },
+        value => value,
+    }
+}
+
+/// Escapes text for use as a Prometheus label value.
+fn escape_label(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Escapes text for inclusion in HTML element content or attribute values.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Renders a 256-bin histogram as an inline SVG bar chart.
+fn svg_histogram(histogram: &[u64; 256], color: &str) -> String {
+    const BAR_WIDTH: u64 = 2;
+    const HEIGHT: u64 = 128;
+    let tallest = histogram.iter().copied().max().unwrap_or(0).max(1);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+        256 * BAR_WIDTH, HEIGHT, 256 * BAR_WIDTH, HEIGHT
+    );
+    svg += &format!("<rect width=\"100%\" height=\"100%\" fill=\"#f4f4f4\"/>\n<g fill=\"{}\">\n", color);
+    for (value, &count) in histogram.iter().enumerate() {
+        let bar_height = count * HEIGHT / tallest;
+        if bar_height > 0 {
+            svg += &format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"><title>{}: {}</title></rect>\n",
+                value as u64 * BAR_WIDTH, HEIGHT - bar_height, BAR_WIDTH, bar_height, value, count
+            );
+        }
+    }
+    svg + "</g>\n</svg>"
+}
+
+/// Label and value rows summarizing one file, shared by the HTML and Markdown reports.
+fn result_rows(results: &FileResults, smoothing: &str, unit: EntropyUnit) -> Vec<(String, String)> {
+    let mut rows = Vec::new();
+    if let Some(limit) = results.entropy_limit {
+        let [red_entropy, green_entropy, blue_entropy] = limit.channel_entropy;
+        let entropy = |bits: f64| format!("{:.2}", unit.from_bits(bits));
+        rows.push((format!("Red Entropy ({}/pixel)", unit.name()), entropy(red_entropy)));
+        rows.push((format!("Green Entropy ({}/pixel)", unit.name()), entropy(green_entropy)));
+        rows.push((format!("Blue Entropy ({}/pixel)", unit.name()), entropy(blue_entropy)));
+        rows.push((format!("Total Entropy ({}/pixel)", unit.name()), entropy(limit.total_entropy)));
+        rows.push((String::from("Entropy Smoothing"), String::from(smoothing)));
+    }
+    for stored in results.stored_channels.unwrap_or_default() {
+        let label = format!("Stored {} Entropy ({}/pixel)", stored.channel, unit.name());
+        rows.push((label, format!("{:.2}", unit.from_bits(stored.entropy))));
+    }
+    rows.push((String::from("Original Size (bytes)"), results.file_size.to_string()));
+    if let Some(content_hash) = results.content_hash {
+        rows.push((String::from("Content Hash (BLAKE3)"), content_hash.to_string()));
+    }
+    if let Some(limit) = results.entropy_limit {
+        rows.push((String::from("Theoretical Minimum Size (bytes)"), format!("{:.2}", limit.theoretical_minimum_size)));
+        rows.push((
+            String::from("Compression Percentage (Based on Entropy Limit)"),
+            format!("{:.2}%", limit.compression_percentage),
+        ));
+    }
+    if let Some(compressed_sizes) = &results.result.compressed_sizes {
+        match compressed_sizes {
+            CompressedSizes::PerChannel([red_size, green_size, blue_size]) => {
+                rows.push((String::from("Red Compressed Size (bytes)"), red_size.to_string()));
+                rows.push((String::from("Green Compressed Size (bytes)"), green_size.to_string()));
+                rows.push((String::from("Blue Compressed Size (bytes)"), blue_size.to_string()));
+            },
+            CompressedSizes::Whole(whole_size) => {
+                let label = format!("{} Compressed Size (bytes)", results.options.compressor.name());
+                rows.push((label, whole_size.to_string()));
+            },
+        }
+        let compressed_total_size = compressed_sizes.total();
+        rows.push((
+            String::from("Total Compressed Size (bytes)"),
+            format!("{}{}", compressed_total_size, expansion_marker(compressed_total_size, results.file_size as usize)),
+        ));
+        if let Some(limit) = results.entropy_limit
+            && let Some(efficiency) = limit.coding_efficiency(compressed_total_size)
+        {
+            rows.push((String::from("Entropy Size, Uncapped (bytes)"), format!("{:.2}", limit.entropy_size)));
+            rows.push((String::from("Coding Efficiency (Compressed Size / Entropy Size)"), format!("{:.2}%", efficiency)));
+        }
+    }
+
+    rows
+}
@@ -0,0 +1,187 @@
+use std::io::Read;
+use clap::ValueEnum;
+use flate2::read::ZlibDecoder;
+use image::DynamicImage;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// First column, first row, column step and row step of each Adam7 interlacing pass.
+const ADAM7_PASSES: [(u64, u64, u64, u64); 7] =
+    [(0, 0, 8, 8), (4, 0, 8, 8), (0, 4, 4, 8), (2, 0, 4, 4), (0, 2, 2, 4), (1, 0, 2, 2), (0, 1, 1, 2)];
+
+/// Length of the scanline stream an IHDR chunk describes: each row of each pass, with its
+/// filter-type byte.
+fn expected_stream_length(header: &[u8]) -> Result<u64, String> {
+    let invalid = || String::from("Error: The PNG has an invalid IHDR chunk.");
+    let field = |offset: usize| header.get(offset..offset + 4).map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()));
+    let (width, height) = (field(0).ok_or_else(invalid)? as u64, field(4).ok_or_else(invalid)? as u64);
+    let (bit_depth, color_type, interlace) = match header.get(8..13) {
+        Some(&[bit_depth, color_type, _, _, interlace]) => (bit_depth as u64, color_type, interlace),
+        _ => return Err(invalid()),
+    };
+    let channels = match color_type {
+        0 | 3 => 1,
+        4 => 2,
+        2 => 3,
+        6 => 4,
+        _ => return Err(invalid()),
+    };
+
+    let stream = |width: u64, height: u64| {
+        if width == 0 { 0 } else { height.saturating_mul((width * channels * bit_depth).div_ceil(8) + 1) }
+    };
+    let pass_length = |size: u64, start: u64, step: u64| size.saturating_sub(start).div_ceil(step);
+    Ok(if interlace == 1 {
+        ADAM7_PASSES
+            .iter()
+            .map(|&(x, y, dx, dy)| stream(pass_length(width, x, dx), pass_length(height, y, dy)))
+            .sum()
+    } else {
+        stream(width, height)
+    })
+}
+
+/// Inflates the concatenated IDAT chunks of a PNG, yielding the filtered scanlines
+/// (each prefixed by its filter-type byte) exactly as DEFLATE sees them. The stream may not inflate
+/// past the length the header implies, so a small file can't expand without bound.
+pub fn read_idat_stream(data: &[u8]) -> Result<Vec<u8>, String> {
+    if !data.starts_with(PNG_SIGNATURE) {
+        return Err(String::from("Error: The file does not start with a PNG signature."));
+    }
+
+    let mut header = None;
+    let mut compressed = Vec::new();
+    let mut position = PNG_SIGNATURE.len();
+    while position + 8 <= data.len() {
+        let length = u32::from_be_bytes([data[position], data[position + 1], data[position + 2], data[position + 3]]) as usize;
+        let chunk_type = &data[position + 4..position + 8];
+        let chunk = data
+            .get(position + 8..position + 8 + length)
+            .ok_or_else(|| String::from("Error: PNG chunk extends past the end of the file."))?;
+
+        match chunk_type {
+            b"IHDR" => header = Some(chunk),
+            b"IDAT" => compressed.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {},
+        }
+        position += 12 + length; // Length, type, data and CRC
+    }
+
+    if compressed.is_empty() {
+        return Err(String::from("Error: The PNG contains no IDAT data."));
+    }
+
+    let expected = expected_stream_length(header.ok_or("Error: The PNG has no IHDR chunk.")?)?;
+    let mut scanlines = Vec::new();
+    ZlibDecoder::new(compressed.as_slice())
+        .take(expected.saturating_add(1))
+        .read_to_end(&mut scanlines)
+        .map_err(|err| format!("Error: Unable to inflate the PNG image data: {}", err))?;
+    if scanlines.len() as u64 > expected {
+        return Err(format!("Error: The PNG image data inflates past the {} bytes its header describes.", expected));
+    }
+    Ok(scanlines)
+}
+
+/// PNG scanline filters, plus the per-row choice an encoder makes among them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PngFilter {
+    /// Bytes as they are
+    None,
+    /// Difference from the byte one pixel to the left
+    Sub,
+    /// Difference from the byte one row up
+    Up,
+    /// Difference from the mean of the left and upper bytes
+    Average,
+    /// Difference from the Paeth predictor of the left, upper and upper-left bytes
+    Paeth,
+    /// The filter with the minimum sum of absolute differences, chosen per row
+    Optimal,
+}
+
+/// The five filters a scanline can use, indexed by their filter-type byte.
+pub const ROW_FILTERS: [PngFilter; 5] = [PngFilter::None, PngFilter::Sub, PngFilter::Up, PngFilter::Average, PngFilter::Paeth];
+
+impl PngFilter {
+    /// Display name of the filter.
+    pub fn name(self) -> &'static str {
+        match self {
+            PngFilter::None => "None",
+            PngFilter::Sub => "Sub",
+            PngFilter::Up => "Up",
+            PngFilter::Average => "Average",
+            PngFilter::Paeth => "Paeth",
+            PngFilter::Optimal => "Optimal",
+        }
+    }
+}
+
+/// The image's samples laid out as a PNG of its color type would hold them, at 8 bits per sample,
+/// with the number of bytes per pixel.
+pub fn png_samples(image: &DynamicImage) -> (Vec<u8>, usize) {
+    match image.color().channel_count() {
+        1 => (image.to_luma8().into_raw(), 1),
+        2 => (image.to_luma_alpha8().into_raw(), 2),
+        4 => (image.to_rgba8().into_raw(), 4),
+        _ => (image.to_rgb8().into_raw(), 3),
+    }
+}
+
+/// Filters each `row_bytes`-long scanline of `samples` and returns the stream a PNG encoder would
+/// deflate: every row prefixed by its filter-type byte. `Optimal` picks, per row, the filter whose
+/// residuals have the smallest sum of absolute values as signed bytes, the heuristic libpng uses.
+pub fn filter_scanlines(samples: &[u8], row_bytes: usize, bytes_per_pixel: usize, filter: PngFilter) -> Vec<u8> {
+    let mut stream = Vec::with_capacity(samples.len() + samples.len() / row_bytes.max(1));
+    let first_previous = vec![0; row_bytes];
+    let mut previous = first_previous.as_slice();
+    for row in samples.chunks_exact(row_bytes) {
+        let (type_byte, residuals) = match ROW_FILTERS.iter().position(|&row_filter| row_filter == filter) {
+            Some(type_byte) => (type_byte, filter_row(row, previous, bytes_per_pixel, type_byte)),
+            None => (0..ROW_FILTERS.len())
+                .map(|type_byte| (type_byte, filter_row(row, previous, bytes_per_pixel, type_byte)))
+                .min_by_key(|(_, residuals)| residuals.iter().map(|&residual| (residual as i8).unsigned_abs() as u64).sum::<u64>())
+                .expect("There are five row filters"),
+        };
+        stream.push(type_byte as u8);
+        stream.extend(residuals);
+        previous = row;
+    }
+    stream
+}
+
+/// Applies the filter with the given filter-type byte to one scanline. Bytes left of the first
+/// pixel and above the first row count as zero, as in PNG.
+fn filter_row(row: &[u8], previous: &[u8], bytes_per_pixel: usize, type_byte: usize) -> Vec<u8> {
+    (0..row.len())
+        .map(|i| {
+            let left = if i >= bytes_per_pixel { row[i - bytes_per_pixel] } else { 0 };
+            let up = previous[i];
+            let upper_left = if i >= bytes_per_pixel { previous[i - bytes_per_pixel] } else { 0 };
+            let prediction = match type_byte {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                _ => paeth_predictor(left, up, upper_left),
+            };
+            row[i].wrapping_sub(prediction)
+        })
+        .collect()
+}
+
+/// The neighbor closest to left + up - upper-left, preferring left, then up, on ties.
+fn paeth_predictor(left: u8, up: u8, upper_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - upper_left as i16;
+    let left_distance = (estimate - left as i16).abs();
+    let up_distance = (estimate - up as i16).abs();
+    let upper_left_distance = (estimate - upper_left as i16).abs();
+    if left_distance <= up_distance && left_distance <= upper_left_distance {
+        left
+    } else if up_distance <= upper_left_distance {
+        up
+    } else {
+        upper_left
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use crate::{PpmModel, PPM_MAX_ORDER};
+
+/// Version of the model file layout; files of another version are rejected.
+const MODEL_VERSION: u32 = 1;
+
+/// A trained PPM model for each of the red, green and blue channels.
+pub type ChannelModels = [PpmModel; 3];
+
+/// One channel's contexts, each with the counts of the bytes that followed it.
+type StoredContexts = Vec<(Vec<u8>, Vec<(u8, usize)>)>;
+
+/// On-disk form of `ChannelModels`: gzip-compressed JSON. Contexts are byte sequences, which JSON
+/// can't use as object keys, so each is stored with its counts as a pair.
+#[derive(Serialize, Deserialize)]
+struct ModelFile {
+    version: u32,
+    max_order: usize,
+    channels: Vec<StoredContexts>,
+}
+
+/// Writes the channel models to the file, replacing it.
+pub fn save_models(path: &Path, models: &ChannelModels) -> Result<(), String> {
+    let file = ModelFile {
+        version: MODEL_VERSION,
+        max_order: models[0].max_order(),
+        channels: models
+            .iter()
+            .map(|model| {
+                model
+                    .contexts()
+                    .map(|(context, counts)| (context, counts.iter().map(|(&byte, &count)| (byte, count)).collect()))
+                    .collect()
+            })
+            .collect(),
+    };
+    let writer = File::create(path).map_err(|err| format!("Error: Unable to create '{}': {}", path.display(), err))?;
+    let mut encoder = GzEncoder::new(BufWriter::new(writer), Compression::default());
+    serde_json::to_writer(&mut encoder, &file)
+        .map_err(|err| err.to_string())
+        .and_then(|()| encoder.finish().map(drop).map_err(|err| err.to_string()))
+        .map_err(|err| format!("Error: Unable to write the PPM model '{}': {}", path.display(), err))
+}
+
+/// Reads channel models written by `save_models`.
+pub fn load_models(path: &Path) -> Result<ChannelModels, String> {
+    let invalid = |reason: &dyn std::fmt::Display| format!("Error: Unable to read the PPM model '{}': {}", path.display(), reason);
+    let reader = File::open(path).map_err(|err| invalid(&err))?;
+    let file: ModelFile = serde_json::from_reader(GzDecoder::new(BufReader::new(reader))).map_err(|err| invalid(&err))?;
+    if file.version != MODEL_VERSION {
+        return Err(invalid(&format!("version {} is not supported", file.version)));
+    }
+    if file.max_order > PPM_MAX_ORDER {
+        return Err(invalid(&format!("order {} exceeds the maximum of {}", file.max_order, PPM_MAX_ORDER)));
+    }
+
+    let models: Vec<PpmModel> = file
+        .channels
+        .into_iter()
+        .map(|contexts| {
+            let contexts = contexts.into_iter().map(|(context, counts)| (context, counts.into_iter().collect::<HashMap<_, _>>()));
+            PpmModel::from_contexts(file.max_order, contexts)
+        })
+        .collect();
+    models.try_into().map_err(|_| invalid(&"it doesn't hold exactly three channels"))
+}
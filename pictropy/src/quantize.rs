@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use image::{DynamicImage, RgbImage};
+
+/// Posterizes the image by keeping only the top `bits` bits of each color sample and zeroing the rest.
+/// Alpha is left untouched; the result is 8-bit RGBA.
+pub fn keep_msb(image: &DynamicImage, bits: u8) -> DynamicImage {
+    let mask = !(0xFFu8.checked_shr(u32::from(bits)).unwrap_or(0));
+    let mut rgba_image = image.to_rgba8();
+    for pixel in rgba_image.pixels_mut() {
+        for sample in &mut pixel.0[..3] {
+            *sample &= mask;
+        }
+    }
+    DynamicImage::ImageRgba8(rgba_image)
+}
+
+/// An image reduced to a palette: the colors and, for each pixel in row-major order, its index.
+pub struct PaletteReduction {
+    pub palette: Vec<[u8; 3]>,
+    pub indices: Vec<u8>,
+}
+
+impl PaletteReduction {
+    /// The reduced image as RGB, for comparing against the original.
+    pub fn to_image(&self, width: u32, height: u32) -> DynamicImage {
+        let samples = self.indices.iter().flat_map(|&index| self.palette[index as usize]).collect();
+        DynamicImage::ImageRgb8(RgbImage::from_raw(width, height, samples).expect("one index per pixel"))
+    }
+}
+
+/// Reduces the image's RGB colors to at most `colors` with median cut: starting from one box
+/// holding every distinct color, the box spanning the widest range on any channel is split at
+/// the pixel-weighted median along that channel until there are `colors` boxes or none can be
+/// split. Each box becomes the pixel-weighted mean of its colors. Alpha is ignored.
+///
+/// # Panics
+///
+/// If `colors` is 0 or more than 256.
+pub fn median_cut(image: &DynamicImage, colors: usize) -> PaletteReduction {
+    assert!((1..=256).contains(&colors), "a palette holds 1 to 256 colors, not {}", colors);
+    let rgb_image = image.to_rgb8();
+    let mut counts: HashMap<[u8; 3], u64> = HashMap::new();
+    for pixel in rgb_image.pixels() {
+        *counts.entry(pixel.0).or_insert(0) += 1;
+    }
+
+    let mut boxes: Vec<Vec<([u8; 3], u64)>> = vec![counts.into_iter().collect()];
+    while boxes.len() < colors {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(index, colors)| (index, widest_channel(colors)))
+            .max_by_key(|&(_, (_, range))| range);
+        let Some((index, (channel, _))) = widest else {
+            break; // Every box holds a single color
+        };
+
+        let mut split = boxes.swap_remove(index);
+        split.sort_unstable_by_key(|&(color, _)| (color[channel], color));
+        let half = split.iter().map(|(_, count)| count).sum::<u64>() / 2;
+        let mut seen = 0;
+        let median = split
+            .iter()
+            .position(|(_, count)| {
+                seen += count;
+                seen > half
+            })
+            .unwrap_or(0)
+            .clamp(1, split.len() - 1);
+        let upper = split.split_off(median);
+        boxes.push(split);
+        boxes.push(upper);
+    }
+
+    let mut lookup = HashMap::new();
+    let palette = boxes
+        .iter()
+        .enumerate()
+        .map(|(index, colors)| {
+            let total: u64 = colors.iter().map(|(_, count)| count).sum();
+            let mut sums = [0u64; 3];
+            for (color, count) in colors {
+                lookup.insert(*color, index as u8);
+                for (sum, &sample) in sums.iter_mut().zip(color) {
+                    *sum += sample as u64 * count;
+                }
+            }
+            sums.map(|sum| ((sum + total / 2) / total.max(1)) as u8)
+        })
+        .collect();
+    let indices = rgb_image.pixels().map(|pixel| lookup[&pixel.0]).collect();
+    PaletteReduction { palette, indices }
+}
+
+/// The channel along which the colors spread the most, and the width of that spread.
+fn widest_channel(colors: &[([u8; 3], u64)]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let (min, max) = colors
+                .iter()
+                .fold((u8::MAX, u8::MIN), |(min, max), (color, _)| (min.min(color[channel]), max.max(color[channel])));
+            (channel, max - min)
+        })
+        .max_by_key(|&(_, range)| range)
+        .expect("three channels")
+}
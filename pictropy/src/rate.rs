@@ -0,0 +1,40 @@
+use std::io::Write;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+/// Shortest prefix that is compressed; below this gzip's header and warm-up dominate the size.
+const MIN_PREFIX: usize = 4096;
+/// Largest number of prefixes compressed, halving from the full length.
+const MAX_PREFIXES: usize = 8;
+
+/// Size of the data after gzip at the best compression level.
+pub fn gzip_size(data: &[u8]) -> usize {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data).expect("Writing to a Vec should not fail");
+    encoder.finish().expect("Writing to a Vec should not fail").len()
+}
+
+/// Estimates the entropy rate of the data in bits per byte.
+///
+/// Prefixes of the full length, half of it, a quarter and so on are compressed with gzip, and
+/// a least-squares line is fitted to compressed bits against prefix length. The slope is the
+/// cost of each further byte once the fixed overhead and dictionary warm-up are paid, so unlike
+/// order-0 entropy it credits repetition and long-range structure. Returns None when the data
+/// is too short for two prefixes.
+pub fn estimate_entropy_rate(data: &[u8]) -> Option<f64> {
+    let prefixes: Vec<(f64, f64)> = std::iter::successors(Some(data.len()), |length| Some(length / 2))
+        .take_while(|&length| length >= MIN_PREFIX)
+        .take(MAX_PREFIXES)
+        .map(|length| (length as f64, (gzip_size(&data[..length]) * 8) as f64))
+        .collect();
+    if prefixes.len() < 2 {
+        return None;
+    }
+
+    let count = prefixes.len() as f64;
+    let mean_length = prefixes.iter().map(|&(length, _)| length).sum::<f64>() / count;
+    let mean_bits = prefixes.iter().map(|&(_, bits)| bits).sum::<f64>() / count;
+    let covariance: f64 = prefixes.iter().map(|&(length, bits)| (length - mean_length) * (bits - mean_bits)).sum();
+    let variance: f64 = prefixes.iter().map(|&(length, _)| (length - mean_length).powi(2)).sum();
+    Some((covariance / variance).clamp(0.0, 8.0))
+}
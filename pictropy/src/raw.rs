@@ -0,0 +1,30 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use rawloader::RawImageData;
+
+/// Reads the undemosaiced Bayer (CFA) samples from a camera RAW or DNG file.
+pub fn read_bayer_samples(path: &Path) -> Result<Vec<u16>, String> {
+    let raw_image = rawloader::decode_file(path).map_err(|err| {
+        format!(
+            "Error: Unable to decode the RAW file '{}': {}",
+            path.display(), err
+        )
+    })?;
+
+    match raw_image.data {
+        RawImageData::Integer(samples) => Ok(samples),
+        RawImageData::Float(_) => Err(format!(
+            "Error: The RAW file '{}' stores floating-point sensor data, which is not supported.",
+            path.display()
+        )),
+    }
+}
+
+/// Width and height of the sensor data, from the file's metadata. rawloader's dummy decode parses
+/// everything but leaves the sample buffer unallocated.
+pub fn raw_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let file = File::open(path).ok()?;
+    let raw_image = rawloader::decode_dummy(&mut BufReader::new(file)).ok()?;
+    Some((u32::try_from(raw_image.width).ok()?, u32::try_from(raw_image.height).ok()?))
+}
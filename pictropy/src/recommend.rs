@@ -0,0 +1,102 @@
+/// Most colors an indexed PNG palette holds.
+pub const PALETTE_SIZE: usize = 256;
+/// Smallest saving, as a fraction of the file, worth re-encoding losslessly for.
+pub const MIN_LOSSLESS_SAVING: f64 = 0.10;
+/// Smallest saving worth giving up exact pixels for.
+pub const MIN_LOSSY_SAVING: f64 = 0.50;
+/// Lowest PSNR, in dB, at which a lossy encoding is considered visually transparent.
+pub const MIN_LOSSY_PSNR: f64 = 40.0;
+/// Entropy-limit saving below which the file counts as near-incompressible.
+pub const INCOMPRESSIBLE_SAVING: f64 = 0.05;
+
+/// A lossy encoding considered for the recommendation.
+pub struct LossyOption {
+    pub format: &'static str,
+    pub quality: u8,
+    pub size: usize,
+    pub psnr: f64,
+}
+
+/// The measurements a recommendation is based on.
+pub struct Metrics {
+    pub file_size: u64,
+    /// Distinct colors, or None when there are more than `PALETTE_SIZE`
+    pub unique_colors: Option<usize>,
+    /// Lossless size limit implied by the entropy, in bytes, if entropy was measured
+    pub entropy_limit: Option<f64>,
+    /// Smallest real lossless encoding, by encoder name
+    pub best_lossless: Option<(&'static str, usize)>,
+    /// Lossy encodings drop alpha, so they aren't considered for images that have it
+    pub has_alpha: bool,
+    /// Smallest lossy encoding that reaches `MIN_LOSSY_PSNR`
+    pub lossy: Option<LossyOption>,
+}
+
+/// Advice in order of preference, with the metrics each piece rests on.
+pub struct Recommendation {
+    pub advice: Vec<String>,
+    pub evidence: Vec<String>,
+}
+
+/// Turns the metrics into advice by fixed thresholds, checked in order:
+///
+/// 1. At most `PALETTE_SIZE` colors: store as an indexed PNG.
+/// 2. A lossless encoder saves at least `MIN_LOSSLESS_SAVING`: re-encode with it.
+/// 3. A lossy encoding at `MIN_LOSSY_PSNR` or better saves at least `MIN_LOSSY_SAVING`: offer it.
+///
+/// When none applies, the file is kept as-is, and called near-incompressible if its entropy
+/// limit saves less than `INCOMPRESSIBLE_SAVING`.
+pub fn recommend(metrics: &Metrics) -> Recommendation {
+    let file_size = metrics.file_size as f64;
+    let saving = |size: f64| 1.0 - size / file_size;
+    let mut advice = Vec::new();
+    let mut evidence = Vec::new();
+
+    match metrics.unique_colors {
+        Some(count) => {
+            evidence.push(format!("Unique colors: {} (a palette holds {})", count, PALETTE_SIZE));
+            if count <= PALETTE_SIZE {
+                advice.push(format!("Store as an indexed PNG; it uses {} of the {} palette entries.", count, PALETTE_SIZE));
+            }
+        },
+        None => evidence.push(format!("Unique colors: more than {}", PALETTE_SIZE)),
+    }
+
+    if let Some((name, size)) = metrics.best_lossless {
+        let lossless_saving = saving(size as f64);
+        evidence.push(format!("Best lossless: {} at {} bytes ({:.1}% savings)", name, size, lossless_saving * 100.0));
+        if lossless_saving >= MIN_LOSSLESS_SAVING {
+            advice.push(format!("Re-encode losslessly as {} for {:.0}% savings.", name, lossless_saving * 100.0));
+        }
+    }
+
+    match &metrics.lossy {
+        _ if metrics.has_alpha => evidence.push(String::from("Lossy: not considered, as the image has transparency")),
+        Some(lossy) => {
+            let lossy_saving = saving(lossy.size as f64);
+            evidence.push(format!(
+                "Lowest {} quality at {:.0} dB PSNR or better: q{} at {} bytes, PSNR {:.1} dB ({:.1}% savings)",
+                lossy.format, MIN_LOSSY_PSNR, lossy.quality, lossy.size, lossy.psnr, lossy_saving * 100.0
+            ));
+            if lossy_saving >= MIN_LOSSY_SAVING {
+                advice.push(format!(
+                    "Convert to lossy {} q{} for {:.0}% savings at PSNR {:.0} dB.",
+                    lossy.format, lossy.quality, lossy_saving * 100.0, lossy.psnr
+                ));
+            }
+        },
+        None => evidence.push(format!("No lossy quality reaches {:.0} dB PSNR", MIN_LOSSY_PSNR)),
+    }
+
+    let incompressible = metrics.entropy_limit.map(|limit| {
+        evidence.push(format!("Entropy limit: {:.0} bytes ({:.1}% savings)", limit, saving(limit).max(0.0) * 100.0));
+        saving(limit) < INCOMPRESSIBLE_SAVING
+    });
+    if advice.is_empty() {
+        advice.push(String::from(match incompressible {
+            Some(true) => "Already near-incompressible; keep as-is.",
+            _ => "Keep as-is; no option saves enough to be worth it.",
+        }));
+    }
+    Recommendation { advice, evidence }
+}
@@ -0,0 +1,60 @@
+use std::fs;
+use std::path::Path;
+use crate::{byte_histogram, file_extension, read_image, split_rgb_channels, ChannelOrder, IMAGE_EXTENSIONS};
+
+/// Red, green and blue 256-bin histograms describing a typical image.
+pub type ReferenceDistribution = [[u64; 256]; 3];
+
+/// Loads a reference distribution from a JPG, PNG or QOI image, or from a text file with one line
+/// per channel: the name (`red`, `green`, `blue`) followed by 256 whitespace-separated counts.
+pub fn load_reference(path: &Path, order: ChannelOrder) -> Result<ReferenceDistribution, String> {
+    if IMAGE_EXTENSIONS.contains(&file_extension(path).as_str()) {
+        let img = read_image(path)?;
+        let (red, green, blue) = split_rgb_channels(&img, order);
+        return Ok([byte_histogram(&red), byte_histogram(&green), byte_histogram(&blue)]);
+    }
+
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("Error: Unable to read the reference distribution '{}': {}", path.display(), err))?;
+    let invalid = |reason: &str| format!("Error: Invalid reference distribution '{}': {}", path.display(), reason);
+
+    let mut distribution = [None; 3];
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        let mut fields = line.split_whitespace();
+        let channel = match fields.next() {
+            Some("red") => 0,
+            Some("green") => 1,
+            Some("blue") => 2,
+            _ => return Err(invalid("each line must start with red, green or blue")),
+        };
+        let counts: Vec<u64> = fields
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid("counts must be non-negative integers"))?;
+        distribution[channel] = Some(counts.try_into().map_err(|_| invalid("each channel needs 256 counts"))?);
+    }
+
+    match distribution {
+        [Some(red), Some(green), Some(blue)] => Ok([red, green, blue]),
+        _ => Err(invalid("red, green and blue lines are all required")),
+    }
+}
+
+/// Cross-entropy in bits per symbol of the data when coded with the reference histogram's
+/// probabilities. The reference is add-one smoothed, so values it never saw cost a finite amount.
+pub fn cross_entropy(data: &[u8], reference: &[u64; 256]) -> f64 {
+    let histogram = byte_histogram(data);
+    let total = data.len() as f64;
+    let reference_total = reference.iter().sum::<u64>() as f64 + 256.0;
+
+    histogram
+        .iter()
+        .zip(reference)
+        .filter(|&(&count, _)| count > 0)
+        .map(|(&count, &reference_count)| {
+            let probability = count as f64 / total;
+            let reference_probability = (reference_count as f64 + 1.0) / reference_total;
+            -probability * reference_probability.log2()
+        })
+        .sum()
+}
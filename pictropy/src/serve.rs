@@ -0,0 +1,215 @@
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use log::{info, warn};
+use serde::Serialize;
+use crate::{analyze, check_max_pixels, data_dimensions, decode_error_message, decode_image, AnalysisOptions, AnalysisResult};
+
+/// Longest a client may take to send a whole request before it is turned away with 408.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// Most header lines a request may carry.
+const MAX_HEADERS: usize = 100;
+/// Longest request or header line, in bytes.
+const MAX_LINE: u64 = 8192;
+
+/// Settings of the analysis server.
+pub struct ServeConfig {
+    /// Address to listen on, such as `127.0.0.1:8080`
+    pub listen: String,
+    /// Requests analyzed at once; further ones are turned away with 503
+    pub max_concurrent_requests: usize,
+    /// Largest image upload accepted, in bytes; larger ones are turned away with 413
+    pub max_request_bytes: usize,
+    /// Most pixels an upload may declare in its header; larger ones are turned away with 413
+    pub max_pixels: u64,
+    pub options: AnalysisOptions,
+}
+
+/// Body of a successful response: the upload's size and everything `analyze` measured.
+#[derive(Serialize)]
+struct AnalysisResponse {
+    original_size: usize,
+    #[serde(flatten)]
+    result: AnalysisResult,
+}
+
+/// Frees a request slot when the request is done, however it ends.
+struct Slot(Arc<AtomicUsize>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Serves `POST /analyze` over HTTP/1.1 until the process ends. The request body is the image
+/// file itself, in any format `read_image_by_content` decodes, and the response is the analysis
+/// as JSON, or `{"error": ...}` with a 4xx or 5xx status. Each connection carries one request.
+pub fn serve(config: ServeConfig) -> Result<(), String> {
+    let listener = TcpListener::bind(&config.listen)
+        .map_err(|err| format!("Error: Unable to listen on '{}': {}", config.listen, err))?;
+    info!("Listening on http://{}/analyze", config.listen);
+
+    let config = Arc::new(config);
+    let active = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!("Failed to accept a connection: {}", err);
+                continue;
+            },
+        };
+        if active.fetch_add(1, Ordering::SeqCst) >= config.max_concurrent_requests {
+            active.fetch_sub(1, Ordering::SeqCst);
+            respond_error(&mut stream, 503, "Too many requests are being analyzed; try again later.");
+            continue;
+        }
+        let slot = Slot(Arc::clone(&active));
+        let config = Arc::clone(&config);
+        thread::spawn(move || {
+            handle_connection(stream, &config);
+            drop(slot);
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream, config: &ServeConfig) {
+    let started = Instant::now();
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+    let (status, body) = match read_request(&stream, config.max_request_bytes) {
+        Ok(data) => analyze_upload(&data, config),
+        Err((status, message)) => (status, error_body(&message)),
+    };
+    info!("{} response in {:.2} seconds", status, started.elapsed().as_secs_f64());
+    write_response(&mut stream, status, &body);
+}
+
+/// Reads from the stream until a deadline. The socket timeout is narrowed to the time left before
+/// every read, so a client trickling bytes can't stretch the request past the deadline.
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::from(ErrorKind::TimedOut));
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf)
+    }
+}
+
+/// Reads one request and returns its body, or the status and message to reject it with.
+fn read_request(stream: &TcpStream, max_request_bytes: usize) -> Result<Vec<u8>, (u16, String)> {
+    let bad_request = |message: &str| (400, String::from(message));
+    let read_error = |err: io::Error| match err.kind() {
+        ErrorKind::TimedOut | ErrorKind::WouldBlock => {
+            (408, format!("The request took longer than {} seconds to arrive.", READ_TIMEOUT.as_secs()))
+        },
+        _ => bad_request(&err.to_string()),
+    };
+    let mut reader = BufReader::new(DeadlineReader { stream, deadline: Instant::now() + READ_TIMEOUT });
+    let mut read_line = || {
+        let mut line = String::new();
+        (&mut reader).take(MAX_LINE).read_line(&mut line).map_err(read_error)?;
+        Ok(line.trim_end().to_string())
+    };
+    let request_line = read_line()?;
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+
+    let mut content_length = None;
+    let mut header_count = 0;
+    loop {
+        let line = read_line()?;
+        if line.is_empty() {
+            break;
+        }
+        header_count += 1;
+        if header_count > MAX_HEADERS {
+            return Err((431, format!("A request may carry at most {} headers.", MAX_HEADERS)));
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = Some(value.trim().parse::<usize>().map_err(|_| bad_request("Invalid Content-Length."))?);
+        }
+    }
+
+    if target.split('?').next() != Some("/analyze") {
+        return Err((404, String::from("Not found; POST the image to /analyze.")));
+    }
+    if method != "POST" {
+        return Err((405, String::from("Only POST is supported.")));
+    }
+    let content_length = content_length.ok_or((411, String::from("A Content-Length header is required.")))?;
+    if content_length > max_request_bytes {
+        return Err((413, format!("The upload exceeds the limit of {} bytes.", max_request_bytes)));
+    }
+    let mut data = vec![0; content_length];
+    reader.read_exact(&mut data).map_err(read_error)?;
+    Ok(data)
+}
+
+/// Decodes and analyzes the uploaded image, returning the status and JSON body to respond with.
+/// The pixel count is checked from the header first, as a small file can declare a huge image.
+fn analyze_upload(data: &[u8], config: &ServeConfig) -> (u16, String) {
+    let dimensions = data_dimensions(data);
+    if let Err(message) = check_max_pixels("upload", dimensions, config.max_pixels) {
+        return (if dimensions.is_some() { 413 } else { 422 }, error_body(&message));
+    }
+    let image = match decode_image(data) {
+        Ok(image) => image,
+        Err(err) => return (422, error_body(&decode_error_message(Path::new("upload"), data, &err, false))),
+    };
+    match analyze(&image, &config.options) {
+        Ok(result) => {
+            let response = AnalysisResponse { original_size: data.len(), result };
+            (200, serde_json::to_string(&response).expect("the analysis serializes to JSON"))
+        },
+        Err(error_message) => (422, error_body(&error_message)),
+    }
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+fn respond_error(stream: &mut TcpStream, status: u16, message: &str) {
+    write_response(stream, status, &error_body(message));
+}
+
+fn write_response(stream: &mut TcpStream, status: u16, body: &str) {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        411 => "Length Required",
+        413 => "Content Too Large",
+        422 => "Unprocessable Content",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "",
+    };
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+    if let Err(err) = stream.write_all(response.as_bytes()) {
+        warn!("Failed to send the response: {}", err);
+    }
+}
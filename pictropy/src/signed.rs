@@ -0,0 +1,81 @@
+use std::io::Cursor;
+use std::path::Path;
+use image::{DynamicImage, ImageBuffer};
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::tags::{SampleFormat, Tag};
+use tiff::ColorType;
+use crate::{decode_image, is_tiff};
+
+/// Whether the data is a TIFF file whose SampleFormat tag marks its samples as signed integers,
+/// which the `image` crate refuses to decode.
+pub fn is_signed_tiff(data: &[u8]) -> bool {
+    let Ok(mut decoder) = Decoder::new(Cursor::new(data)) else {
+        return false;
+    };
+    matches!(decoder.find_tag_unsigned_vec::<u16>(Tag::SampleFormat), Ok(Some(formats))
+        if formats.first().copied().map(SampleFormat::from_u16_exhaustive) == Some(SampleFormat::Int))
+}
+
+/// Reads an image whose 16-bit samples hold signed integers, such as an elevation raster, and
+/// offsets each by 32768, so the samples run in value order from -32768 at 0 to 32767 at 65535.
+/// Read as unsigned, negative samples land above the positive ones instead, so values either
+/// side of zero end up at opposite ends of the range.
+///
+/// TIFFs are decoded whatever their SampleFormat tag says, so files written without the tag can
+/// be read as signed too; other formats are decoded by their content.
+pub fn read_signed_image(path: &Path) -> Result<DynamicImage, String> {
+    let data = std::fs::read(path).map_err(|err| format!("Error: Unable to read '{}': {}", path.display(), err))?;
+    decode_signed_image(path, &data)
+}
+
+/// Decodes the already-read content of the file at `path` as `read_signed_image` does.
+pub fn decode_signed_image(path: &Path, data: &[u8]) -> Result<DynamicImage, String> {
+    let image = if is_tiff(data) {
+        decode_tiff(data)
+            .map_err(|err| format!("Error: Unable to decode the TIFF image '{}': {}", path.display(), err))?
+    } else {
+        decode_image(data).map_err(|err| format!("Error: Unable to decode '{}': {}", path.display(), err))?
+    };
+    offset_signed(image).ok_or_else(|| {
+        format!("Error: '{}' has 8-bit samples; --signed reinterprets 16-bit samples only.", path.display())
+    })
+}
+
+/// Decodes a TIFF's 16-bit samples as their raw bit patterns, signed or not.
+fn decode_tiff(data: &[u8]) -> Result<DynamicImage, String> {
+    let mut decoder = Decoder::new(Cursor::new(data)).map_err(|err| err.to_string())?;
+    let (width, height) = decoder.dimensions().map_err(|err| err.to_string())?;
+    let color = decoder.colortype().map_err(|err| err.to_string())?;
+    let samples = match decoder.read_image().map_err(|err| err.to_string())? {
+        DecodingResult::I16(samples) => samples.into_iter().map(|sample| sample as u16).collect(),
+        DecodingResult::U16(samples) => samples,
+        DecodingResult::I8(_) | DecodingResult::U8(_) => return Err(String::from("the samples are 8-bit")),
+        _ => return Err(String::from("the samples are neither 8 nor 16-bit integers")),
+    };
+    let image = match color {
+        ColorType::Gray(16) => ImageBuffer::from_raw(width, height, samples).map(DynamicImage::ImageLuma16),
+        ColorType::GrayA(16) => ImageBuffer::from_raw(width, height, samples).map(DynamicImage::ImageLumaA16),
+        ColorType::RGB(16) => ImageBuffer::from_raw(width, height, samples).map(DynamicImage::ImageRgb16),
+        ColorType::RGBA(16) => ImageBuffer::from_raw(width, height, samples).map(DynamicImage::ImageRgba16),
+        _ => return Err(format!("{:?} images are not supported", color)),
+    };
+    image.ok_or_else(|| String::from("the pixel data doesn't match the dimensions"))
+}
+
+/// Moves two's complement 16-bit color samples to offset binary by flipping their top bit, or
+/// returns `None` for images without 16-bit samples. Alpha is coverage, never signed, so it's kept.
+fn offset_signed(mut image: DynamicImage) -> Option<DynamicImage> {
+    let (samples, channels, color_channels): (&mut [u16], usize, usize) = match &mut image {
+        DynamicImage::ImageLuma16(buffer) => (buffer, 1, 1),
+        DynamicImage::ImageLumaA16(buffer) => (buffer, 2, 1),
+        DynamicImage::ImageRgb16(buffer) => (buffer, 3, 3),
+        DynamicImage::ImageRgba16(buffer) => (buffer, 4, 3),
+        _ => return None,
+    };
+    for pixel in samples.chunks_exact_mut(channels) {
+        for sample in &mut pixel[..color_channels] {
+            *sample ^= 0x8000;
+        }
+    }
+    Some(image)
+}
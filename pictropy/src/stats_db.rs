@@ -0,0 +1,75 @@
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use rusqlite::{params, Connection, TransactionBehavior};
+
+/// How long a run waits for another process holding the database lock.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One analysis run, as persisted in the stats database.
+pub struct AnalysisRecord<'a> {
+    pub filename: &'a str,
+    pub red_entropy: f64,
+    pub green_entropy: f64,
+    pub blue_entropy: f64,
+    pub total_entropy: f64,
+    pub original_size: u64,
+    pub theoretical_size: f64,
+    pub compressed_size: usize,
+}
+
+/// Appends a record to the SQLite stats database, creating the schema if it is absent.
+pub fn append_record(db_path: &Path, record: &AnalysisRecord) -> Result<(), String> {
+    let to_message = |err: rusqlite::Error| {
+        format!("Error: Unable to update the stats database '{}': {}", db_path.display(), err)
+    };
+
+    let mut connection = Connection::open(db_path).map_err(to_message)?;
+    connection.busy_timeout(BUSY_TIMEOUT).map_err(to_message)?;
+
+    // IMMEDIATE takes the write lock up front so concurrent runs queue instead of failing mid-transaction
+    let transaction = connection
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(to_message)?;
+    transaction
+        .execute(
+            "CREATE TABLE IF NOT EXISTS analyses (
+                id INTEGER PRIMARY KEY,
+                filename TEXT NOT NULL,
+                analyzed_at INTEGER NOT NULL,
+                red_entropy REAL NOT NULL,
+                green_entropy REAL NOT NULL,
+                blue_entropy REAL NOT NULL,
+                total_entropy REAL NOT NULL,
+                original_size INTEGER NOT NULL,
+                theoretical_size REAL NOT NULL,
+                compressed_size INTEGER NOT NULL
+            )",
+            [],
+        )
+        .map_err(to_message)?;
+
+    let analyzed_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or(0);
+    transaction
+        .execute(
+            "INSERT INTO analyses (filename, analyzed_at, red_entropy, green_entropy, blue_entropy,
+                total_entropy, original_size, theoretical_size, compressed_size)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                record.filename,
+                analyzed_at,
+                record.red_entropy,
+                record.green_entropy,
+                record.blue_entropy,
+                record.total_entropy,
+                record.original_size as i64,
+                record.theoretical_size,
+                record.compressed_size as i64,
+            ],
+        )
+        .map_err(to_message)?;
+
+    transaction.commit().map_err(to_message)
+}
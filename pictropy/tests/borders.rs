@@ -0,0 +1,32 @@
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+use pictropy::borders::{detect_borders, trim_borders, Borders};
+
+/// A 20x16 image with a black frame of the given widths around a two-color checkerboard.
+fn framed(borders: Borders) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(20, 16, |x, y| {
+        let inside = x >= borders.left && x < 20 - borders.right && y >= borders.top && y < 16 - borders.bottom;
+        if inside && (x + y) % 2 == 0 { Rgb([255, 255, 255]) } else if inside { Rgb([90, 90, 90]) } else { Rgb([0, 0, 0]) }
+    }))
+}
+
+#[test]
+fn frames_are_found_on_every_edge() {
+    let borders = Borders { top: 2, bottom: 3, left: 1, right: 4 };
+    let img = framed(borders);
+    assert_eq!(detect_borders(&img), borders);
+    assert_eq!(borders.border_pixels(20, 16), 20 * 16 - 15 * 11);
+    assert_eq!(trim_borders(&img, &borders).dimensions(), (15, 11));
+}
+
+#[test]
+fn unframed_and_banded_images_have_no_borders() {
+    assert!(detect_borders(&framed(Borders::default())).is_empty());
+
+    // Three horizontal bands leave no content between the margins
+    let banded = DynamicImage::ImageRgb8(RgbImage::from_fn(8, 9, |_, y| Rgb([(y / 3 * 100) as u8, 0, 0])));
+    assert!(detect_borders(&banded).is_empty());
+
+    // And so do two vertical ones, split unevenly
+    let columns = DynamicImage::ImageRgb8(RgbImage::from_fn(8, 9, |x, _| if x < 6 { Rgb([255, 0, 0]) } else { Rgb([0, 0, 255]) }));
+    assert!(detect_borders(&columns).is_empty());
+}
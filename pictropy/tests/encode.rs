@@ -0,0 +1,29 @@
+use image::{DynamicImage, ImageBuffer, Luma, Rgba};
+use pictropy::encode::{encode_png_best, encode_png_smallest};
+
+/// 16-bit images with samples whose two bytes differ, so a byte swap can't go unnoticed.
+fn wide_images() -> Vec<DynamicImage> {
+    vec![
+        DynamicImage::ImageLuma16(ImageBuffer::from_fn(5, 3, |x, y| Luma([0x0102 + (x * 3 + y) as u16 * 0x0101]))),
+        DynamicImage::ImageRgba16(ImageBuffer::from_fn(5, 3, |x, y| {
+            Rgba([0x0102 * x as u16, 0x3456 + y as u16, 0xFF00, 0x00FF + x as u16])
+        })),
+    ]
+}
+
+#[test]
+fn best_png_round_trips_16_bit_samples() {
+    for image in wide_images() {
+        let decoded = image::load_from_memory(&encode_png_best(&image).unwrap()).unwrap();
+        assert_eq!(decoded.color(), image.color());
+        assert_eq!(decoded.as_bytes(), image.as_bytes());
+    }
+}
+
+#[test]
+fn smallest_png_round_trips_16_bit_samples() {
+    for image in wide_images() {
+        let decoded = image::load_from_memory(&encode_png_smallest(&image).unwrap()).unwrap();
+        assert_eq!(decoded.as_bytes(), image.as_bytes());
+    }
+}
@@ -0,0 +1,102 @@
+use image::{DynamicImage, Rgb, RgbImage};
+use pictropy::{
+    calculate_entropy, calculate_symbol_entropy, colorspace_planes, content_entropy, histogram_variance,
+    symbol_histogram, top_values, ChannelOrder, Colorspace,
+};
+
+/// Deterministic pseudo-random bytes from a linear congruential generator.
+fn noise(length: usize) -> Vec<u8> {
+    let mut state: u32 = 0x1234_5678;
+    (0..length)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 24) as u8
+        })
+        .collect()
+}
+
+#[test]
+fn array_histogram_matches_hashmap_histogram() {
+    let skewed: Vec<u8> = noise(10_000).iter().map(|&value| value / 16).collect();
+    for data in [noise(10_000), skewed, vec![7; 1_000], Vec::new()] {
+        let array_entropy = calculate_entropy(&data);
+        let hashmap_entropy = calculate_symbol_entropy(&data);
+        assert!(
+            (array_entropy - hashmap_entropy).abs() < 1e-9,
+            "Array entropy {} differs from HashMap entropy {}",
+            array_entropy,
+            hashmap_entropy
+        );
+    }
+}
+
+#[test]
+fn symbol_histogram_is_sorted_by_value() {
+    let samples: Vec<i32> = noise(10_000).iter().map(|&value| value as i32 - 128).collect();
+    let histogram = symbol_histogram(&samples);
+    assert!(histogram.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    assert_eq!(histogram.iter().map(|&(_, count)| count).sum::<u64>(), samples.len() as u64);
+
+    // The sum runs in symbol order, so repeated runs agree to the last bit
+    let entropy = calculate_symbol_entropy(&samples);
+    assert!((0..10).all(|_| calculate_symbol_entropy(&samples).to_bits() == entropy.to_bits()));
+}
+
+#[test]
+fn histogram_variance_separates_dominant_values_from_an_even_spread() {
+    let even: Vec<u8> = (0..=255).cycle().take(256 * 40).collect();
+    assert_eq!(histogram_variance(&even), 0.0);
+
+    // 16 equally likely levels: 4 bits of entropy, well below noise, yet a far higher variance
+    let posterized: Vec<u8> = noise(10_000).iter().map(|&value| value & 0xF0).collect();
+    assert!(histogram_variance(&posterized) > 100.0 * histogram_variance(&noise(10_000)));
+
+    let single = vec![9; 100];
+    assert!((histogram_variance(&single) - 255.0 / 65536.0).abs() < 1e-12);
+}
+
+#[test]
+fn top_values_rank_by_frequency_and_sum_to_the_entropy() {
+    // A background value on 80% of the samples, with the rest spread over noise
+    let data: Vec<u8> = noise(10_000).iter().enumerate().map(|(i, &value)| if i % 5 == 0 { value } else { 200 }).collect();
+    let top = top_values(&data, 3);
+    assert_eq!(top.len(), 3);
+    assert_eq!(top[0].value, 200);
+    assert!(top[0].frequency >= 0.8);
+    assert!(top.windows(2).all(|pair| pair[0].count >= pair[1].count));
+
+    let all = top_values(&data, 256);
+    let total: f64 = all.iter().map(|share| share.entropy_contribution).sum();
+    assert!((total - calculate_entropy(&data)).abs() < 1e-9);
+}
+
+#[test]
+fn content_entropy_leaves_out_the_uniform_backdrop() {
+    // A 16x16 noise patch in the top-left tile of a flat gray 64x64 backdrop
+    let patch = noise(16 * 16);
+    let img = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, y| {
+        if x < 16 && y < 16 { Rgb([patch[(y * 16 + x) as usize]; 3]) } else { Rgb([128; 3]) }
+    }));
+    let content = content_entropy(&img, 4, 4, ChannelOrder::Rgb, 0.5).unwrap().unwrap();
+    assert_eq!((content.kept_tiles, content.total_tiles, content.kept_pixels), (1, 16, 256));
+    assert!((content.entropy.red_entropy - calculate_entropy(&patch)).abs() < 1e-12);
+
+    let backdrop = DynamicImage::ImageRgb8(RgbImage::from_pixel(64, 64, Rgb([128; 3])));
+    assert!(content_entropy(&backdrop, 4, 4, ChannelOrder::Rgb, 0.5).unwrap().is_none());
+}
+
+#[test]
+fn hsv_planes_separate_hue_from_brightness() {
+    let colors = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [128, 128, 128], [0, 0, 0], [255, 255, 0]];
+    let img = DynamicImage::ImageRgb8(RgbImage::from_fn(6, 1, |x, _| Rgb(colors[x as usize])));
+    let [hue, saturation, value] = colorspace_planes(&img, ChannelOrder::Rgb, Colorspace::Hsv);
+    assert_eq!(hue, [0, 85, 170, 0, 0, 42]);
+    assert_eq!(saturation, [255, 255, 255, 0, 0, 255]);
+    assert_eq!(value, [255, 255, 255, 128, 0, 255]);
+
+    // The same hue at every brightness leaves the hue plane with no entropy
+    let shades = DynamicImage::ImageRgb8(RgbImage::from_fn(256, 1, |x, _| Rgb([x as u8, 0, 0])));
+    let [hue, _, value] = colorspace_planes(&shades, ChannelOrder::Rgb, Colorspace::Hsv);
+    assert_eq!(calculate_entropy(&hue), 0.0);
+    assert!((calculate_entropy(&value) - 8.0).abs() < 1e-12);
+}
@@ -0,0 +1,34 @@
+use std::fs::File;
+use std::process;
+use image::codecs::hdr::HdrEncoder;
+use image::Rgb;
+use pictropy::float::{binned_entropy, read_float_image};
+
+#[test]
+fn binned_entropy_depends_on_the_bin_count() {
+    // 256 evenly spaced samples: one per bin at 256 bins, two per bin at 128
+    let ramp: Vec<f32> = (0..256).map(|index| index as f32 / 255.0 * 1000.0).collect();
+    assert!((binned_entropy(&ramp, 256) - 8.0).abs() < 1e-9);
+    assert!((binned_entropy(&ramp, 128) - 7.0).abs() < 1e-9);
+    assert_eq!(binned_entropy(&[4.5; 16], 256), 0.0);
+}
+
+#[test]
+fn non_finite_samples_share_a_bin() {
+    let samples = [0.0, 1.0, f32::NAN, f32::INFINITY];
+    assert!((binned_entropy(&samples, 2) - 1.5).abs() < 1e-9);
+}
+
+#[test]
+fn hdr_images_decode_to_float_channels() {
+    let path = std::env::temp_dir().join(format!("pictropy-float-{}.hdr", process::id()));
+    let pixels: Vec<Rgb<f32>> = (0..16).map(|index| Rgb([index as f32 * 8.0, 0.5, 0.0])).collect();
+    HdrEncoder::new(File::create(&path).unwrap()).encode(&pixels, 4, 4).unwrap();
+
+    let image = read_float_image(&path).expect("HDR image should decode");
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!((image.width, image.height), (4, 4));
+    assert_eq!(image.channels[0].len(), 16);
+    assert!((binned_entropy(&image.channels[0], 16) - 4.0).abs() < 1e-9);
+    assert_eq!(binned_entropy(&image.channels[1], 16), 0.0);
+}
@@ -0,0 +1,38 @@
+use std::io::Write;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use pictropy::icc::read_icc_profile;
+
+/// A PNG signature followed by an iCCP chunk holding `profile`, deflated. The CRC isn't checked.
+fn png_with_iccp(profile: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(profile).unwrap();
+    let mut chunk = b"profile\0\0".to_vec();
+    chunk.extend(encoder.finish().unwrap());
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    png.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+    png.extend_from_slice(b"iCCP");
+    png.extend(chunk);
+    png.extend_from_slice(&[0; 4]);
+    png
+}
+
+#[test]
+fn oversized_iccp_profiles_are_ignored() {
+    // 32 MiB of zeros deflates to a few kilobytes
+    let png = png_with_iccp(&vec![0; 32 << 20]);
+    assert!(png.len() < 64 << 10);
+    assert!(read_icc_profile(&png).is_none());
+}
+
+#[test]
+fn tag_counts_are_bounded_by_the_profile() {
+    // A header claiming four billion tags, with room for none
+    let mut profile = vec![0; 132];
+    profile[16..20].copy_from_slice(b"RGB ");
+    profile[128..132].copy_from_slice(&u32::MAX.to_be_bytes());
+    let parsed = read_icc_profile(&png_with_iccp(&profile)).expect("The header is readable");
+    assert_eq!(parsed.color_space, "RGB");
+    assert_eq!(parsed.name.as_deref(), Some("profile")); // Named after the chunk, as no desc tag was read
+}
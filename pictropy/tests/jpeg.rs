@@ -0,0 +1,299 @@
+use image::GenericImageView;
+use pictropy::jpeg::read_dct_coefficients;
+
+/// DC table of T.81 Annex K.3.1: the twelve sizes 0 to 11 under variable-length codes.
+const DC_COUNTS: [u8; 16] = [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0];
+
+/// Every baseline AC symbol: EOB in two bits, then ZRL and each run and size in nine.
+fn ac_table() -> ([u8; 16], Vec<u8>) {
+    let mut values = vec![0x00, 0xF0];
+    for run in 0..16 {
+        for size in 1..=10 {
+            values.push(run << 4 | size);
+        }
+    }
+    let mut counts = [0; 16];
+    counts[1] = 1;
+    counts[8] = values.len() as u8 - 1;
+    (counts, values)
+}
+
+/// Canonical Huffman codes and their lengths, indexed by symbol.
+fn huffman_codes(counts: &[u8; 16], values: &[u8]) -> Vec<(u32, u32)> {
+    let mut codes = vec![(0, 0); 256];
+    let mut code = 0;
+    let mut index = 0;
+    for length in 1..=16 {
+        for _ in 0..counts[length - 1] {
+            codes[values[index] as usize] = (code, length as u32);
+            code += 1;
+            index += 1;
+        }
+        code <<= 1;
+    }
+    codes
+}
+
+/// Writes entropy-coded bits with byte stuffing.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, length: u32) {
+        for shift in (0..length).rev() {
+            self.buffer = self.buffer << 1 | (value >> shift & 1);
+            self.bits += 1;
+            if self.bits == 8 {
+                self.bytes.push(self.buffer as u8);
+                if self.buffer == 0xFF {
+                    self.bytes.push(0x00);
+                }
+                self.buffer = 0;
+                self.bits = 0;
+            }
+        }
+    }
+
+    /// Pads the last byte with one bits, as T.81 F.1.2.3 asks before a marker.
+    fn flush(&mut self) {
+        while self.bits > 0 {
+            self.write(1, 1);
+        }
+    }
+
+    /// Writes a coefficient as its Huffman-coded size followed by its magnitude bits.
+    fn write_value(&mut self, codes: &[(u32, u32)], symbol_run: u8, value: i32) {
+        let size = 32 - value.unsigned_abs().leading_zeros();
+        let (code, length) = codes[(symbol_run << 4) as usize | size as usize];
+        self.write(code, length);
+        let bits = if value < 0 { value + (1 << size) - 1 } else { value };
+        self.write(bits as u32, size);
+    }
+}
+
+/// A JPEG component: id, sampling factors and its blocks in zigzag order, in the order the
+/// interleaved scan visits them.
+struct Component {
+    id: u8,
+    sampling: (u8, u8),
+    blocks: Vec<[i32; 64]>,
+}
+
+/// Encodes a baseline JPEG with a single interleaved scan, unit quantization and optional restart
+/// markers every `restart_interval` MCUs.
+fn encode(width: u16, height: u16, components: &[Component], restart_interval: u16) -> Vec<u8> {
+    let (ac_counts, ac_values) = ac_table();
+    let dc_values: Vec<u8> = (0..12).collect();
+    let dc_codes = huffman_codes(&DC_COUNTS, &dc_values);
+    let ac_codes = huffman_codes(&ac_counts, &ac_values);
+
+    let mut jpeg = vec![0xFF, 0xD8];
+    let mut segment = |marker: u8, body: &[u8]| {
+        jpeg.extend_from_slice(&[0xFF, marker]);
+        jpeg.extend_from_slice(&(body.len() as u16 + 2).to_be_bytes());
+        jpeg.extend_from_slice(body);
+    };
+
+    let mut quantization = vec![0];
+    quantization.extend_from_slice(&[1; 64]);
+    segment(0xDB, &quantization);
+
+    let mut frame = vec![8];
+    frame.extend_from_slice(&height.to_be_bytes());
+    frame.extend_from_slice(&width.to_be_bytes());
+    frame.push(components.len() as u8);
+    for component in components {
+        frame.extend_from_slice(&[component.id, component.sampling.0 << 4 | component.sampling.1, 0]);
+    }
+    segment(0xC0, &frame);
+
+    let mut tables = vec![0x00];
+    tables.extend_from_slice(&DC_COUNTS);
+    tables.extend_from_slice(&dc_values);
+    tables.push(0x10);
+    tables.extend_from_slice(&ac_counts);
+    tables.extend_from_slice(&ac_values);
+    segment(0xC4, &tables);
+
+    if restart_interval > 0 {
+        segment(0xDD, &restart_interval.to_be_bytes());
+    }
+
+    let mut scan = vec![components.len() as u8];
+    for component in components {
+        scan.extend_from_slice(&[component.id, 0x00]);
+    }
+    scan.extend_from_slice(&[0, 63, 0]);
+    segment(0xDA, &scan);
+
+    let blocks_per_mcu: Vec<usize> =
+        components.iter().map(|component| (component.sampling.0 * component.sampling.1) as usize).collect();
+    let mcu_count = components[0].blocks.len() / blocks_per_mcu[0];
+    let mut predictions = vec![0; components.len()];
+    let mut writer = BitWriter::default();
+
+    for mcu in 0..mcu_count {
+        if restart_interval > 0 && mcu > 0 && mcu % restart_interval as usize == 0 {
+            writer.flush();
+            writer.bytes.extend_from_slice(&[0xFF, 0xD0 + ((mcu / restart_interval as usize - 1) % 8) as u8]);
+            predictions.iter_mut().for_each(|prediction| *prediction = 0);
+        }
+        for (slot, component) in components.iter().enumerate() {
+            for block in &component.blocks[mcu * blocks_per_mcu[slot]..(mcu + 1) * blocks_per_mcu[slot]] {
+                writer.write_value(&dc_codes, 0, block[0] - predictions[slot]);
+                predictions[slot] = block[0];
+
+                let mut run = 0;
+                for &coefficient in &block[1..] {
+                    if coefficient == 0 {
+                        run += 1;
+                        continue;
+                    }
+                    while run > 15 {
+                        let (code, length) = ac_codes[0xF0];
+                        writer.write(code, length);
+                        run -= 16;
+                    }
+                    writer.write_value(&ac_codes, run, coefficient);
+                    run = 0;
+                }
+                if run > 0 {
+                    let (code, length) = ac_codes[0x00];
+                    writer.write(code, length);
+                }
+            }
+        }
+    }
+    writer.flush();
+
+    jpeg.extend_from_slice(&writer.bytes);
+    jpeg.extend_from_slice(&[0xFF, 0xD9]);
+    jpeg
+}
+
+/// A block with only a DC coefficient, which decodes to a flat 128 + dc / 8.
+fn flat_block(dc: i32) -> [i32; 64] {
+    let mut block = [0; 64];
+    block[0] = dc;
+    block
+}
+
+fn coefficients(blocks: &[[i32; 64]]) -> Vec<i32> {
+    blocks.concat()
+}
+
+/// Asserts the pixel decoded by the image crate at the center of each 8x8 block of a flat-block
+/// image is 128 + dc / 8, within the rounding of its IDCT.
+fn assert_block_levels(jpeg: &[u8], levels: &[(u32, u32, i32)]) {
+    let decoded = image::load_from_memory(jpeg).expect("The image crate should decode the JPEG");
+    for &(block_x, block_y, dc) in levels {
+        let pixel = decoded.get_pixel(block_x * 8 + 4, block_y * 8 + 4);
+        let expected = 128 + dc / 8;
+        assert!(
+            pixel.0[..3].iter().all(|&sample| (sample as i32 - expected).abs() <= 1),
+            "block ({}, {}) decoded to {:?}, not {}",
+            block_x,
+            block_y,
+            pixel,
+            expected
+        );
+    }
+}
+
+#[test]
+fn baseline_coefficients_are_read_in_zigzag_order() {
+    let mut first = flat_block(-96);
+    first[1] = 5;
+    first[2] = -3;
+    first[20] = 1; // After a run of 17 zeros, coded with ZRL
+    first[63] = -1;
+    let mut second = flat_block(200);
+    second[9] = 1023;
+    second[10] = -1023;
+    let blocks = vec![first, second];
+
+    let jpeg = encode(16, 8, &[Component { id: 1, sampling: (1, 1), blocks: blocks.clone() }], 0);
+    image::load_from_memory(&jpeg).expect("The image crate should decode the JPEG");
+
+    let components = read_dct_coefficients(&jpeg).unwrap();
+    assert_eq!(components.len(), 1);
+    assert_eq!(components[0].id, 1);
+    assert_eq!(components[0].coefficients, coefficients(&blocks));
+}
+
+#[test]
+fn restart_markers_reset_the_dc_prediction() {
+    let dcs = [-512, 256, 0, 64, -8, 1016, 320, -1024];
+    let blocks: Vec<[i32; 64]> = dcs.iter().map(|&dc| flat_block(dc)).collect();
+
+    // Restart after every MCU, and after every third so intervals end mid-row
+    for restart_interval in [1, 3] {
+        let jpeg = encode(32, 16, &[Component { id: 1, sampling: (1, 1), blocks: blocks.clone() }], restart_interval);
+        let components = read_dct_coefficients(&jpeg).unwrap();
+        assert_eq!(components[0].coefficients, coefficients(&blocks));
+
+        let levels: Vec<(u32, u32, i32)> = dcs.iter().enumerate().map(|(i, &dc)| (i as u32 % 4, i as u32 / 4, dc)).collect();
+        assert_block_levels(&jpeg, &levels);
+    }
+}
+
+#[test]
+fn subsampled_blocks_are_read_in_mcu_order() {
+    // 4:2:0 over 32x16: two MCUs of four luma blocks each, with one block per chroma component
+    let luma_dcs = [-400, -200, 0, 200, 400, 600, -600, 80];
+    let luma: Vec<[i32; 64]> = luma_dcs.iter().map(|&dc| flat_block(dc)).collect();
+    let mut blue_difference = vec![flat_block(0), flat_block(0)];
+    blue_difference[1][3] = 7;
+    let red_difference = vec![flat_block(0), flat_block(0)];
+
+    let jpeg = encode(
+        32,
+        16,
+        &[
+            Component { id: 1, sampling: (2, 2), blocks: luma.clone() },
+            Component { id: 2, sampling: (1, 1), blocks: blue_difference.clone() },
+            Component { id: 3, sampling: (1, 1), blocks: red_difference.clone() },
+        ],
+        0,
+    );
+    let components = read_dct_coefficients(&jpeg).unwrap();
+    assert_eq!(components.iter().map(|component| component.id).collect::<Vec<_>>(), [1, 2, 3]);
+    assert_eq!(components[0].coefficients.len(), 8 * 64);
+    assert_eq!(components[1].coefficients.len(), 2 * 64);
+    assert_eq!(components[0].coefficients, coefficients(&luma));
+    assert_eq!(components[1].coefficients, coefficients(&blue_difference));
+    assert_eq!(components[2].coefficients, coefficients(&red_difference));
+
+    // Each MCU holds its luma blocks left to right, then top to bottom. The chroma of the first
+    // MCU is flat and neutral, so its pixels are gray at the luma level.
+    assert_block_levels(
+        &jpeg,
+        &[(0, 0, luma_dcs[0]), (1, 0, luma_dcs[1]), (0, 1, luma_dcs[2]), (1, 1, luma_dcs[3])],
+    );
+}
+
+#[test]
+fn coefficient_sizes_beyond_twelve_bit_precision_are_rejected() {
+    let mut jpeg = encode(8, 8, &[Component { id: 1, sampling: (1, 1), blocks: vec![flat_block(2047)] }], 0);
+
+    // Relabel DC size 11, the one this block uses, as 40 in the Huffman table
+    let tables = jpeg.windows(2).position(|marker| marker == [0xFF, 0xC4]).unwrap();
+    let size_11 = tables + 4 + 1 + 16 + 11;
+    assert_eq!(jpeg[size_11], 11);
+    jpeg[size_11] = 40;
+
+    let error = read_dct_coefficients(&jpeg).err().expect("A 40-bit DC difference can't be read");
+    assert!(error.contains("40"), "{}", error);
+}
+
+#[test]
+fn scans_shorter_than_their_frame_are_rejected() {
+    // One block of data under a frame declaring 65535x65535 pixels
+    let jpeg = encode(65535, 65535, &[Component { id: 1, sampling: (1, 1), blocks: vec![flat_block(8)] }], 0);
+    let error = read_dct_coefficients(&jpeg).err().expect("The scan holds a single block");
+    assert!(error.contains("ends before"), "{}", error);
+}
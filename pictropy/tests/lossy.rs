@@ -0,0 +1,24 @@
+use image::{DynamicImage, Rgb, RgbImage};
+use pictropy::lossy::{best_acceptable_point, search_formats_for_size, RateDistortionPoint};
+
+#[test]
+fn the_smallest_encoding_above_the_psnr_floor_is_chosen() {
+    let points: Vec<RateDistortionPoint> = [(30, 1_000, 33.0), (60, 1_800, 38.5), (80, 2_600, 41.2), (100, 6_000, 49.0)]
+        .into_iter()
+        .map(|(quality, size, psnr)| RateDistortionPoint { quality, size, psnr })
+        .collect();
+    assert_eq!(best_acceptable_point(&points, 40.0).map(|point| point.quality), Some(80));
+    assert_eq!(best_acceptable_point(&points, 30.0).map(|point| point.quality), Some(30));
+    assert!(best_acceptable_point(&points, 50.0).is_none());
+}
+
+#[test]
+fn every_format_that_fits_is_ranked_by_psnr() {
+    let img = DynamicImage::ImageRgb8(RgbImage::from_fn(48, 32, |x, y| Rgb([(x * 5) as u8, (y * 7) as u8, ((x ^ y) * 3) as u8])));
+    let candidates = search_formats_for_size(&img, 2_000).unwrap();
+    assert!(!candidates.is_empty());
+    assert!(candidates.iter().all(|(_, point)| point.size <= 2_000));
+    assert!(candidates.windows(2).all(|pair| pair[0].1.psnr >= pair[1].1.psnr));
+
+    assert!(search_formats_for_size(&img, 10).unwrap().is_empty());
+}
@@ -0,0 +1,70 @@
+use std::io::Cursor;
+use image::{DynamicImage, ImageOutputFormat, RgbImage};
+use pictropy::metadata::{file_layout, MetadataKind};
+
+/// A little-endian EXIF block whose IFD1 points at a thumbnail of `thumbnail_size` bytes.
+fn exif_segment(thumbnail_size: usize) -> Vec<u8> {
+    let mut tiff = b"II*\0\x08\0\0\0".to_vec();
+    tiff.extend([0, 0, 14, 0, 0, 0]); // Empty IFD0, then the offset of IFD1
+    tiff.extend([2, 0]);
+    for (tag, value) in [(0x0201u16, 44u32), (0x0202, thumbnail_size as u32)] {
+        tiff.extend(tag.to_le_bytes());
+        tiff.extend([4, 0, 1, 0, 0, 0]); // One LONG
+        tiff.extend(value.to_le_bytes());
+    }
+    tiff.extend([0, 0, 0, 0]);
+    tiff.resize(tiff.len() + thumbnail_size, 0xAB);
+
+    let mut segment = vec![0xFF, 0xE1];
+    segment.extend((2 + 6 + tiff.len() as u16).to_be_bytes());
+    segment.extend(b"Exif\0\0");
+    segment.extend(tiff);
+    segment
+}
+
+#[test]
+fn jpeg_metadata_is_told_apart_from_the_image() {
+    let img = DynamicImage::ImageRgb8(RgbImage::from_fn(16, 16, |x, y| image::Rgb([x as u8 * 16, y as u8 * 16, 0])));
+    let mut jpeg = Vec::new();
+    img.write_to(&mut Cursor::new(&mut jpeg), ImageOutputFormat::Jpeg(90)).unwrap();
+    let image_size = jpeg.len();
+
+    let exif = exif_segment(100);
+    let comment = [0xFF, 0xFE, 0, 7, b'h', b'e', b'l', b'l', b'o'];
+    let mut data = jpeg[..2].to_vec();
+    data.extend(&exif);
+    data.extend(comment);
+    data.extend(&jpeg[2..]);
+    data.extend(b"trailer");
+
+    let layout = file_layout(&data).unwrap();
+    assert_eq!(layout.image_data, image_size);
+    assert_eq!(
+        layout.metadata,
+        [
+            (MetadataKind::Exif, exif.len() - 100),
+            (MetadataKind::ExifThumbnail, 100),
+            (MetadataKind::Text, comment.len()),
+            (MetadataKind::Trailing, 7),
+        ]
+    );
+    assert_eq!(layout.image_data + layout.metadata_size(), data.len());
+}
+
+#[test]
+fn png_text_chunks_count_as_metadata() {
+    let img = DynamicImage::ImageRgb8(RgbImage::new(8, 8));
+    let mut png = Vec::new();
+    img.write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png).unwrap();
+    let image_size = png.len();
+
+    let mut text = 10u32.to_be_bytes().to_vec();
+    text.extend(b"tEXtComment\0hi\0\0\0\0");
+    let mut data = png[..33].to_vec(); // Signature and IHDR
+    data.extend(&text);
+    data.extend(&png[33..]);
+
+    let layout = file_layout(&data).unwrap();
+    assert_eq!(layout.image_data, image_size);
+    assert_eq!(layout.metadata, [(MetadataKind::Text, text.len())]);
+}
@@ -0,0 +1,179 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::sync::OnceLock;
+use pictropy::fixtures::write_fixtures;
+use image::{DynamicImage, ImageBuffer, Rgb, RgbImage};
+use pictropy::{
+    analyze, check_max_pixels, gradient_entropy, image_dimensions, jpeg_block_entropy, pyramid_entropy, read_image,
+    AnalysisOptions, AnalysisResult, ChannelOrder, Compressor,
+};
+
+/// Path of a generated 64x64 RGB fixture image. The fixtures are written once per test run.
+fn fixture(name: &str) -> PathBuf {
+    static FIXTURE_DIR: OnceLock<PathBuf> = OnceLock::new();
+    let dir = FIXTURE_DIR.get_or_init(|| {
+        let dir = std::env::temp_dir().join(format!("pictropy-fixtures-{}", process::id()));
+        write_fixtures(&dir).expect("Fixtures should be written");
+        dir
+    });
+    dir.join(name)
+}
+
+/// Runs the full pipeline on a fixture, returning the results and the file size.
+fn analyze_fixture(name: &str, compressor: Compressor) -> (AnalysisResult, u64) {
+    let path = fixture(name);
+    let img = read_image(&path).expect("Fixture should decode");
+    let options = AnalysisOptions { compressor, ..AnalysisOptions::default() };
+    let result = analyze(&img, &options).expect("Fixture should analyze");
+    let file_size = fs::metadata(&path).expect("Fixture should exist").len();
+    (result, file_size)
+}
+
+#[test]
+fn solid_color_has_no_entropy() {
+    let (result, _) = analyze_fixture("solid.png", Compressor::Webp);
+    assert_eq!((result.width, result.height), (64, 64));
+    assert_eq!(result.analyzed_pixels, 64 * 64);
+    assert!(result.total_entropy.unwrap().abs() < 1e-9);
+    assert!(result.theoretical_minimum_size.unwrap().abs() < 1e-9);
+    assert!(result.compressed_size.unwrap() < 100, "WebP size {:?}", result.compressed_size);
+}
+
+#[test]
+fn gradient_entropy_matches_its_ramps() {
+    // Red and green step through 64 values each, blue is constant
+    let (result, _) = analyze_fixture("gradient.png", Compressor::Ppm);
+    let [red, green, blue] = result.channel_entropy.unwrap();
+    assert!((red - 6.0).abs() < 1e-9, "Red entropy {}", red);
+    assert!((green - 6.0).abs() < 1e-9, "Green entropy {}", green);
+    assert!(blue.abs() < 1e-9, "Blue entropy {}", blue);
+    assert!((result.theoretical_minimum_size.unwrap() - 6144.0).abs() < 1e-6);
+}
+
+#[test]
+fn noise_is_nearly_incompressible() {
+    let (result, file_size) = analyze_fixture("noise.png", Compressor::Webp);
+    let total_entropy = result.total_entropy.unwrap();
+    assert!(total_entropy > 23.5 && total_entropy <= 24.0, "Total entropy {}", total_entropy);
+
+    // Neither the entropy limit nor a real encoder should beat the file by more than a few percent
+    let savings = 1.0 - result.theoretical_minimum_size.unwrap() / file_size as f64;
+    assert!(savings < 0.05, "Entropy limit saves {:.1}%", savings * 100.0);
+    let webp_size = result.compressed_size.unwrap();
+    assert!(webp_size as f64 > file_size as f64 * 0.95, "WebP size {} for a {} byte file", webp_size, file_size);
+}
+
+#[test]
+fn noise_compresses_worse_than_structure() {
+    let (solid, _) = analyze_fixture("solid.png", Compressor::Ppm);
+    let (gradient, _) = analyze_fixture("gradient.png", Compressor::Ppm);
+    let (noise, _) = analyze_fixture("noise.png", Compressor::Ppm);
+    let sizes = [solid, gradient, noise].map(|result| result.compressed_size.unwrap());
+    assert!(sizes[0] < sizes[1] && sizes[1] < sizes[2], "PPM sizes {:?}", sizes);
+}
+
+#[test]
+fn fixtures_have_their_documented_entropy() {
+    for fixture in pictropy::fixtures::synthetic_fixtures() {
+        let (result, _) = analyze_fixture(fixture.name, Compressor::Webp);
+        let channel_entropy = result.channel_entropy.unwrap();
+        for (measured, expected) in channel_entropy.iter().zip(fixture.channel_entropy) {
+            assert!((measured - expected).abs() < 1e-9, "{}: entropy {:?}", fixture.name, channel_entropy);
+        }
+    }
+}
+
+#[test]
+fn strips_report_plain_entropy() {
+    // 64 distinct values per channel along the strip, whichever way it runs
+    let pixel = |index: u32| image::Rgb([(index * 4) as u8, (index * 4) as u8, 7]);
+    let row = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 1, |x, _| pixel(x)));
+    let column = DynamicImage::ImageRgb8(RgbImage::from_fn(1, 64, |_, y| pixel(y)));
+    for strip in [row, column] {
+        let result = analyze(&strip, &AnalysisOptions::default()).expect("Strip should analyze");
+        assert_eq!(result.analyzed_pixels, 64);
+        assert!((result.total_entropy.unwrap() - 12.0).abs() < 1e-9, "Total entropy {:?}", result.total_entropy);
+        assert!(result.compressed_size.is_some());
+    }
+}
+
+#[test]
+fn assumed_channels_regroup_the_pixels() {
+    // 48 bytes of RGB read as 4 channels are 12 pixels, not the decoded 16
+    let img = DynamicImage::ImageRgb8(RgbImage::from_fn(4, 4, |x, y| Rgb([(x * 4 + y) as u8, 0, 255])));
+    let options = AnalysisOptions { assume_channels: Some(4), ..AnalysisOptions::default() };
+    let result = analyze(&img, &options).expect("8-bit buffer should split");
+    assert_eq!(result.analyzed_pixels, 12);
+
+    let wide: ImageBuffer<Rgb<u16>, Vec<u16>> = ImageBuffer::new(4, 4);
+    let error = analyze(&DynamicImage::ImageRgb16(wide), &options).unwrap_err();
+    assert!(error.contains("16-bit"), "{}", error);
+}
+
+#[test]
+fn pyramids_stop_at_a_single_pixel() {
+    let img = DynamicImage::ImageRgb8(RgbImage::new(8, 8));
+    let sizes: Vec<u32> = pyramid_entropy(&img, usize::MAX, ChannelOrder::Rgb).iter().map(|level| level.width).collect();
+    assert_eq!(sizes, [8, 4, 2, 1]);
+}
+
+#[test]
+fn empty_images_are_rejected() {
+    for (width, height) in [(0, 5), (5, 0), (0, 0)] {
+        let empty = DynamicImage::ImageRgb8(RgbImage::new(width, height));
+        let error = analyze(&empty, &AnalysisOptions::default()).unwrap_err();
+        assert!(error.contains("no pixels"), "{}", error);
+    }
+}
+
+#[test]
+fn jpeg_blocks_follow_the_8x8_grid() {
+    // The checkerboard's 8x8 squares line up with the blocks, so every block is one color
+    let checkerboard = read_image(&fixture("checkerboard.png")).unwrap();
+    let blocks = jpeg_block_entropy(&checkerboard, ChannelOrder::Rgb);
+    assert_eq!((blocks.len(), blocks[0].len()), (8, 8));
+    assert!(blocks.iter().flatten().all(|block| block.channels().iter().all(|entropy| entropy.abs() < 1e-9)));
+
+    // Each block spans 8 of the gradient's steps in both directions: 3 bits of red and green
+    let gradient = read_image(&fixture("gradient.png")).unwrap();
+    for block in jpeg_block_entropy(&gradient, ChannelOrder::Rgb).iter().flatten() {
+        let [red, green, blue] = block.channels();
+        assert!((red - 3.0).abs() < 1e-9 && (green - 3.0).abs() < 1e-9 && blue.abs() < 1e-9);
+    }
+}
+
+#[test]
+fn gradient_entropy_rises_with_detail() {
+    let entropy = |name: &str| gradient_entropy(&read_image(&fixture(name)).unwrap());
+    assert!(entropy("solid.png").abs() < 1e-9);
+    let (checkerboard, noise) = (entropy("checkerboard.png"), entropy("noise.png"));
+    assert!(checkerboard > 0.0 && checkerboard < noise, "checkerboard {} vs noise {}", checkerboard, noise);
+}
+
+#[test]
+fn truncated_files_are_told_apart_from_non_images() {
+    let data = fs::read(fixture("gradient.png")).unwrap();
+    let truncated = fixture("truncated.png");
+    fs::write(&truncated, &data[..data.len() / 2]).unwrap();
+    let message = read_image(&truncated).unwrap_err();
+    assert!(message.contains("truncated PNG"), "{}", message);
+
+    let text = fixture("text.png");
+    fs::write(&text, "not an image").unwrap();
+    let message = read_image(&text).unwrap_err();
+    assert!(message.contains("not an image"), "{}", message);
+}
+
+#[test]
+fn pixel_limit_reads_misnamed_headers_and_fails_closed() {
+    let misnamed = fixture("misnamed.bin");
+    fs::copy(fixture("gradient.png"), &misnamed).unwrap();
+    assert_eq!(image_dimensions(&misnamed), Some((64, 64)));
+    assert!(check_max_pixels("misnamed.bin", image_dimensions(&misnamed), 10).is_err());
+    assert!(check_max_pixels("misnamed.bin", image_dimensions(&misnamed), 64 * 64).is_ok());
+
+    let text = fixture("header.png");
+    fs::write(&text, "not an image").unwrap();
+    assert!(check_max_pixels("header.png", image_dimensions(&text), u64::MAX).is_err());
+}
@@ -0,0 +1,43 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use pictropy::encode::{encode_png_color_types, PngColorType};
+
+/// A 12x10 image of four gray levels, with a translucent corner when `translucent` is set.
+fn gray_levels(translucent: bool) -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(12, 10, |x, y| {
+        let level = ((x / 3 + y / 5) * 60) as u8;
+        let alpha = if translucent && x < 2 && y < 2 { 128 } else { 255 };
+        Rgba([level, level, level, alpha])
+    }))
+}
+
+#[test]
+fn every_color_type_decodes_to_the_same_pixels() {
+    for img in [gray_levels(false), gray_levels(true)] {
+        for (color_type, data) in encode_png_color_types(&img).unwrap() {
+            let decoded = image::load_from_memory(&data).unwrap();
+            assert_eq!(decoded.to_rgba8(), img.to_rgba8(), "{} changed the pixels", color_type.name());
+        }
+    }
+}
+
+#[test]
+fn only_viable_color_types_are_tried() {
+    let color_types = |img: &DynamicImage| -> Vec<PngColorType> {
+        encode_png_color_types(img).unwrap().into_iter().map(|(color_type, _)| color_type).collect()
+    };
+    assert_eq!(color_types(&gray_levels(false)), [
+        PngColorType::Indexed,
+        PngColorType::Grayscale,
+        PngColorType::GrayscaleAlpha,
+        PngColorType::Truecolor,
+        PngColorType::TruecolorAlpha,
+    ]);
+    assert_eq!(color_types(&gray_levels(true)), [
+        PngColorType::Indexed,
+        PngColorType::GrayscaleAlpha,
+        PngColorType::TruecolorAlpha,
+    ]);
+
+    let colorful = DynamicImage::ImageRgba8(RgbaImage::from_fn(32, 32, |x, y| Rgba([(x * 8) as u8, (y * 8) as u8, 0, 255])));
+    assert_eq!(color_types(&colorful), [PngColorType::Truecolor, PngColorType::TruecolorAlpha]);
+}
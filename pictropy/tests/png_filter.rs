@@ -0,0 +1,87 @@
+use std::io::Write;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use image::{DynamicImage, ImageOutputFormat, RgbImage};
+use pictropy::png::{filter_scanlines, read_idat_stream, PngFilter};
+
+/// Reverses a filtered stream the way a PNG decoder does, using each row's filter-type byte.
+fn unfilter(stream: &[u8], row_bytes: usize, bytes_per_pixel: usize) -> Vec<u8> {
+    let mut samples: Vec<u8> = Vec::new();
+    for (y, row) in stream.chunks_exact(row_bytes + 1).enumerate() {
+        for (i, &residual) in row[1..].iter().enumerate() {
+            let left = if i >= bytes_per_pixel { samples[y * row_bytes + i - bytes_per_pixel] } else { 0 };
+            let up = if y > 0 { samples[(y - 1) * row_bytes + i] } else { 0 };
+            let upper_left = if y > 0 && i >= bytes_per_pixel { samples[(y - 1) * row_bytes + i - bytes_per_pixel] } else { 0 };
+            let prediction = match row[0] {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                _ => {
+                    let estimate = left as i16 + up as i16 - upper_left as i16;
+                    let distances = [left, up, upper_left].map(|byte| (estimate - byte as i16).abs());
+                    if distances[0] <= distances[1] && distances[0] <= distances[2] {
+                        left
+                    } else if distances[1] <= distances[2] {
+                        up
+                    } else {
+                        upper_left
+                    }
+                },
+            };
+            samples.push(residual.wrapping_add(prediction));
+        }
+    }
+    samples
+}
+
+#[test]
+fn every_filter_reverses_to_the_original_samples() {
+    // 7x5 RGB pixels of uneven detail
+    let samples: Vec<u8> = (0..7 * 5 * 3).map(|i: u32| (i * i % 251) as u8 ^ (i / 21) as u8).collect();
+    for filter in [PngFilter::None, PngFilter::Sub, PngFilter::Up, PngFilter::Average, PngFilter::Paeth, PngFilter::Optimal] {
+        let stream = filter_scanlines(&samples, 21, 3, filter);
+        assert_eq!(stream.len(), samples.len() + 5);
+        assert_eq!(unfilter(&stream, 21, 3), samples, "{} doesn't round-trip", filter.name());
+    }
+}
+
+#[test]
+fn optimal_picks_the_filter_that_flattens_each_row() {
+    // A horizontal ramp repeated on every row: Sub flattens the first row, Up every row after it
+    let samples: Vec<u8> = (0..4).flat_map(|_| (0..16u8).map(|x| x * 10)).collect();
+    let stream = filter_scanlines(&samples, 16, 1, PngFilter::Optimal);
+    let type_bytes: Vec<u8> = stream.iter().step_by(17).copied().collect();
+    assert_eq!(type_bytes, [1, 2, 2, 2]);
+    assert!(stream.chunks_exact(17).skip(1).all(|row| row[1..].iter().all(|&residual| residual == 0)));
+}
+
+/// Appends a PNG chunk; the CRC isn't checked, so it's left zero.
+fn push_chunk(png: &mut Vec<u8>, chunk_type: &[u8], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    png.extend_from_slice(&[0; 4]);
+}
+
+#[test]
+fn idat_streams_hold_one_filtered_row_per_scanline() {
+    let img = DynamicImage::ImageRgb8(RgbImage::from_fn(7, 5, |x, y| image::Rgb([x as u8, y as u8, 9])));
+    let mut png = Vec::new();
+    img.write_to(&mut png, ImageOutputFormat::Png).unwrap();
+    assert_eq!(read_idat_stream(&png).unwrap().len(), 5 * (7 * 3 + 1));
+}
+
+#[test]
+fn idat_streams_may_not_inflate_past_their_header() {
+    // A 1x1 grayscale header over a megabyte of image data
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(&vec![0; 1 << 20]).unwrap();
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    push_chunk(&mut png, b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0]);
+    push_chunk(&mut png, b"IDAT", &encoder.finish().unwrap());
+    push_chunk(&mut png, b"IEND", &[]);
+
+    let error = read_idat_stream(&png).unwrap_err();
+    assert!(error.contains("inflates past the 2 bytes"), "{}", error);
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::process;
+use pictropy::ppm_model::{load_models, save_models};
+use pictropy::{ppm_compress, ppm_compress_order, PpmModel, PpmWarmup, PPM_MAX_CONTEXTS, PPM_MAX_ORDER, PPM_ORDER};
+
+/// Pseudo-random bytes from a seeded linear congruential generator; nearly incompressible alone.
+fn pattern(seed: u32) -> Vec<u8> {
+    let mut state = seed;
+    (0..4096)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 24) as u8
+        })
+        .collect()
+}
+
+#[test]
+fn a_trained_model_codes_similar_data_smaller() {
+    let mut model = PpmModel::new(PPM_ORDER);
+    model.code(&pattern(0), PpmWarmup::Backoff);
+
+    let fresh_size = ppm_compress(&pattern(0), PpmWarmup::Backoff);
+    let trained_size = model.clone().code(&pattern(0), PpmWarmup::Backoff);
+    assert!(trained_size < fresh_size / 2, "trained {} vs fresh {}", trained_size, fresh_size);
+}
+
+#[test]
+fn saved_models_load_unchanged() {
+    let models = [pattern(0), pattern(1), pattern(2)].map(|data| {
+        let mut model = PpmModel::new(PPM_ORDER);
+        model.code(&data, PpmWarmup::Backoff);
+        model
+    });
+    let path = std::env::temp_dir().join(format!("pictropy-ppm-model-{}.gz", process::id()));
+    save_models(&path, &models).expect("Models should save");
+    let loaded = load_models(&path).expect("Models should load");
+    std::fs::remove_file(&path).unwrap();
+
+    for (original, loaded) in models.iter().zip(&loaded) {
+        assert_eq!(loaded.max_order(), PPM_ORDER);
+        let data = pattern(3);
+        assert_eq!(original.clone().code(&data, PpmWarmup::Backoff), loaded.clone().code(&data, PpmWarmup::Backoff));
+    }
+}
+
+/// The PPM estimate as it was with one map keyed by the context bytes themselves.
+fn vec_keyed_ppm_size(image_data: &[u8], warmup: PpmWarmup, max_order: usize) -> usize {
+    let mut context_map: HashMap<Vec<u8>, HashMap<u8, usize>> = HashMap::new();
+    let mut compressed_bits = 0;
+    let lowest_order = if warmup == PpmWarmup::Backoff { 0 } else { max_order };
+    for (i, &value) in image_data.iter().enumerate() {
+        let highest_order = max_order.min(i);
+        if highest_order >= lowest_order {
+            let mut bits = 0.0;
+            let mut coded = false;
+            for order in (lowest_order..=highest_order).rev() {
+                let Some(context_freq) = context_map.get(&image_data[i - order..i]) else {
+                    continue;
+                };
+                let denominator = (context_freq.values().sum::<usize>() + context_freq.len()) as f64;
+                if let Some(&count) = context_freq.get(&value) {
+                    bits -= (count as f64 / denominator).log2();
+                    coded = true;
+                    break;
+                }
+                bits -= (context_freq.len() as f64 / denominator).log2();
+            }
+            if !coded {
+                bits += 8.0;
+            }
+            compressed_bits += bits.ceil() as usize;
+        }
+        for order in lowest_order..=highest_order {
+            let context = &image_data[i - order..i];
+            let full = context_map.len() >= PPM_MAX_CONTEXTS;
+            match context_map.get_mut(context) {
+                Some(context_freq) => *context_freq.entry(value).or_insert(0) += 1,
+                None if !full => {
+                    context_map.entry(context.to_vec()).or_default().insert(value, 1);
+                },
+                None => {},
+            }
+        }
+    }
+    compressed_bits.div_ceil(8)
+}
+
+#[test]
+fn packed_context_keys_match_the_vec_keyed_estimate() {
+    // Low-valued bytes make contexts like [0, 7] and [7] common, which must stay distinct
+    let skewed: Vec<u8> = pattern(4)[..2048].iter().map(|&value| value % 9).collect();
+    for data in [pattern(5)[..2048].to_vec(), skewed] {
+        for max_order in 0..=PPM_MAX_ORDER {
+            for warmup in [PpmWarmup::Backoff, PpmWarmup::Exclude] {
+                assert_eq!(
+                    ppm_compress_order(&data, warmup, max_order),
+                    vec_keyed_ppm_size(&data, warmup, max_order),
+                    "order {} with {:?} warmup",
+                    max_order,
+                    warmup
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn ppm_size_is_in_bytes() {
+    // Every byte is new: 8 bits for the first, then an order-0 escape of 1 bit and 8 bits for each other
+    let ramp: Vec<u8> = (0..=255).collect();
+    assert_eq!(ppm_compress(&ramp, PpmWarmup::Backoff), (8 + 255 * 9_usize).div_ceil(8));
+}
@@ -0,0 +1,35 @@
+use image::{DynamicImage, Rgb, RgbImage};
+use pictropy::lossy::psnr;
+use pictropy::quantize::median_cut;
+
+/// A 32x32 image of four flat quadrants.
+fn quadrants() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(32, 32, |x, y| match (x < 16, y < 16) {
+        (true, true) => Rgb([255, 0, 0]),
+        (false, true) => Rgb([0, 255, 0]),
+        (true, false) => Rgb([0, 0, 255]),
+        (false, false) => Rgb([250, 250, 250]),
+    }))
+}
+
+#[test]
+fn median_cut_keeps_images_that_already_fit_the_palette() {
+    let img = quadrants();
+    let reduction = median_cut(&img, 16);
+    assert_eq!(reduction.palette.len(), 4);
+    assert_eq!(reduction.indices.len(), 32 * 32);
+    assert_eq!(reduction.to_image(32, 32).to_rgb8(), img.to_rgb8());
+}
+
+#[test]
+fn larger_palettes_stay_closer_to_the_original() {
+    let img = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 64, |x, y| Rgb([(x * 4) as u8, (y * 4) as u8, ((x + y) * 2) as u8])));
+    let mut last_psnr = 0.0;
+    for colors in [4, 16, 64, 256] {
+        let reduction = median_cut(&img, colors);
+        assert_eq!(reduction.palette.len(), colors);
+        let psnr = psnr(&img, &reduction.to_image(64, 64));
+        assert!(psnr > last_psnr, "{} colors: {:.2} dB after {:.2} dB", colors, psnr, last_psnr);
+        last_psnr = psnr;
+    }
+}
@@ -0,0 +1,50 @@
+use pictropy::recommend::{recommend, LossyOption, Metrics};
+
+/// Metrics for a 10,000 byte file that no rule acts on.
+fn baseline() -> Metrics {
+    Metrics {
+        file_size: 10_000,
+        unique_colors: None,
+        entropy_limit: Some(9_000.0),
+        best_lossless: Some(("QOI", 9_500)),
+        has_alpha: false,
+        lossy: Some(LossyOption { format: "WebP", quality: 90, size: 8_000, psnr: 42.0 }),
+    }
+}
+
+#[test]
+fn rules_apply_in_order_of_preference() {
+    let metrics = Metrics {
+        unique_colors: Some(12),
+        best_lossless: Some(("lossless WebP", 6_000)),
+        lossy: Some(LossyOption { format: "WebP", quality: 70, size: 2_000, psnr: 41.0 }),
+        ..baseline()
+    };
+    let recommendation = recommend(&metrics);
+    assert_eq!(recommendation.advice.len(), 3);
+    assert!(recommendation.advice[0].contains("indexed PNG"));
+    assert!(recommendation.advice[1].contains("lossless WebP for 40%"));
+    assert!(recommendation.advice[2].contains("lossy WebP q70 for 80%"));
+    assert_eq!(recommendation.evidence.len(), 4);
+}
+
+#[test]
+fn small_savings_keep_the_file() {
+    let recommendation = recommend(&baseline());
+    assert_eq!(recommendation.advice, ["Keep as-is; no option saves enough to be worth it."]);
+
+    let incompressible = recommend(&Metrics { entropy_limit: Some(9_800.0), ..baseline() });
+    assert_eq!(incompressible.advice, ["Already near-incompressible; keep as-is."]);
+}
+
+#[test]
+fn transparent_images_get_no_lossy_advice() {
+    let metrics = Metrics {
+        has_alpha: true,
+        lossy: None,
+        ..baseline()
+    };
+    let recommendation = recommend(&metrics);
+    assert!(recommendation.evidence.iter().any(|line| line.contains("transparency")));
+    assert!(recommendation.advice.iter().all(|advice| !advice.contains("lossy")));
+}
@@ -0,0 +1,60 @@
+use std::fs::File;
+use std::path::PathBuf;
+use std::process;
+use image::{DynamicImage, ImageBuffer, Rgba, RgbImage};
+use pictropy::read_image;
+use pictropy::signed::read_signed_image;
+use tiff::encoder::{colortype, TiffEncoder};
+
+/// Writes a 4x4 grayscale TIFF of signed 16-bit samples from -8 to 7, tagged as signed.
+fn signed_tiff(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("pictropy-signed-{}-{}.tif", name, process::id()));
+    let samples: Vec<i16> = (-8..8).collect();
+    TiffEncoder::new(File::create(&path).unwrap())
+        .unwrap()
+        .write_image::<colortype::GrayI16>(4, 4, &samples)
+        .unwrap();
+    path
+}
+
+#[test]
+fn signed_samples_are_offset_into_value_order() {
+    let path = signed_tiff("offset");
+    let image = read_signed_image(&path).expect("Signed TIFF should decode");
+    std::fs::remove_file(&path).unwrap();
+    let DynamicImage::ImageLuma16(image) = image else {
+        panic!("Expected 16-bit grayscale");
+    };
+    let expected: Vec<u16> = (32760..32776).collect();
+    assert_eq!(image.into_raw(), expected);
+}
+
+#[test]
+fn signed_tiffs_are_pointed_to_the_flag() {
+    let path = signed_tiff("detect");
+    let error = read_image(&path).expect_err("The image crate doesn't read signed samples");
+    std::fs::remove_file(&path).unwrap();
+    assert!(error.contains("--signed"), "{}", error);
+}
+
+#[test]
+fn eight_bit_images_are_rejected() {
+    let path = std::env::temp_dir().join(format!("pictropy-signed-8-bit-{}.png", process::id()));
+    DynamicImage::ImageRgb8(RgbImage::new(4, 4)).save(&path).unwrap();
+    let error = read_signed_image(&path).expect_err("8-bit samples have no sign to reinterpret");
+    std::fs::remove_file(&path).unwrap();
+    assert!(error.contains("8-bit"), "{}", error);
+}
+
+#[test]
+fn alpha_is_not_offset() {
+    let path = std::env::temp_dir().join(format!("pictropy-signed-alpha-{}.png", process::id()));
+    let image: ImageBuffer<Rgba<u16>, Vec<u16>> = ImageBuffer::from_pixel(2, 2, Rgba([0x0000, 0x7FFF, 0xFFFF, 0xFFFF]));
+    DynamicImage::ImageRgba16(image).save(&path).unwrap();
+    let image = read_signed_image(&path).expect("16-bit PNG should decode");
+    std::fs::remove_file(&path).unwrap();
+    let DynamicImage::ImageRgba16(image) = image else {
+        panic!("Expected 16-bit RGBA");
+    };
+    assert!(image.pixels().all(|pixel| pixel.0 == [0x8000, 0xFFFF, 0x7FFF, 0xFFFF]));
+}
@@ -0,0 +1,51 @@
+use image::{DynamicImage, GenericImageView, ImageBuffer, Luma, Rgb, RgbImage};
+use pictropy::{webp_encode_lossless, WEBP_MAX_DIMENSION};
+
+/// Builds a small two-color checkerboard with 2x2 squares.
+fn checkerboard() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(16, 16, |x, y| {
+        if (x / 2 + y / 2) % 2 == 0 {
+            Rgb([255, 255, 255])
+        } else {
+            Rgb([12, 34, 56])
+        }
+    }))
+}
+
+#[test]
+fn webp_lossless_round_trips_pixel_identical() {
+    let original = checkerboard();
+    let encoded = webp_encode_lossless(&original).expect("Checkerboard should encode");
+
+    let decoded = webp::Decoder::new(&encoded)
+        .decode()
+        .expect("Lossless WebP output should decode")
+        .to_image();
+
+    assert_eq!(decoded.dimensions(), original.dimensions());
+    for ((x, y, expected), (_, _, actual)) in original.pixels().zip(decoded.pixels()) {
+        assert_eq!(expected, actual, "Pixel ({}, {}) changed after the WebP round trip", x, y);
+    }
+}
+
+#[test]
+fn images_wider_than_webp_allows_are_rejected() {
+    let too_wide = DynamicImage::ImageRgb8(RgbImage::new(WEBP_MAX_DIMENSION + 1, 1));
+    let error = webp_encode_lossless(&too_wide).expect_err("WebP cannot store the image");
+    assert!(error.contains("maximum dimension"), "{}", error);
+
+    let widest = DynamicImage::ImageRgb8(RgbImage::new(WEBP_MAX_DIMENSION, 1));
+    assert!(webp_encode_lossless(&widest).is_ok());
+}
+
+#[test]
+fn sixteen_bit_images_are_rounded_to_8_bits() {
+    // 0x00FF and 0x7FFF would truncate to 0 and 127; only the first is nearer the next step up
+    let samples = [0, 0x00FF, 0x7FFF, 0xFFFF];
+    let gray: ImageBuffer<Luma<u16>, Vec<u16>> = ImageBuffer::from_fn(4, 1, |x, _| Luma([samples[x as usize]]));
+    let encoded = webp_encode_lossless(&DynamicImage::ImageLuma16(gray)).expect("16-bit images should encode");
+
+    let decoded = webp::Decoder::new(&encoded).decode().expect("Lossless WebP output should decode").to_image();
+    let levels: Vec<u8> = decoded.to_rgb8().pixels().map(|pixel| pixel[0]).collect();
+    assert_eq!(levels, [0, 1, 127, 255]);
+}